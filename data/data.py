@@ -1,3 +1,4 @@
+import argparse
 import json
 import logging
 import math
@@ -7,6 +8,14 @@ from typing import Any
 from astroquery.jplhorizons import Horizons, HorizonsClass
 from astropy import units as u
 
+parser = argparse.ArgumentParser(description="Fetches our data from JPL Horizons into compiled_data.json.")
+parser.add_argument(
+    "--validation",
+    action="store_true",
+    help="also fetch the reference vectors of the validation tests into validation/, replacing the generated ones",
+)
+args = parser.parse_args()
+
 logging.basicConfig(
     level=logging.WARNING, format="\033[93m%(levelname)s: %(message)s\033[0m"
 )
@@ -43,6 +52,7 @@ bodies = (
         ],
     ),
     (
+        # For asteroids in bulk, import an MPC orbit file on startup instead (`mpcorb <path>`)
         "smallbody",  # dwarf planets, asteroids
         [
             ("999", []),  # Pluto
@@ -243,7 +253,7 @@ def get_geophysical_data(text: str):
         # The first value *should* have been what we want.
 
         # Note: small bodies such as Ceres, Eris, etc. do not seem to have these properties listed.
-        if "name" in data and data["name"].lower() == "sun":
+        if "name" in metadata and metadata["name"].lower() == "sun":
             data["mass"] = (1 * u.M_sun).value  # type: ignore
         else:
             mass = get_mass(line)
@@ -368,10 +378,13 @@ def attempt_to_fill_missing_data(data: dict[str, Any], name: str):
     return data
 
 
+# 2460676.5: 2025-01-01 00:00:00 TDB
+EPOCH = 2460676.5
+
+
 def get_data(id: str | int):
-    # 2460676.5: 2025-01-01 00:00:00 TDB
     # location="500@10" -> use the Sun as the center
-    body = Horizons(id=id, epochs=2460676.5, location="500@10")
+    body = Horizons(id=id, epochs=EPOCH, location="500@10")
 
     vector_data = get_initial_vectors(body.vectors_async().text)  # type: ignore
     body_data, body_metadata = get_geophysical_data(body.ephemerides_async().text)  # type: ignore
@@ -431,5 +444,39 @@ for body_type, body_list in bodies:
         print(f"{json.dumps(__data__["data"], indent=4)}")
 
 
+# `cargo run --bin solar-system-data` compiles the saved responses into the same file without a network connection,
+# and reads more of the physical data than the regexes above. Run it after fetching new responses.
 with open(os.path.join(os.path.dirname(__file__), "compiled_data.json"), "w") as f:
-    f.write(json.dumps(all_bodies_data, indent=4))
+    f.write(json.dumps({"epoch": EPOCH, "bodies": all_bodies_data}, indent=4))
+
+
+# Reference vectors for the validation tests, at some time after EPOCH.
+# Moons are relative to their planet, everything else to the Sun.
+VALIDATION_EPOCHS = [EPOCH + 30, EPOCH + 365.25]
+
+
+def write_validation_vectors(id: str, center: str):
+    path = os.path.join(os.path.dirname(__file__), "validation")
+
+    if not os.path.exists(path):
+        os.mkdir(path)
+
+    body = Horizons(id=id, epochs=VALIDATION_EPOCHS, location=f"500@{center}")
+    vectors = body.vectors_async()
+
+    with open(os.path.join(path, f"{id}.txt"), "w") as f:
+        f.write(f"VECTORS REQUEST URL: {vectors.url}\n\n")  # type: ignore
+        f.write(vectors.text)  # type: ignore
+
+
+if args.validation:
+    for body_type, body_list in bodies:
+        for body in body_list:
+            id = body[0]
+            satellites = body[1]
+
+            if id != "10":
+                write_validation_vectors(id, "10")
+
+            for satellite in satellites:
+                write_validation_vectors(satellite, id)
@@ -0,0 +1,133 @@
+"""Writes `ephemeris.bsp`, a small SPK kernel laid out like DE440s, for the tests of our SPK reader.
+
+It is not cut from a JPL kernel, but built the way they are: the Horizons states our data was compiled from
+are integrated like `../validation/make_references.py` does, and fitted with type 2 Chebyshev segments in the
+J2000 frame. Like in DE440s, the Sun and the Earth-Moon barycenter are relative to the solar system barycenter,
+the Earth and the Moon to the Earth-Moon barycenter, with records of the same length and degree.
+"""
+
+import math
+import os
+import sys
+
+HERE = os.path.dirname(__file__)
+sys.path.insert(0, os.path.join(HERE, "..", "validation"))
+
+from make_fixture import DAY, write_kernel  # noqa: E402
+from make_references import ASTRONOMICAL_UNIT, G, integrate, load  # noqa: E402
+
+# Days of coverage either side of the epoch of our data
+COVERAGE = 16.0
+
+# Days per integration step
+STEP = 0.002
+
+KILOMETER = 1000.0
+
+# Obliquity of the ecliptic at J2000, as in `src/spk/mod.rs`
+OBLIQUITY_J2000 = math.radians(84381.448 / 3600.0)
+
+# (name, target, center, days per record, coefficients per component)
+SEGMENTS = [
+    ("SUN", 10, 0, 16.0, 11),
+    ("EARTH-MOON BARYCENTER", 3, 0, 16.0, 13),
+    ("EARTH", 399, 3, 4.0, 13),
+    ("MOON", 301, 3, 4.0, 13),
+]
+
+
+def states_at(bodies, days):
+    """Positions at each of `days`, which may be before the epoch as well."""
+    positions = {}
+
+    after = sorted(d for d in days if d >= 0.0)
+    for day, (p, _) in zip(after, integrate(bodies, after, STEP, G)):
+        positions[day] = p
+
+    # Backwards in time is forwards with the velocities reversed
+    reversed_bodies = [(*b[:5], [-v for v in b[5]]) for b in bodies]
+    before = sorted(-d for d in days if d < 0.0)
+    for day, (p, _) in zip(before, integrate(reversed_bodies, before, STEP, G)):
+        positions[-day] = p
+
+    return positions
+
+
+def ecliptic_to_equatorial(v):
+    sin, cos = math.sin(OBLIQUITY_J2000), math.cos(OBLIQUITY_J2000)
+
+    return [v[0], cos * v[1] - sin * v[2], sin * v[1] + cos * v[2]]
+
+
+def nodes(count):
+    """Where the Chebyshev interpolant of `count` coefficients is fitted, in [-1, 1]."""
+    return [math.cos(math.pi * (j + 0.5) / count) for j in range(count)]
+
+
+def fit(values, count):
+    """Chebyshev coefficients of the polynomial through `values` at the `nodes`."""
+    x = nodes(count)
+
+    return [
+        (1.0 if k == 0 else 2.0) / count * sum(v * math.cos(k * math.acos(xj)) for v, xj in zip(values, x))
+        for k in range(count)
+    ]
+
+
+def main():
+    epoch, bodies = load(rounded=float)
+    ids = [b[1] for b in bodies]
+    masses = [b[3] for b in bodies]
+    earth, moon = ids.index(399), ids.index(301)
+
+    def barycenter(positions, indices):
+        total = sum(masses[i] for i in indices)
+        return [sum(masses[i] * positions[i][k] for i in indices) / total for k in range(3)]
+
+    def state(positions, target, center):
+        """Position of a NAIF ID relative to another, AU in the ecliptic frame."""
+
+        def absolute(id):
+            match id:
+                case 0:
+                    return barycenter(positions, range(len(bodies)))
+                case 3:
+                    return barycenter(positions, [earth, moon])
+                case _:
+                    return positions[ids.index(id)]
+
+        return [a - b for a, b in zip(absolute(target), absolute(center))]
+
+    records = {}
+    times = set()
+
+    for name, _, _, length, count in SEGMENTS:
+        middles = [-COVERAGE + length * (i + 0.5) for i in range(round(2 * COVERAGE / length))]
+        records[name] = [(middle, [middle + x * length / 2 for x in nodes(count)]) for middle in middles]
+        times.update(t for _, days in records[name] for t in days)
+
+    positions = states_at(bodies, times)
+
+    et0 = (epoch - 2451545.0) * DAY
+    segments = []
+
+    for name, target, center, length, count in SEGMENTS:
+        fitted = []
+
+        for _, days in records[name]:
+            km = [
+                [x * ASTRONOMICAL_UNIT / KILOMETER for x in ecliptic_to_equatorial(state(positions[t], target, center))]
+                for t in days
+            ]
+            fitted.append([fit([p[k] for p in km], count) for k in range(3)])
+
+        start = et0 - COVERAGE * DAY
+        segments.append(
+            (name, target, center, 1, 2, start, et0 + COVERAGE * DAY, start, length * DAY, fitted)
+        )
+
+    write_kernel(os.path.join(HERE, "ephemeris.bsp"), f"solar-system ephemeris fixture, JD {epoch}", segments)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,116 @@
+"""Writes `fixture.bsp`, a tiny SPK kernel for the tests of our SPK reader.
+
+The segments hold made up Chebyshev coefficients, not real ephemerides, so the tests can
+check the evaluation against the polynomials directly. Keep them in sync with `src/spk/mod.rs`.
+"""
+
+import os
+import struct
+
+RECORD = 1024
+ND, NI = 2, 6
+SUMMARY_SIZE = ND + (NI + 1) // 2  # in doubles
+
+J2000 = 2451545.0
+DAY = 86400.0
+
+# 2025-01-01 00:00:00 TDB, the epoch of our data, in seconds past J2000
+ET0 = (2460676.5 - J2000) * DAY
+
+# (name, target, center, frame, type, start, end, init, interval, records)
+# Frame 1 is J2000 (equatorial), 17 is ECLIPJ2000.
+# Type 2 records hold X, Y, Z coefficients, type 3 records also VX, VY, VZ.
+SEGMENTS = [
+    (
+        "EMB",
+        3, 0, 1, 2,
+        ET0, ET0 + 4 * DAY, ET0, 2 * DAY,
+        [
+            [[-2.6e7, 1.3e6, 2.0e3], [1.3e8, 2.5e6, -1.0e3], [5.6e7, 1.1e6, 500.0]],
+            [[-2.5e7, 1.3e6, 2.0e3], [1.31e8, 2.4e6, -1.0e3], [5.65e7, 1.0e6, 500.0]],
+        ],
+    ),
+    (
+        "EARTH",
+        399, 3, 17, 3,
+        ET0, ET0 + 4 * DAY, ET0, 4 * DAY,
+        [
+            [[4000.0, 100.0], [-2000.0, 50.0], [10.0, 1.0], [0.01, 0.001], [0.02, 0.0], [0.0, 0.0]],
+        ],
+    ),
+    (
+        "SUN",
+        10, 0, 1, 2,
+        ET0, ET0 + 4 * DAY, ET0, 4 * DAY,
+        [
+            [[-1.0e6], [5.0e5], [2.0e5]],
+        ],
+    ),
+    # Overrides EARTH for its second half, later segments take precedence
+    (
+        "EARTH OVERRIDE",
+        399, 3, 17, 2,
+        ET0 + 2 * DAY, ET0 + 4 * DAY, ET0 + 2 * DAY, 2 * DAY,
+        [
+            [[1.0], [2.0], [3.0]],
+        ],
+    ),
+]
+
+
+def segment_data(init, interval, records):
+    data = []
+    coefficients = len(records[0][0])
+
+    for i, record in enumerate(records):
+        data += [init + interval * (i + 0.5), interval / 2]
+
+        for component in record:
+            data += component
+
+    record_size = 2 + len(records[0]) * coefficients
+
+    return data + [init, interval, float(record_size), float(len(records))]
+
+
+def write_kernel(path, internal_name, segments):
+    """Writes `segments`, as in `SEGMENTS`, to a little endian SPK kernel at `path`."""
+    data = []
+    summaries = []
+
+    # The file record, one summary record and one name record come first
+    address = 3 * RECORD // 8 + 1
+
+    for name, target, center, frame, type, start, end, init, interval, records in segments:
+        words = segment_data(init, interval, records)
+        summaries.append((name, start, end, target, center, frame, type, address, address + len(words) - 1))
+        data += words
+        address += len(words)
+
+    file_record = bytearray(RECORD)
+    file_record[0:8] = b"DAF/SPK "
+    struct.pack_into("<ii", file_record, 8, ND, NI)
+    file_record[16:76] = internal_name.encode().ljust(60)
+    struct.pack_into("<iii", file_record, 76, 2, 2, address)
+    file_record[88:96] = b"LTL-IEEE"
+    file_record[699:727] = b"FTPSTR:\r:\n:\r\n:\r\x00:\x81:\x10\xce:ENDFTP"
+
+    summary_record = bytearray(RECORD)
+    struct.pack_into("<ddd", summary_record, 0, 0.0, 0.0, float(len(summaries)))
+
+    name_record = bytearray(RECORD)
+
+    for i, (name, start, end, *integers) in enumerate(summaries):
+        offset = 24 + i * SUMMARY_SIZE * 8
+        struct.pack_into("<dd6i", summary_record, offset, start, end, *integers)
+        name_record[i * SUMMARY_SIZE * 8:(i + 1) * SUMMARY_SIZE * 8] = name.encode().ljust(SUMMARY_SIZE * 8)
+
+    body = struct.pack(f"<{len(data)}d", *data)
+    body += bytes(-len(body) % RECORD)
+
+    with open(path, "wb") as f:
+        f.write(file_record + summary_record + name_record + body)
+
+
+if __name__ == "__main__":
+    write_kernel(os.path.join(os.path.dirname(__file__), "fixture.bsp"), "solar-system SPK test fixture", SEGMENTS)
@@ -0,0 +1,206 @@
+"""Writes reference vectors for the validation tests, one Horizons style vector table per body.
+
+These are not fetched from Horizons: we integrate the Horizons states our data was compiled from
+(`../compiled_data.json`) with point masses in double precision and a small RK4 step, independently of
+the simulation. That checks our integrators against a converged solution of the same model, without
+a network connection. The states are rounded to single precision first, like the simulation loads them,
+which moves the innermost moons by up to 6e-4 of their distance and would dephase them within a year. `data.py --validation` replaces them with the real vectors from Horizons,
+which also include what we do not model, like the oblateness of the planets.
+
+Moons are relative to their planet, everything else to the Sun, like `data.py` fetches them.
+"""
+
+import datetime
+import json
+import math
+import os
+import struct
+import sys
+
+HERE = os.path.dirname(__file__)
+
+# Days after the epoch of our data, like `VALIDATION_EPOCHS` in `data.py`
+OFFSETS = [30.0, 365.25]
+
+# Days per step. `--check` shows how much the positions change with twice the step: less than 1e-5 of their
+# distance, apart from the moons that go around in less than a day, which the tests only keep in their orbits.
+STEP = 0.001
+
+# m^3 / (kg s^2), kg, m, s, as in `src/simulation/units.rs`
+GRAVITATIONAL_CONSTANT = 6.6743e-11
+SOLAR_MASS = 1.98841e30
+ASTRONOMICAL_UNIT = 149597870700.0
+DAY = 86400.0
+
+
+def single(value):
+    """`value` rounded to single precision, like an `f32`."""
+    return struct.unpack("f", struct.pack("f", value))[0]
+
+
+# AU^3 / (Solar Mass day^2)
+G = GRAVITATIONAL_CONSTANT * SOLAR_MASS * DAY**2 / ASTRONOMICAL_UNIT**3
+
+
+def load(rounded=single):
+    """The epoch and the bodies of our data, with every number passed through `rounded`."""
+    with open(os.path.join(HERE, "..", "compiled_data.json")) as f:
+        data = json.load(f)
+
+    # (name, id, center index, mass, position, velocity)
+    bodies = []
+
+    def add(body, center):
+        d = body["data"]
+        position = [rounded(d["position"][k]) for k in "xyz"]
+        velocity = [rounded(d["velocity"][k]) for k in "xyz"]
+        mass = rounded(d["mass"])
+        bodies.append((body["metadata"]["name"], body["metadata"]["id"], center, mass, position, velocity))
+
+    for body in data["bodies"]:
+        add(body, 0 if bodies else None)
+        parent = len(bodies) - 1
+
+        for satellite in body.get("satellites") or []:
+            add(satellite, parent)
+
+    return data["epoch"], bodies
+
+
+def accelerations(positions, gm):
+    n = len(positions)
+    result = [[0.0, 0.0, 0.0] for _ in range(n)]
+
+    for i in range(n):
+        xi, yi, zi = positions[i]
+        ai = result[i]
+
+        for j in range(i + 1, n):
+            dx = positions[j][0] - xi
+            dy = positions[j][1] - yi
+            dz = positions[j][2] - zi
+            r2 = dx * dx + dy * dy + dz * dz
+            inverse = 1.0 / (r2 * math.sqrt(r2))
+
+            s = gm[j] * inverse
+            ai[0] += dx * s
+            ai[1] += dy * s
+            ai[2] += dz * s
+
+            s = gm[i] * inverse
+            aj = result[j]
+            aj[0] -= dx * s
+            aj[1] -= dy * s
+            aj[2] -= dz * s
+
+    return result
+
+
+def step(positions, velocities, gm, h):
+    def moved(base, rate, fraction):
+        return [[b[k] + r[k] * h * fraction for k in range(3)] for b, r in zip(base, rate)]
+
+    k1v, k1a = velocities, accelerations(positions, gm)
+    k2v = moved(velocities, k1a, 0.5)
+    k2a = accelerations(moved(positions, k1v, 0.5), gm)
+    k3v = moved(velocities, k2a, 0.5)
+    k3a = accelerations(moved(positions, k2v, 0.5), gm)
+    k4v = moved(velocities, k3a, 1.0)
+    k4a = accelerations(moved(positions, k3v, 1.0), gm)
+
+    def combine(base, k1, k2, k3, k4):
+        return [
+            [b[k] + (k1[i][k] + 2.0 * k2[i][k] + 2.0 * k3[i][k] + k4[i][k]) * h / 6.0 for k in range(3)]
+            for i, b in enumerate(base)
+        ]
+
+    return combine(positions, k1v, k2v, k3v, k4v), combine(velocities, k1a, k2a, k3a, k4a)
+
+
+def integrate(bodies, days, h, g):
+    """States after each of `days`, which have to be increasing, with the gravitational constant `g`."""
+    positions = [b[4] for b in bodies]
+    velocities = [b[5] for b in bodies]
+    gm = [g * b[3] for b in bodies]
+
+    states = []
+    elapsed = 0.0
+
+    for target in days:
+        count = round((target - elapsed) / h)
+        last = (target - elapsed) - count * h
+
+        for _ in range(count):
+            positions, velocities = step(positions, velocities, gm, h)
+
+        # Land exactly on the target
+        if abs(last) > 0.0:
+            positions, velocities = step(positions, velocities, gm, last)
+
+        elapsed = target
+        states.append((positions, velocities))
+
+    return states
+
+
+def calendar(julian_date):
+    date = datetime.datetime(2000, 1, 1, 12) + datetime.timedelta(days=julian_date - 2451545.0)
+    return "A.D. " + date.strftime("%Y-%b-%d %H:%M:%S.0000")
+
+
+def write(epoch, bodies, states):
+    for index, (name, id, center, _, _, _) in enumerate(bodies):
+        if center is None:
+            continue
+
+        center_name, center_id = bodies[center][0], bodies[center][1]
+
+        lines = [
+            "*******************************************************************************",
+            "GENERATED by make_references.py, not fetched from Horizons. Point masses integrated with RK4",
+            f"from the Horizons states at JD {epoch}, rounded to single precision, in steps of {STEP} days.",
+            "*******************************************************************************",
+            f"Target body name: {name} ({id})",
+            f"Center body name: {center_name} ({center_id})",
+            "Output units    : AU-D",
+            "Output type     : GEOMETRIC cartesian states",
+            "Reference frame : Ecliptic of J2000.0",
+            "*******************************************************************************",
+            "            JDTDB,            Calendar Date (TDB),                      X,                      Y,"
+            "                      Z,                     VX,                     VY,                     VZ,",
+            "$$SOE",
+        ]
+
+        for offset, (positions, velocities) in zip(OFFSETS, states):
+            julian_date = epoch + offset
+            relative = [positions[index][k] - positions[center][k] for k in range(3)] + [
+                velocities[index][k] - velocities[center][k] for k in range(3)
+            ]
+
+            lines.append(
+                f"{julian_date:.9f}, {calendar(julian_date)}, " + ", ".join(f"{v: .15E}" for v in relative) + ","
+            )
+
+        lines.append("$$EOE")
+
+        with open(os.path.join(HERE, f"{id}.txt"), "w") as f:
+            f.write("\n".join(lines) + "\n")
+
+
+if __name__ == "__main__":
+    epoch, bodies = load()
+    states = integrate(bodies, OFFSETS, STEP, single(G))
+
+    if "--check" not in sys.argv[1:]:
+        write(epoch, bodies, states)
+        sys.exit()
+
+    coarse = integrate(bodies, OFFSETS, STEP * 2, single(G))
+
+    for index, (name, _, center, _, _, _) in enumerate(bodies):
+        if center is None:
+            continue
+
+        fine = [states[-1][0][index][k] - states[-1][0][center][k] for k in range(3)]
+        rough = [coarse[-1][0][index][k] - coarse[-1][0][center][k] for k in range(3)]
+        print(f"{name:<10} {math.dist(fine, rough) / math.hypot(*fine):.3e}")
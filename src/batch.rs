@@ -0,0 +1,545 @@
+//! Runs the simulation without a window, for CI and compute clusters.
+
+use std::{
+    fmt,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    log::{Level, LogPlugin},
+    math::DVec3,
+    prelude::*,
+    utils::tracing::dispatcher,
+};
+
+use crate::{
+    cli::Cli,
+    mpcorb::MinorPlanetImport,
+    simulation::{
+        body::{Body, ParentBody},
+        conics::{patched_conics_system, sphere_of_influence_system, SoiTransitionEvent},
+        data::{initialize_bodies, Dataset},
+        elements::osculating_elements_system,
+        export::{export_system, Exporter},
+        frame::{barycentric_correction_system, ReferenceFrame},
+        jump::{date_jump_system, DateJump},
+        orbit::world_to_ecliptic,
+        physics::gravity_system,
+        scenario::Scenario,
+        settings::{Integrator, SimulationParameters, UPDATE_FREQUENCY},
+        snapshot::Snapshot,
+        time::{simulation_time_system, JulianDate, SimulationTime},
+    },
+    spk::{load_rails_system, rails_system, KernelSeed, Rails},
+};
+
+pub const EXIT_ERROR: u8 = 1;
+pub const EXIT_USAGE: u8 = 2;
+pub const EXIT_ENERGY_DRIFT: u8 = 3;
+pub const EXIT_NOT_FINITE: u8 = 4;
+
+/// Just the physics, the clock and exporting, without rendering, UI or input. Add it to an app with `MinimalPlugins`,
+/// spawn the bodies, then run `Startup` once to shift them into the barycentric frame or put them on rails.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (barycentric_correction_system, load_rails_system))
+            .add_systems(
+                FixedUpdate,
+                (
+                    gravity_system,
+                    sphere_of_influence_system.after(gravity_system),
+                    patched_conics_system.after(sphere_of_influence_system),
+                    rails_system.after(patched_conics_system),
+                    simulation_time_system.after(rails_system),
+                    osculating_elements_system.after(rails_system),
+                    export_system
+                        .after(simulation_time_system)
+                        .after(osculating_elements_system),
+                ),
+            )
+            .add_event::<SoiTransitionEvent>()
+            .insert_resource(Exporter::default())
+            .insert_resource(SimulationParameters::default())
+            .insert_resource(SimulationTime::default())
+            .insert_resource(ReferenceFrame::default())
+            .insert_resource(KernelSeed::default())
+            .insert_resource(Rails::default())
+            .insert_resource(DateJump::default());
+    }
+}
+
+/// Spawns every body of `dataset` with just what the physics needs.
+pub fn spawn_dataset(world: &mut World, dataset: &Dataset) {
+    for body in &dataset.bodies {
+        let entity = world
+            .spawn((
+                body.clone(),
+                Transform::from_translation(body.data.position),
+            ))
+            .id();
+
+        for satellite in body.satellites.iter().flatten() {
+            let satellite = satellite.read().unwrap();
+
+            world.spawn((
+                satellite.clone(),
+                Transform::from_translation(satellite.data.position),
+                ParentBody(entity),
+            ));
+        }
+    }
+}
+
+/// Runs physics updates until the clock is as close to `target` as a single update gets.
+pub fn run_until(world: &mut World, target: JulianDate) {
+    world.resource_mut::<DateJump>().jump_to(target);
+
+    while world.resource::<DateJump>().is_active() {
+        date_jump_system(world);
+    }
+}
+
+/// Conserved quantities of the whole system, in the units the simulation runs in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Diagnostics {
+    /// Kinetic plus potential energy, `None` if the force law has no potential
+    pub energy: Option<f64>,
+    pub angular_momentum: DVec3,
+    pub momentum: DVec3,
+
+    /// Whether every position and velocity is still a number
+    pub finite: bool,
+}
+
+impl Diagnostics {
+    pub fn measure(world: &mut World) -> Self {
+        let force_law = world.resource::<SimulationParameters>().force_law();
+
+        let bodies: Vec<(DVec3, DVec3, f64)> = world
+            .query::<(&Transform, &Body)>()
+            .iter(world)
+            .map(|(t, b)| {
+                (
+                    t.translation.as_dvec3(),
+                    b.data.velocity.as_dvec3(),
+                    b.data.mass as f64,
+                )
+            })
+            .collect();
+
+        let mut diagnostics = Self {
+            energy: Some(0.0),
+            angular_momentum: DVec3::ZERO,
+            momentum: DVec3::ZERO,
+            finite: bodies
+                .iter()
+                .all(|(p, v, _)| p.is_finite() && v.is_finite()),
+        };
+
+        for (i, (position, velocity, mass)) in bodies.iter().enumerate() {
+            let kinetic = 0.5 * mass * velocity.length_squared();
+            diagnostics.energy = diagnostics.energy.map(|energy| energy + kinetic);
+            diagnostics.angular_momentum += *mass * position.cross(*velocity);
+            diagnostics.momentum += *mass * *velocity;
+
+            for (other_position, _, other_mass) in &bodies[i + 1..] {
+                let distance = position.distance(*other_position);
+
+                if distance > 0.0 {
+                    let potential = force_law.potential(distance, *other_mass);
+                    diagnostics.energy = diagnostics
+                        .energy
+                        .zip(potential)
+                        .map(|(energy, potential)| energy + mass * potential);
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    pub scenario: String,
+    pub integrator: Integrator,
+    pub bodies: usize,
+
+    pub start: JulianDate,
+    pub end: JulianDate,
+
+    pub initial: Diagnostics,
+    pub last: Diagnostics,
+
+    /// Real time the run took
+    pub duration: Duration,
+}
+
+impl BatchReport {
+    /// Change in energy relative to the initial energy, `None` if the force law conserves none.
+    pub fn energy_drift(&self) -> Option<f64> {
+        let (initial, last) = self.initial.energy.zip(self.last.energy)?;
+
+        Some(relative(last - initial, initial.abs()))
+    }
+
+    /// Change in angular momentum relative to the initial angular momentum.
+    pub fn angular_momentum_drift(&self) -> f64 {
+        relative(
+            (self.last.angular_momentum - self.initial.angular_momentum).length(),
+            self.initial.angular_momentum.length(),
+        )
+    }
+}
+
+fn relative(difference: f64, reference: f64) -> f64 {
+    if reference == 0.0 {
+        return difference.abs();
+    }
+
+    difference.abs() / reference
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24}{}", "Scenario:", self.scenario)?;
+        writeln!(f, "{:<24}{:?}", "Integrator:", self.integrator)?;
+        writeln!(f, "{:<24}{}", "Bodies:", self.bodies)?;
+        writeln!(f, "{:<24}{} TDB", "Start:", self.start)?;
+        writeln!(f, "{:<24}{} TDB", "End:", self.end)?;
+        writeln!(
+            f,
+            "{:<24}{:.3}",
+            "Simulated days:",
+            self.end.days_since(&self.start)
+        )?;
+        writeln!(
+            f,
+            "{:<24}{:.3} s",
+            "Real time:",
+            self.duration.as_secs_f64()
+        )?;
+        match (self.initial.energy, self.last.energy, self.energy_drift()) {
+            (Some(initial), Some(last), Some(drift)) => {
+                writeln!(f, "{:<24}{:.9e} -> {:.9e}", "Energy:", initial, last)?;
+                writeln!(f, "{:<24}{:.3e}", "Energy drift:", drift)?;
+            }
+            _ => writeln!(f, "{:<24}none, the force law has no potential", "Energy:")?,
+        }
+        writeln!(
+            f,
+            "{:<24}{:.3e}",
+            "Angular momentum drift:",
+            self.angular_momentum_drift()
+        )?;
+        writeln!(
+            f,
+            "{:<24}{:.3e} -> {:.3e}",
+            "Momentum:",
+            self.initial.momentum.length(),
+            self.last.momentum.length()
+        )?;
+
+        if !self.last.finite {
+            writeln!(
+                f,
+                "Some bodies ended up at positions or velocities that are not numbers"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a headless app with the scenario of `cli` set up, at the epoch of the scenario.
+pub fn setup(cli: &Cli) -> Result<App, String> {
+    let mut app = App::new();
+
+    // Bevy's logging, to stderr, first so that loading the data is logged too.
+    // It can only be set up once per process, so later apps share it.
+    if !dispatcher::has_been_set() {
+        app.add_plugins(LogPlugin {
+            level: match cli.headless.verbose {
+                true => Level::INFO,
+                false => Level::WARN,
+            },
+            ..default()
+        });
+    }
+
+    let scenario = cli.scenario()?;
+
+    let mut parameters = SimulationParameters::default();
+    let mut seed = KernelSeed::default();
+    let mut jump = DateJump::default();
+    let mut frame = ReferenceFrame::default();
+    let mut minor_planets = MinorPlanetImport::default();
+
+    cli.apply(
+        &scenario,
+        &mut parameters,
+        &mut seed,
+        &mut jump,
+        &mut frame,
+        &mut minor_planets,
+    );
+
+    // Make every physics update a single integration step
+    if let Some(step) = cli.headless.step {
+        parameters.time_step = (step * UPDATE_FREQUENCY as f64) as f32;
+        parameters.updates_per_step = 1.0;
+    }
+
+    let dataset = initialize_bodies(None, &parameters, &scenario, &seed, &minor_planets)
+        .map_err(|e| format!("Could not load the data of \"{}\": {}", scenario.name, e))?;
+
+    app.add_plugins((MinimalPlugins, HeadlessPlugin));
+
+    let world = app.world_mut();
+    world.insert_resource(parameters);
+    world.insert_resource(frame);
+    world.insert_resource(seed);
+    world.insert_resource(SimulationTime::new(dataset.epoch));
+    spawn_dataset(world, &dataset);
+    world.run_schedule(Startup);
+
+    // Scenarios with data at another epoch start with a jump to theirs, or to the one asked for
+    if let Some(target) = jump.target() {
+        run_until(world, target);
+    }
+
+    world.insert_resource(scenario);
+
+    if let Some(settings) = &cli.export {
+        let mut exporter = world.resource_mut::<Exporter>();
+        exporter.settings = settings.clone();
+        exporter.start()?;
+    }
+
+    Ok(app)
+}
+
+/// Runs `days` from wherever `app` is, measuring how well the conserved quantities are kept.
+pub fn run(app: &mut App, days: f64) -> BatchReport {
+    let world = app.world_mut();
+
+    let start = world.resource::<SimulationTime>().current;
+    let initial = Diagnostics::measure(world);
+    let started = Instant::now();
+
+    let mut target = start;
+    target.add_days(days);
+
+    run_until(world, target);
+
+    BatchReport {
+        scenario: world
+            .get_resource::<Scenario>()
+            .map(|s| s.name.clone())
+            .unwrap_or_default(),
+        integrator: world.resource::<SimulationParameters>().integrator,
+        bodies: world.query::<&Body>().iter(world).count(),
+        start,
+        end: world.resource::<SimulationTime>().current,
+        initial,
+        last: Diagnostics::measure(world),
+        duration: started.elapsed(),
+    }
+}
+
+/// Runs the scenario of `cli` for as long as it asks, then prints the final state and the diagnostics.
+pub fn main(cli: &Cli) -> ExitCode {
+    let mut app = match setup(cli) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    if cli.headless.max_energy_drift.is_some()
+        && Diagnostics::measure(app.world_mut()).energy.is_none()
+    {
+        eprintln!(
+            "error: --max-energy-drift needs a force law that conserves energy, MOND does not"
+        );
+        return ExitCode::from(EXIT_USAGE);
+    }
+
+    let report = run(&mut app, cli.headless.days);
+    let world = app.world_mut();
+
+    world.resource_mut::<Exporter>().stop();
+
+    // Lengths in AU
+    let length = world.resource::<SimulationParameters>().unit_scale as f64;
+
+    println!(
+        "{:<16} {:>16} {:>16} {:>16} {:>16} {:>16} {:>16}",
+        "Body", "x (AU)", "y (AU)", "z (AU)", "vx (AU/d)", "vy (AU/d)", "vz (AU/d)"
+    );
+
+    for (transform, body) in world.query::<(&Transform, &Body)>().iter(world) {
+        let position = world_to_ecliptic(transform.translation) * length;
+        let velocity = world_to_ecliptic(body.data.velocity) * length;
+
+        println!(
+            "{:<16} {:>16.9} {:>16.9} {:>16.9} {:>16.9e} {:>16.9e} {:>16.9e}",
+            body.metadata.name.as_deref().unwrap_or("<unknown>"),
+            position.x,
+            position.y,
+            position.z,
+            velocity.x,
+            velocity.y,
+            velocity.z
+        );
+    }
+
+    println!();
+    print!("{}", report);
+
+    if let Some(output) = &cli.headless.output {
+        let saved = Snapshot::capture(world).and_then(|snapshot| snapshot.save(output));
+
+        match saved {
+            Ok(_) => println!("Saved the final state to {}", output.display()),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    if !report.last.finite {
+        eprintln!("error: the simulation blew up");
+        return ExitCode::from(EXIT_NOT_FINITE);
+    }
+
+    if let Some((limit, drift)) = cli.headless.max_energy_drift.zip(report.energy_drift()) {
+        if drift > limit {
+            eprintln!(
+                "error: energy drifted by {:.3e}, more than the allowed {:.3e}",
+                drift, limit
+            );
+            return ExitCode::from(EXIT_ENERGY_DRIFT);
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Command;
+
+    fn cli(args: &str) -> Cli {
+        match Cli::parse(args.split_whitespace().map(String::from)) {
+            Ok(Command::Run(cli)) => *cli,
+            _ => panic!("could not parse {}", args),
+        }
+    }
+
+    #[test]
+    fn runs_scenarios() {
+        let export = std::env::temp_dir().join(format!("batch-{}.csv", std::process::id()));
+
+        let mut app = setup(&cli(&format!(
+            "--scenario scenarios/jupiter_system.ron --integrator leapfrog --step 0.01 \
+             --export {} --export-bodies Io --export-interval 1",
+            export.display()
+        )))
+        .unwrap();
+
+        let report = run(&mut app, 10.0);
+        app.world_mut().resource_mut::<Exporter>().stop();
+
+        println!("{}", report);
+
+        assert_eq!(report.integrator, Integrator::Leapfrog);
+        assert_eq!(report.bodies, 6);
+        assert!((report.end.days_since(&report.start) - 10.0).abs() < 0.01);
+        assert!(report.last.finite);
+        assert!(report.energy_drift().unwrap() < 1e-5);
+        assert!(report.angular_momentum_drift() < 1e-5);
+
+        // A row a day, from the start to the end
+        let exported = std::fs::read_to_string(&export).unwrap();
+        std::fs::remove_file(&export).unwrap();
+        assert_eq!(exported.lines().filter(|l| l.contains(",Io,")).count(), 10);
+
+        // Half a day is most of an orbit of Io, Euler drifts away where leapfrog keeps the energy bounded
+        let drift = |integrator: &str| {
+            let mut app = setup(&cli(&format!(
+                "--scenario scenarios/jupiter_system.ron --integrator {} --step 0.5",
+                integrator
+            )))
+            .unwrap();
+
+            run(&mut app, 10.0).energy_drift().unwrap()
+        };
+
+        let euler = drift("euler");
+        assert!(euler > 1e-4);
+        assert!(drift("leapfrog") < euler);
+
+        // A softened potential is still conserved, MOND has none
+        let mut app = setup(&cli(
+            "--scenario scenarios/jupiter_system.ron --step 0.01 --force-law plummer --softening-factor 1e-8",
+        ))
+        .unwrap();
+
+        assert!(run(&mut app, 10.0).energy_drift().unwrap() < 1e-5);
+
+        let mut app = setup(&cli(
+            "--scenario scenarios/jupiter_system.ron --step 0.01 --force-law mond",
+        ))
+        .unwrap();
+
+        assert_eq!(run(&mut app, 1.0).energy_drift(), None);
+
+        assert!(setup(&cli("--scenario scenarios/missing.ron")).is_err());
+    }
+
+    /// The bodies in the kernel end up where it has them, the others are integrated around them.
+    #[test]
+    fn keeps_bodies_on_rails() {
+        use crate::simulation::{orbit::ecliptic_to_world, units::KILOMETER};
+
+        let mut app = setup(&cli(
+            "--kernel data/spk/ephemeris.bsp --on-rails --step 0.1",
+        ))
+        .unwrap();
+
+        let report = run(&mut app, 10.0);
+        assert!(report.last.finite);
+
+        let world = app.world_mut();
+        let kernel = world.resource::<Rails>().kernel.clone().unwrap();
+        let (length, _, _) = world.resource::<SimulationParameters>().base_units();
+
+        let mut on_rails: Vec<_> = world
+            .query::<(&Transform, &Body)>()
+            .iter(world)
+            .filter_map(|(transform, body)| {
+                let id = body.metadata.id? as i32;
+                let state = kernel.barycentric_state(id, report.end.value()).ok()?;
+                let expected = ecliptic_to_world(state.position * KILOMETER / length);
+
+                assert!(
+                    (transform.translation - expected).length() < 1e-6,
+                    "{:?}",
+                    body.metadata.name
+                );
+
+                body.metadata.name.clone()
+            })
+            .collect();
+
+        on_rails.sort();
+        assert_eq!(on_rails, ["Earth", "Moon", "Sun"]);
+    }
+}
@@ -0,0 +1,27 @@
+//! Runs a scenario without a window, then prints the final state and how well energy was conserved.
+//! Takes the same options as `solar-system --headless`, see `--help`.
+
+use std::{env, process::ExitCode};
+
+use solar_system::{
+    batch::{self, EXIT_USAGE},
+    cli::{Cli, Command, USAGE},
+};
+
+fn main() -> ExitCode {
+    let mut cli = match Cli::parse(env::args().skip(1)) {
+        Ok(Command::Run(cli)) => cli,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\nSee solar-system-batch --help", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    cli.headless.enabled = true;
+
+    batch::main(&cli)
+}
@@ -0,0 +1,58 @@
+//! Regenerates our data file from the saved Horizons responses, without a network connection.
+//!
+//! Usage: solar-system-data [responses directory] [output file]
+
+use std::{env, fs, path::PathBuf, process::ExitCode};
+
+use serde::Serialize;
+use solar_system::{horizons::compile::compile_responses, simulation::data::DATA_PATH};
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+
+    let responses = PathBuf::from(args.next().unwrap_or("data/responses".to_string()));
+    let output = PathBuf::from(args.next().unwrap_or(DATA_PATH.to_string()));
+
+    let compilation = match compile_responses(&responses) {
+        Ok(compilation) => compilation,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for warning in &compilation.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    // Indented like the data `data.py` wrote, to keep diffs of it small
+    let mut json = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+
+    if let Err(e) = compilation.dataset.serialize(&mut serializer) {
+        eprintln!("error: could not serialize the data: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(e) = fs::write(&output, json) {
+        eprintln!("error: could not write {}: {}", output.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    let count: usize = compilation
+        .dataset
+        .bodies
+        .iter()
+        .map(|b| 1 + b.satellites.len())
+        .sum();
+
+    println!(
+        "Compiled {} bodies at JD {} into {}",
+        count,
+        compilation.dataset.epoch,
+        output.display()
+    );
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,618 @@
+//! The command line, shared by the app and `solar-system-batch`.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::{
+    mpcorb::{MinorPlanetImport, OrbitClass},
+    simulation::{
+        export::{ExportFormat, ExportSettings, Exporter},
+        frame::{FrameCenter, ReferenceFrame},
+        jump::DateJump,
+        scenario::{Scenario, ScenarioParameters, DEFAULT_SCENARIO_PATH},
+        settings::{ForceLawKind, Integrator, SimulationMode, SimulationParameters},
+        time::JulianDate,
+        units::UnitSystem,
+    },
+    spk::KernelSeed,
+    ui::element::UI_DEBUG,
+};
+
+pub const USAGE: &str = "\
+Usage: solar-system [options]
+
+Scenario:
+  --scenario <path>                   Scenario to start with [default: scenarios/solar_system.ron]
+  --jump-to <date>                    Jump to a date once the bodies are spawned
+  --kernel <path>                     Place the bodies with an SPK kernel
+  --epoch <date>                      Epoch to place the bodies at with the kernel
+  --on-rails                          Keep the bodies the kernel covers on it, instead of integrating them
+  --no-barycentric-correction         Keep the momentum the bodies start with, the system drifts
+
+Parameters, overriding the ones of the scenario:
+  --gravitational-constant <value>    AU^3 / Solar Mass * day^2, derived from the scales if not given
+  --mass-scale <value>                Unit of mass, Solar Mass
+  --unit-scale <value>                Unit of length, AU
+  --data-units <units>                astronomical or si
+  --time-step <days>                  Days per second
+  --updates-per-step <value>          Integration steps per physics update
+  --softening-factor <value>          Softening of close encounters
+  --integrator <name>                 euler, leapfrog or rk4
+  --mode <name>                       nbody or patched_conics
+  --force-law <name>                  newtonian, plummer, yukawa, mond or power_law
+  --yukawa-strength <value>           Yukawa α, relative to Newtonian gravity
+  --yukawa-range <AU>                 Yukawa λ
+  --mond-acceleration <AU/day^2>      MOND a0
+  --power-law-exponent <value>        Gravity falls off as 1 / r^exponent
+
+Minor planets:
+  --mpcorb <path>                     Import minor planets from an MPCORB.DAT or JSON file
+  --mpcorb-designations <list>        Comma separated numbers, names or designations
+  --mpcorb-classes <list>             Comma separated classes, like neo,trojan
+  --mpcorb-max-magnitude <value>      Only objects at least this bright
+  --mpcorb-limit <count>              At most this many objects
+
+Export:
+  --export <path>                     Export body states to a CSV or JSON Lines file
+  --export-format <format>            csv or jsonl [default: from the extension]
+  --export-bodies <list>              Comma separated names [default: all]
+  --export-interval <days>            Simulated days between rows [default: 1]
+  --export-center <center>            sun or barycenter [default: sun]
+
+Headless:
+  --headless                          Run without a window, then print the final state
+  --days <days>                       Simulated days to run for [default: 365.25]
+  --step <days>                       Days per integration step, instead of the time step
+  --max-energy-drift <value>          Fail if the energy drifts more than this, relative
+  --output <path>                     Save the final state as a snapshot
+  --verbose                           Log everything, not just warnings
+
+Other:
+  --debug                             Outline the UI elements
+  -h, --help                          Print this help
+
+Dates are UTC, like 2030-06-01T12:00, or Julian Dates (TDB), like JD2462653.5.
+Exit codes: 0 on success, 1 if the scenario could not be run, 2 for invalid arguments,
+3 if the energy drifted more than allowed, 4 if a body ended up at a position that is not a number.";
+
+/// Options for running without a window.
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub enabled: bool,
+
+    /// Simulated days to run for, negative to run backwards
+    pub days: f64,
+
+    /// Days per integration step, overriding the time step of the scenario
+    pub step: Option<f64>,
+
+    /// Relative to the initial energy
+    pub max_energy_drift: Option<f64>,
+
+    /// Where to save a snapshot of the final state
+    pub output: Option<PathBuf>,
+
+    pub verbose: bool,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            days: 365.25,
+            step: None,
+            max_energy_drift: None,
+            output: None,
+            verbose: false,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Cli {
+    /// The default scenario if not given
+    pub scenario: Option<PathBuf>,
+
+    /// Override the parameters of the scenario
+    pub parameters: ScenarioParameters,
+
+    pub jump_to: Option<JulianDate>,
+    pub kernel: Option<PathBuf>,
+    pub epoch: Option<JulianDate>,
+    pub on_rails: bool,
+
+    /// Override the scenario
+    pub barycentric_correction: Option<bool>,
+
+    pub minor_planets: MinorPlanetImport,
+
+    pub export: Option<ExportSettings>,
+
+    pub debug: bool,
+
+    pub headless: HeadlessOptions,
+}
+
+pub enum Command {
+    Run(Box<Cli>),
+    Help,
+}
+
+impl Cli {
+    /// Parses the arguments, without the name of the program.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+
+        let mut export_path = None;
+        let mut export_format = None;
+        let mut export = ExportSettings::default();
+        let mut export_option = None;
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(Command::Help);
+            }
+
+            let Some(option) = arg.strip_prefix("--") else {
+                return Err(format!("Unexpected argument \"{}\"", arg));
+            };
+
+            // Either --option value or --option=value
+            let (name, inline) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, None),
+            };
+
+            match name {
+                "debug" | "headless" | "verbose" | "on-rails" | "no-barycentric-correction" => {
+                    if inline.is_some() {
+                        return Err(format!("--{} does not take a value", name));
+                    }
+
+                    match name {
+                        "debug" => cli.debug = true,
+                        "headless" => cli.headless.enabled = true,
+                        "verbose" => cli.headless.verbose = true,
+                        "on-rails" => cli.on_rails = true,
+                        _ => cli.barycentric_correction = Some(false),
+                    }
+
+                    continue;
+                }
+                _ => {}
+            }
+
+            let value = match inline.or_else(|| args.next()) {
+                Some(value) => value,
+                None => return Err(format!("--{} needs a value", name)),
+            };
+
+            let value = Value {
+                name,
+                value: &value,
+            };
+            let p = &mut cli.parameters;
+
+            match name {
+                "scenario" => cli.scenario = Some(PathBuf::from(value.value)),
+                "jump-to" => cli.jump_to = Some(value.date()?),
+                "kernel" => cli.kernel = Some(PathBuf::from(value.value)),
+                "epoch" => cli.epoch = Some(value.date()?),
+
+                "gravitational-constant" => p.gravitational_constant = Some(value.positive()?),
+                "mass-scale" => p.mass_scale = Some(value.positive()?),
+                "unit-scale" => p.unit_scale = Some(value.positive()?),
+                "data-units" => {
+                    p.data_units = Some(value.choice(UnitSystem::parse, "astronomical or si")?)
+                }
+                "time-step" => p.time_step = Some(value.number()?),
+                "updates-per-step" => p.updates_per_step = Some(value.positive()?),
+                "softening-factor" => p.softening_factor = Some(value.non_negative()?),
+                "integrator" => {
+                    p.integrator = Some(value.choice(Integrator::parse, "euler, leapfrog or rk4")?)
+                }
+                "mode" => {
+                    p.mode = Some(value.choice(SimulationMode::parse, "nbody or patched_conics")?)
+                }
+                "force-law" => {
+                    p.force_law = Some(value.choice(
+                        ForceLawKind::parse,
+                        "newtonian, plummer, yukawa, mond or power_law",
+                    )?)
+                }
+                "yukawa-strength" => p.yukawa_strength = Some(value.number()?),
+                "yukawa-range" => p.yukawa_range = Some(value.positive()?),
+                "mond-acceleration" => p.mond_acceleration = Some(value.positive()?),
+                "power-law-exponent" => p.power_law_exponent = Some(value.positive()?),
+
+                "mpcorb" => cli.minor_planets.path = Some(PathBuf::from(value.value)),
+                "mpcorb-designations" => cli.minor_planets.filter.designations = value.list(),
+                "mpcorb-classes" => {
+                    for class in value.list() {
+                        match OrbitClass::parse(&class) {
+                            Some(classes) => cli.minor_planets.filter.classes.extend(classes),
+                            None => return Err(format!(
+                                "Invalid value \"{}\" for --{}: expected neo, atira, aten, apollo, amor, mars_crosser, hungaria, main_belt, phocaea, hilda, trojan or distant",
+                                class, name
+                            )),
+                        }
+                    }
+                }
+                "mpcorb-max-magnitude" => {
+                    cli.minor_planets.filter.max_magnitude = Some(value.number()?)
+                }
+                "mpcorb-limit" => cli.minor_planets.filter.limit = Some(value.number()?),
+
+                "export" => export_path = Some(PathBuf::from(value.value)),
+                "export-format" | "export-bodies" | "export-interval" | "export-center" => {
+                    export_option.get_or_insert(name.to_string());
+
+                    match name {
+                        "export-format" => {
+                            export_format = Some(value.choice(ExportFormat::parse, "csv or jsonl")?)
+                        }
+                        "export-bodies" => export.bodies = value.list(),
+                        "export-interval" => export.interval = value.positive()?,
+                        _ => {
+                            export.center = value.choice(FrameCenter::parse, "sun or barycenter")?
+                        }
+                    }
+                }
+
+                "days" => cli.headless.days = value.number()?,
+                "step" => cli.headless.step = Some(value.positive()?),
+                "max-energy-drift" => cli.headless.max_energy_drift = Some(value.non_negative()?),
+                "output" => cli.headless.output = Some(PathBuf::from(value.value)),
+
+                _ => return Err(format!("Unknown option --{}", name)),
+            }
+        }
+
+        match export_path {
+            Some(path) => {
+                export.format = export_format.unwrap_or(ExportFormat::from_path(&path));
+                export.path = path;
+                cli.export = Some(export);
+            }
+            None => {
+                if let Some(option) = export_option {
+                    return Err(format!("--{} needs --export", option));
+                }
+            }
+        }
+
+        if cli.epoch.is_some() && cli.kernel.is_none() {
+            return Err("--epoch needs --kernel".to_string());
+        }
+
+        if cli.on_rails && cli.kernel.is_none() {
+            return Err("--on-rails needs --kernel".to_string());
+        }
+
+        Ok(Command::Run(Box::new(cli)))
+    }
+
+    /// The scenario to start with. If none was given and the default one is missing, we use the built in one.
+    pub fn scenario(&self) -> Result<Scenario, String> {
+        match &self.scenario {
+            Some(path) => Scenario::load(path),
+            None => Ok(
+                Scenario::load(&PathBuf::from(DEFAULT_SCENARIO_PATH)).unwrap_or_else(|e| {
+                    error!("Could not load the scenario, using the built in one: {}", e);
+                    Scenario::default()
+                }),
+            ),
+        }
+    }
+
+    /// Sets up the scenario, with everything given on the command line taking precedence over it.
+    pub fn apply(
+        &self,
+        scenario: &Scenario,
+        parameters: &mut SimulationParameters,
+        seed: &mut KernelSeed,
+        jump: &mut DateJump,
+        frame: &mut ReferenceFrame,
+        minor_planets: &mut MinorPlanetImport,
+    ) {
+        let mut scenario = scenario.clone();
+        scenario.parameters.merge(&self.parameters);
+        scenario.barycentric_correction = self
+            .barycentric_correction
+            .or(scenario.barycentric_correction);
+        scenario.apply(parameters, seed, jump, frame);
+
+        if let Some(kernel) = &self.kernel {
+            info!("Placing the bodies with the kernel {}", kernel.display());
+            seed.kernel = Some(kernel.clone());
+            seed.epoch = self.epoch;
+            seed.on_rails = self.on_rails;
+
+            // The kernel is barycentric already, and shifting the bodies on it would only move them off it
+            if self.on_rails {
+                frame.barycentric_correction = false;
+            }
+        }
+
+        if let Some(target) = self.jump_to {
+            info!("Jumping to {} (TDB) on startup", target);
+            jump.jump_to(target);
+        }
+
+        *minor_planets = self.minor_planets.clone();
+    }
+}
+
+/// The value of an option, for error messages that say which.
+struct Value<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl Value<'_> {
+    fn invalid(&self, expected: &str) -> String {
+        format!(
+            "Invalid value \"{}\" for --{}: expected {}",
+            self.value, self.name, expected
+        )
+    }
+
+    /// Rust also parses "inf" and "NaN", which no option can make sense of.
+    fn number<T: std::str::FromStr>(&self) -> Result<T, String> {
+        self.value
+            .parse()
+            .ok()
+            .filter(|_| self.value.parse::<f64>().is_ok_and(f64::is_finite))
+            .ok_or(self.invalid("a number"))
+    }
+
+    fn positive<T: std::str::FromStr + PartialOrd + Default>(&self) -> Result<T, String> {
+        self.number()
+            .ok()
+            .filter(|n| *n > T::default())
+            .ok_or(self.invalid("a positive number"))
+    }
+
+    fn non_negative<T: std::str::FromStr + PartialOrd + Default>(&self) -> Result<T, String> {
+        self.number()
+            .ok()
+            .filter(|n| *n >= T::default())
+            .ok_or(self.invalid("a number of at least 0"))
+    }
+
+    fn choice<T>(&self, parse: fn(&str) -> Option<T>, expected: &str) -> Result<T, String> {
+        parse(self.value).ok_or(self.invalid(expected))
+    }
+
+    fn date(&self) -> Result<JulianDate, String> {
+        JulianDate::parse(self.value)
+            .filter(|date| date.value().is_finite())
+            .ok_or(
+                self.invalid("a UTC date like 2030-06-01T12:00 or a Julian Date like JD2462653.5"),
+            )
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+/// Sets up the simulation as the command line asks, before the bodies are spawned.
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the command line sets up every one of these resources"
+)]
+pub fn cli_system(
+    cli: Res<Cli>,
+    scenario: Res<Scenario>,
+    mut parameters: ResMut<SimulationParameters>,
+    mut seed: ResMut<KernelSeed>,
+    mut jump: ResMut<DateJump>,
+    mut frame: ResMut<ReferenceFrame>,
+    mut minor_planets: ResMut<MinorPlanetImport>,
+    mut exporter: ResMut<Exporter>,
+) {
+    cli.apply(
+        &scenario,
+        &mut parameters,
+        &mut seed,
+        &mut jump,
+        &mut frame,
+        &mut minor_planets,
+    );
+
+    if cli.debug {
+        info!("Debug mode enabled");
+        unsafe { UI_DEBUG = true };
+    }
+
+    if let Some(settings) = &cli.export {
+        exporter.settings = settings.clone();
+
+        if let Err(e) = exporter.start() {
+            error!("Could not start exporting: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, String> {
+        match Cli::parse(args.split_whitespace().map(String::from))? {
+            Command::Run(cli) => Ok(*cli),
+            Command::Help => Err("help".to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_options() {
+        let cli = parse(
+            "--debug --time-step 2.5 --integrator=rk4 --mode patched_conics --gravitational-constant 3e-4 \
+             --scenario scenarios/jupiter_system.ron --jump-to JD2460700.5 --mpcorb-classes neo,trojan \
+             --export out.jsonl --export-bodies Earth,Mars --headless --days 10 --no-barycentric-correction \
+             --kernel data/spk/ephemeris.bsp --on-rails",
+        )
+        .unwrap();
+
+        // Flags do not take the next argument with them
+        assert!(cli.debug);
+        assert_eq!(cli.parameters.time_step, Some(2.5));
+        assert_eq!(cli.parameters.integrator, Some(Integrator::RK4));
+        assert_eq!(cli.parameters.mode, Some(SimulationMode::PatchedConics));
+        assert_eq!(cli.parameters.gravitational_constant, Some(3e-4));
+        assert_eq!(cli.parameters.softening_factor, None);
+        assert_eq!(
+            cli.scenario,
+            Some(PathBuf::from("scenarios/jupiter_system.ron"))
+        );
+        assert_eq!(cli.jump_to, Some(JulianDate::new(2460700.5)));
+        assert_eq!(cli.minor_planets.filter.classes.len(), 5);
+
+        let export = cli.export.unwrap();
+        assert_eq!(export.format, ExportFormat::JsonLines);
+        assert_eq!(export.bodies, vec!["Earth", "Mars"]);
+
+        assert!(cli.headless.enabled);
+        assert_eq!(cli.headless.days, 10.0);
+        assert_eq!(cli.barycentric_correction, Some(false));
+        assert!(cli.on_rails);
+
+        assert!(parse("--time-step 1 --help").is_err_and(|e| e == "help"));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        for (args, error) in [
+            ("debug", "Unexpected argument \"debug\""),
+            ("--time_step 2", "Unknown option --time_step"),
+            ("--time-step", "--time-step needs a value"),
+            (
+                "--time-step fast",
+                "Invalid value \"fast\" for --time-step: expected a number",
+            ),
+            (
+                "--days nan",
+                "Invalid value \"nan\" for --days: expected a number",
+            ),
+            (
+                "--days inf",
+                "Invalid value \"inf\" for --days: expected a number",
+            ),
+            (
+                "--step inf",
+                "Invalid value \"inf\" for --step: expected a positive number",
+            ),
+            (
+                "--time-step NaN",
+                "Invalid value \"NaN\" for --time-step: expected a number",
+            ),
+            (
+                "--softening-factor -infinity",
+                "Invalid value \"-infinity\" for --softening-factor: expected a number of at least 0",
+            ),
+            (
+                "--softening-factor -1e-6",
+                "Invalid value \"-1e-6\" for --softening-factor: expected a number of at least 0",
+            ),
+            (
+                "--export out.csv --export-interval 0",
+                "Invalid value \"0\" for --export-interval: expected a positive number",
+            ),
+            (
+                "--jump-to JDnan",
+                "Invalid value \"JDnan\" for --jump-to: expected a UTC date like 2030-06-01T12:00 or a Julian Date like JD2462653.5",
+            ),
+            (
+                "--updates-per-step 0",
+                "Invalid value \"0\" for --updates-per-step: expected a positive number",
+            ),
+            (
+                "--power-law-exponent 0",
+                "Invalid value \"0\" for --power-law-exponent: expected a positive number",
+            ),
+            (
+                "--integrator verlet",
+                "Invalid value \"verlet\" for --integrator: expected euler, leapfrog or rk4",
+            ),
+            (
+                "--mpcorb-limit -1",
+                "Invalid value \"-1\" for --mpcorb-limit: expected a number",
+            ),
+            ("--export-interval 2", "--export-interval needs --export"),
+            ("--epoch JD2460700.5", "--epoch needs --kernel"),
+            ("--on-rails", "--on-rails needs --kernel"),
+            ("--headless=yes", "--headless does not take a value"),
+            (
+                "--no-barycentric-correction=1",
+                "--no-barycentric-correction does not take a value",
+            ),
+        ] {
+            assert_eq!(parse(args).err().as_deref(), Some(error), "{}", args);
+        }
+    }
+
+    #[test]
+    fn overrides_the_scenario() {
+        let cli = parse("--time-step 4 --jump-to JD2460700.5").unwrap();
+        let scenario = Scenario::default();
+
+        let mut parameters = SimulationParameters::default();
+        let mut jump = DateJump::default();
+        let mut frame = ReferenceFrame::default();
+
+        cli.apply(
+            &scenario,
+            &mut parameters,
+            &mut KernelSeed::default(),
+            &mut jump,
+            &mut frame,
+            &mut MinorPlanetImport::default(),
+        );
+
+        assert_eq!(parameters.time_step, 4.0);
+        assert_eq!(
+            parameters.integrator,
+            scenario.parameters.integrator.unwrap()
+        );
+        assert_eq!(
+            parameters.gravitational_constant,
+            parameters.derived_gravitational_constant()
+        );
+        assert_eq!(jump.target(), Some(JulianDate::new(2460700.5)));
+        assert!(frame.barycentric_correction);
+
+        parse("--no-barycentric-correction").unwrap().apply(
+            &scenario,
+            &mut parameters,
+            &mut KernelSeed::default(),
+            &mut jump,
+            &mut frame,
+            &mut MinorPlanetImport::default(),
+        );
+        assert!(!frame.barycentric_correction);
+
+        let mut seed = KernelSeed::default();
+        let mut frame = ReferenceFrame::default();
+
+        parse("--kernel data/spk/ephemeris.bsp --on-rails")
+            .unwrap()
+            .apply(
+                &scenario,
+                &mut parameters,
+                &mut seed,
+                &mut jump,
+                &mut frame,
+                &mut MinorPlanetImport::default(),
+            );
+        assert!(seed.on_rails);
+        assert!(!frame.barycentric_correction);
+    }
+}
@@ -0,0 +1,374 @@
+//! Compiles the saved Horizons responses into our data file, the way `data.py` used to,
+//! so the data can be regenerated without Python or a network connection.
+
+use std::{f64::consts::PI, fs, path::Path};
+
+use serde::Serialize;
+
+use super::{naif_parent, parse_response, HorizonsElements, HorizonsResponse};
+use crate::simulation::units::{ASTRONOMICAL_UNIT, KILOMETER, SOLAR_MASS};
+
+/// Mass of bodies we know nothing about, small enough not to disturb anything
+const FALLBACK_MASS: f64 = 1.0e16;
+
+const COLORS: [(&str, [f32; 4]); 14] = [
+    ("Sun", [0.96, 0.75, 0.15, 1.0]),
+    ("Mercury", [0.5, 0.5, 0.5, 1.0]),
+    ("Venus", [0.9, 0.8, 0.6, 1.0]),
+    ("Earth", [0.0, 0.5, 1.0, 1.0]),
+    ("Mars", [0.8, 0.3, 0.2, 1.0]),
+    ("Jupiter", [0.9, 0.6, 0.4, 1.0]),
+    ("Saturn", [0.8, 0.7, 0.5, 1.0]),
+    ("Uranus", [0.6, 0.9, 0.8, 1.0]),
+    ("Neptune", [0.2, 0.3, 0.9, 1.0]),
+    ("Pluto", [0.7, 0.5, 0.3, 1.0]),
+    ("Eris", [0.8, 0.8, 0.8, 1.0]),
+    ("Haumea", [1.0, 1.0, 1.0, 1.0]),
+    ("Makemake", [0.6, 0.3, 0.2, 1.0]),
+    ("Ceres", [0.5, 0.4, 0.3, 1.0]),
+];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CompiledVector {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CompiledElements {
+    pub eccentricity: f64,
+    pub periapsis_distance: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_perifocus: f64,
+    pub time_of_periapsis: f64,
+    pub mean_motion: f64,
+    pub mean_anomaly: f64,
+    pub true_anomaly: f64,
+    pub semi_major_axis: f64,
+    pub apoapsis_distance: f64,
+    pub sidereal_orbit_period: f64,
+}
+
+impl From<HorizonsElements> for CompiledElements {
+    fn from(e: HorizonsElements) -> Self {
+        Self {
+            eccentricity: e.eccentricity,
+            periapsis_distance: e.periapsis_distance,
+            inclination: e.inclination,
+            longitude_of_ascending_node: e.longitude_of_ascending_node,
+            argument_of_perifocus: e.argument_of_perifocus,
+            time_of_periapsis: e.time_of_periapsis,
+            mean_motion: e.mean_motion,
+            mean_anomaly: e.mean_anomaly,
+            true_anomaly: e.true_anomaly,
+            semi_major_axis: e.semi_major_axis,
+            apoapsis_distance: e.apoapsis_distance,
+            sidereal_orbit_period: e.sidereal_orbit_period,
+        }
+    }
+}
+
+/// The `data` of a body in our data file, in the units the simulation loads.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledData {
+    /// Solar masses
+    pub mass: f64,
+
+    /// AU
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+
+    /// g/cm^3
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density: Option<f64>,
+
+    /// rad/s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<f64>,
+
+    /// K
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+
+    /// Degrees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub obliquity: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub j2: Option<f64>,
+
+    /// Heliocentric, AU
+    pub position: CompiledVector,
+
+    /// Heliocentric, AU/day
+    pub velocity: CompiledVector,
+
+    pub orbital_elements: Option<CompiledElements>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledMetadata {
+    pub id: u32,
+    pub name: String,
+    pub color: Option<[f32; 4]>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledBody {
+    pub data: CompiledData,
+    pub metadata: CompiledMetadata,
+    pub satellites: Vec<CompiledBody>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompiledDataset {
+    /// Julian Date (TDB)
+    pub epoch: f64,
+
+    pub bodies: Vec<CompiledBody>,
+}
+
+/// The dataset, and what we had to make up along the way.
+#[derive(Debug, Clone)]
+pub struct Compilation {
+    pub dataset: CompiledDataset,
+    pub warnings: Vec<String>,
+}
+
+/// Compiles one body from its response. `file_name` is only used for its name, if Horizons does not give one.
+pub fn compile_body(
+    response: &HorizonsResponse,
+    file_name: &str,
+    warnings: &mut Vec<String>,
+) -> Result<(f64, CompiledBody), String> {
+    let vectors = response
+        .vectors
+        .first()
+        .ok_or(format!("{}: no vector table", file_name))?;
+
+    let state = vectors
+        .states
+        .first()
+        .ok_or(format!("{}: empty vector table", file_name))?;
+
+    if vectors.center.id != Some(10) {
+        return Err(format!(
+            "{}: vectors are relative to {}, expected the Sun",
+            file_name, vectors.center
+        ));
+    }
+
+    let target = &vectors.target;
+    let id = target
+        .id
+        .ok_or(format!("{}: no NAIF ID for {}", file_name, target))?;
+
+    // Files are saved as "{name} – {id}.txt"
+    let name = match target.name.is_empty() {
+        false => target.name.clone(),
+        true => file_name
+            .split(" – ")
+            .next()
+            .unwrap_or(file_name)
+            .to_string(),
+    };
+
+    let properties = &response.properties;
+
+    let mass = match properties.derived_mass() {
+        Some(mass) => mass,
+        None => match (properties.density, properties.radius) {
+            (Some(density), Some(radius)) => {
+                let mass = 4.0 / 3.0 * PI * (radius * KILOMETER).powi(3) * density * 1000.0;
+                warnings.push(format!(
+                    "Missing mass for {}, calculated {:e} kg from its density",
+                    name, mass
+                ));
+                mass
+            }
+            _ => {
+                warnings.push(format!(
+                    "Missing mass for {}, using {:e} kg",
+                    name, FALLBACK_MASS
+                ));
+                FALLBACK_MASS
+            }
+        },
+    };
+
+    let elements = response
+        .elements
+        .iter()
+        .flat_map(|table| table.elements.first())
+        .next()
+        .map(|e| CompiledElements::from(*e));
+
+    if elements.is_none() && id != 10 {
+        warnings.push(format!("No orbital elements for {}", name));
+    }
+
+    Ok((
+        state.julian_date,
+        CompiledBody {
+            data: CompiledData {
+                mass: mass / SOLAR_MASS,
+                radius: properties.radius.map(|r| r * KILOMETER / ASTRONOMICAL_UNIT),
+                density: properties.density,
+                rotation: properties.rotation_rate,
+                temperature: properties.temperature,
+                obliquity: properties.obliquity,
+                j2: properties.j2,
+                position: CompiledVector {
+                    x: state.position.x,
+                    y: state.position.y,
+                    z: state.position.z,
+                },
+                velocity: CompiledVector {
+                    x: state.velocity.x,
+                    y: state.velocity.y,
+                    z: state.velocity.z,
+                },
+                orbital_elements: elements,
+            },
+            metadata: CompiledMetadata {
+                id,
+                color: COLORS.iter().find(|(n, _)| *n == name).map(|(_, c)| *c),
+                name,
+            },
+            satellites: Vec::new(),
+        },
+    ))
+}
+
+/// Compiles every `.txt` response in `dir`, putting natural satellites under their planet.
+pub fn compile_responses(dir: &Path) -> Result<Compilation, String> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Could not read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+
+    paths.sort();
+
+    let mut warnings = Vec::new();
+    let mut epoch: Option<f64> = None;
+    let mut bodies = Vec::new();
+
+    for path in paths {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let response = parse_response(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let file_name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let (julian_date, body) = compile_body(&response, file_name, &mut warnings)?;
+
+        match epoch {
+            Some(epoch) if epoch != julian_date => {
+                return Err(format!(
+                    "{} is at JD {}, the others at JD {}",
+                    path.display(),
+                    julian_date,
+                    epoch
+                ));
+            }
+            _ => epoch = Some(julian_date),
+        }
+
+        bodies.push(body);
+    }
+
+    let epoch = epoch.ok_or(format!("No responses in {}", dir.display()))?;
+
+    bodies.sort_by_key(|b| b.metadata.id);
+
+    let (satellites, mut primaries): (Vec<_>, Vec<_>) = bodies
+        .into_iter()
+        .partition(|b| naif_parent(b.metadata.id).is_some());
+
+    for satellite in satellites {
+        let parent_id = naif_parent(satellite.metadata.id).unwrap();
+
+        match primaries.iter_mut().find(|p| p.metadata.id == parent_id) {
+            Some(parent) => parent.satellites.push(satellite),
+            None => {
+                warnings.push(format!(
+                    "No response for {}, the planet of {}",
+                    parent_id, satellite.metadata.name
+                ));
+                primaries.push(satellite);
+            }
+        }
+    }
+
+    Ok(Compilation {
+        dataset: CompiledDataset {
+            epoch,
+            bodies: primaries,
+        },
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_responses() {
+        let compilation = compile_responses(Path::new("data/responses")).unwrap();
+        let dataset = &compilation.dataset;
+
+        assert_eq!(dataset.epoch, 2460676.5);
+        assert_eq!(dataset.bodies.len(), 14);
+
+        let sun = &dataset.bodies[0];
+        assert_eq!(sun.metadata.name, "Sun");
+        assert!((sun.data.mass - 1.0).abs() < 1e-5);
+        assert!(sun.data.orbital_elements.is_none());
+
+        let earth = dataset
+            .bodies
+            .iter()
+            .find(|b| b.metadata.id == 399)
+            .unwrap();
+        assert_eq!(earth.satellites.len(), 1);
+        assert_eq!(earth.data.j2, Some(0.00108262545));
+        assert_eq!(earth.metadata.color, Some([0.0, 0.5, 1.0, 1.0]));
+
+        // The Moon is about 1/81 of the Earth
+        let ratio = earth.data.mass / earth.satellites[0].data.mass;
+        assert!((ratio - 81.3).abs() < 0.1, "{}", ratio);
+
+        let jupiter = dataset
+            .bodies
+            .iter()
+            .find(|b| b.metadata.id == 599)
+            .unwrap();
+        assert_eq!(jupiter.satellites.len(), 8);
+
+        let ceres = dataset.bodies.last().unwrap();
+        assert_eq!(ceres.metadata.name, "Ceres");
+        assert_eq!(ceres.metadata.id, 2000001);
+
+        // Ceres and friends list no mass, nor anything to derive it from
+        assert!(compilation.warnings.iter().any(|w| w.contains("Ceres")));
+    }
+
+    /// What we compile must load into the same initial conditions as the checked in data.
+    #[test]
+    fn matches_compiled_data() {
+        let compilation = compile_responses(Path::new("data/responses")).unwrap();
+        let json = serde_json::to_string(&compilation.dataset).unwrap();
+
+        let compiled: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let checked_in: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string("data/compiled_data.json").unwrap()).unwrap();
+
+        assert_eq!(compiled, checked_in);
+    }
+}
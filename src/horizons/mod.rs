@@ -0,0 +1,629 @@
+//! Parses the plain text responses of the JPL Horizons API, as saved in `data/responses`.
+//!
+//! A response holds a header with the physical properties of the target, followed by any number of tables.
+//! We read the vector and osculating element tables, which have to be requested with `CSV_FORMAT=YES`.
+
+use std::{f64::consts::TAU, fmt};
+
+use bevy::math::DVec3;
+
+use crate::simulation::units::GRAVITATIONAL_CONSTANT;
+
+pub mod compile;
+
+/// A body as Horizons names it, e.g. "Earth (399)" or "136199 Eris (2003 UB313)".
+#[derive(Debug, Clone, PartialEq)]
+pub struct HorizonsBody {
+    pub name: String,
+    pub id: Option<u32>,
+}
+
+impl HorizonsBody {
+    pub fn parse(text: &str) -> Self {
+        // Drop the "{source: ...}" part
+        let text = text.split('{').next().unwrap_or_default().trim();
+
+        let (name, designation) = match text.rsplit_once('(') {
+            Some((name, designation)) => (name.trim(), designation.trim_end_matches(')').trim()),
+            None => (text, ""),
+        };
+
+        // Numbered minor bodies have their number in front of the name, and a provisional designation in parentheses
+        let (number, bare_name) = match name.split_once(' ') {
+            Some((number, bare_name)) if number.parse::<u32>().is_ok() => {
+                (number.parse::<u32>().ok(), bare_name.trim())
+            }
+            _ => (None, name),
+        };
+
+        Self {
+            name: bare_name.to_string(),
+            id: designation.parse::<u32>().ok().or(number),
+        }
+    }
+}
+
+impl fmt::Display for HorizonsBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name.is_empty(), self.id) {
+            (false, _) => write!(f, "{}", self.name),
+            (true, Some(id)) => write!(f, "{}", id),
+            (true, None) => write!(f, "<unknown>"),
+        }
+    }
+}
+
+/// Physical properties from the header of a response, in the units Horizons gives them.
+/// Anything the header does not list, or lists in a way we do not understand, is `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhysicalProperties {
+    /// km^3 / s^2
+    pub gm: Option<f64>,
+
+    /// kg
+    pub mass: Option<f64>,
+
+    /// Mean radius, km
+    pub radius: Option<f64>,
+
+    /// g / cm^3
+    pub density: Option<f64>,
+
+    /// Sidereal rotation rate, rad / s, negative for retrograde rotation
+    pub rotation_rate: Option<f64>,
+
+    /// Degrees
+    pub obliquity: Option<f64>,
+
+    /// Second zonal harmonic, unitless
+    pub j2: Option<f64>,
+
+    /// Mean temperature, K
+    pub temperature: Option<f64>,
+}
+
+impl PhysicalProperties {
+    /// kg, derived from GM when given, as that is known much more precisely than G.
+    pub fn derived_mass(&self) -> Option<f64> {
+        self.gm
+            .filter(|gm| *gm > 0.0)
+            .map(|gm| gm * 1e9 / GRAVITATIONAL_CONSTANT)
+            .or(self.mass)
+    }
+}
+
+/// A row of a vector table, relative to the center, in the ecliptic frame of J2000.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateVector {
+    /// Julian Date (TDB)
+    pub julian_date: f64,
+
+    /// AU
+    pub position: DVec3,
+
+    /// AU / day
+    pub velocity: DVec3,
+}
+
+/// A row of an osculating element table. Distances are in AU, angles in degrees and times in days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HorizonsElements {
+    /// Julian Date (TDB)
+    pub julian_date: f64,
+    pub eccentricity: f64,
+    pub periapsis_distance: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_perifocus: f64,
+
+    /// Julian Date (TDB)
+    pub time_of_periapsis: f64,
+
+    /// Degrees / day
+    pub mean_motion: f64,
+    pub mean_anomaly: f64,
+    pub true_anomaly: f64,
+    pub semi_major_axis: f64,
+    pub apoapsis_distance: f64,
+    pub sidereal_orbit_period: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct VectorTable {
+    pub target: HorizonsBody,
+    pub center: HorizonsBody,
+    pub states: Vec<StateVector>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ElementTable {
+    pub target: HorizonsBody,
+    pub center: HorizonsBody,
+    pub elements: Vec<HorizonsElements>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HorizonsResponse {
+    /// Taken from the first table, `None` if there is none
+    pub target: Option<HorizonsBody>,
+
+    pub properties: PhysicalProperties,
+    pub vectors: Vec<VectorTable>,
+    pub elements: Vec<ElementTable>,
+}
+
+/// Columns of a vector table we need, in the order of `StateVector`.
+const VECTOR_COLUMNS: [&str; 7] = ["JDTDB", "X", "Y", "Z", "VX", "VY", "VZ"];
+
+/// Columns of an element table we need, in the order of `HorizonsElements`.
+const ELEMENT_COLUMNS: [&str; 13] = [
+    "JDTDB", "EC", "QR", "IN", "OM", "W", "Tp", "N", "MA", "TA", "A", "AD", "PR",
+];
+
+enum TableKind {
+    Vectors,
+    Elements,
+}
+
+/// Parses a whole response, which may be several API responses one after the other.
+pub fn parse_response(text: &str) -> Result<HorizonsResponse, String> {
+    let mut response = HorizonsResponse {
+        properties: parse_properties(text),
+        ..Default::default()
+    };
+
+    let mut target: Option<HorizonsBody> = None;
+    let mut center: Option<HorizonsBody> = None;
+    let mut units: Option<String> = None;
+
+    // What the last table header was, and where the columns we need are in it
+    let mut header: Option<(TableKind, Vec<usize>)> = None;
+    let mut rows: Option<Vec<Vec<f64>>> = None;
+
+    for (number, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if let Some(name) = trimmed.strip_prefix("Target body name:") {
+            target = Some(HorizonsBody::parse(name));
+        } else if let Some(name) = trimmed.strip_prefix("Center body name:") {
+            center = Some(HorizonsBody::parse(name));
+        } else if let Some(value) = trimmed.strip_prefix("Output units    :") {
+            units = Some(value.trim().to_string());
+        } else if trimmed.starts_with("JDTDB,") {
+            let columns: Vec<&str> = trimmed.split(',').map(|c| c.trim()).collect();
+            let indices = |names: &[&str]| {
+                names
+                    .iter()
+                    .map(|name| columns.iter().position(|c| c == name))
+                    .collect::<Option<Vec<usize>>>()
+            };
+
+            header = indices(&VECTOR_COLUMNS)
+                .map(|i| (TableKind::Vectors, i))
+                .or(indices(&ELEMENT_COLUMNS).map(|i| (TableKind::Elements, i)));
+        } else if trimmed == "$$SOE" && header.is_some() {
+            if !units.as_deref().is_some_and(|u| u.starts_with("AU-D")) {
+                return Err(format!(
+                    "Table on line {} is in {}, expected AU-D",
+                    number + 1,
+                    units.as_deref().unwrap_or("unknown units")
+                ));
+            }
+
+            rows = Some(Vec::new());
+        } else if trimmed == "$$EOE" {
+            if let (Some(rows), Some((kind, _))) = (rows.take(), header.take()) {
+                let target = target.clone().ok_or("Table without a target body")?;
+                let center = center.clone().ok_or("Table without a center body")?;
+
+                response.target.get_or_insert(target.clone());
+
+                match kind {
+                    TableKind::Vectors => response.vectors.push(VectorTable {
+                        target,
+                        center,
+                        states: rows
+                            .iter()
+                            .map(|r| StateVector {
+                                julian_date: r[0],
+                                position: DVec3::new(r[1], r[2], r[3]),
+                                velocity: DVec3::new(r[4], r[5], r[6]),
+                            })
+                            .collect(),
+                    }),
+                    TableKind::Elements => response.elements.push(ElementTable {
+                        target,
+                        center,
+                        elements: rows
+                            .iter()
+                            .map(|r| HorizonsElements {
+                                julian_date: r[0],
+                                eccentricity: r[1],
+                                periapsis_distance: r[2],
+                                inclination: r[3],
+                                longitude_of_ascending_node: r[4],
+                                argument_of_perifocus: r[5],
+                                time_of_periapsis: r[6],
+                                mean_motion: r[7],
+                                mean_anomaly: r[8],
+                                true_anomaly: r[9],
+                                semi_major_axis: r[10],
+                                apoapsis_distance: r[11],
+                                sidereal_orbit_period: r[12],
+                            })
+                            .collect(),
+                    }),
+                }
+            }
+        } else if let (Some(rows), Some((_, indices))) = (rows.as_mut(), header.as_ref()) {
+            let fields: Vec<&str> = trimmed.split(',').map(|f| f.trim()).collect();
+
+            let row = indices
+                .iter()
+                .map(|&i| fields.get(i).and_then(|f| f.parse::<f64>().ok()))
+                .collect::<Option<Vec<f64>>>()
+                .ok_or(format!("Could not parse line {}: {}", number + 1, trimmed))?;
+
+            rows.push(row);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Reads the physical properties from the header, which lists them as "key = value" in one or two columns.
+/// Every body is laid out a little differently, so we look for the keys we know of, in order of preference.
+pub fn parse_properties(text: &str) -> PhysicalProperties {
+    // The header ends where the first ephemeris starts
+    let header: Vec<&str> = text
+        .lines()
+        .take_while(|l| !l.starts_with("Ephemeris /") && l.trim() != "$$SOE")
+        .collect();
+
+    // The value of the first of `labels` found, the earlier labels take precedence
+    let find = |labels: &[&str], parse: &dyn Fn(&str, &str) -> Option<f64>| {
+        labels.iter().find_map(|label| {
+            header
+                .iter()
+                .find_map(|line| key_values(line, label).find_map(|(key, value)| parse(key, value)))
+        })
+    };
+
+    let number = |_: &str, value: &str| parse_number(value).map(|(n, _)| n);
+
+    PhysicalProperties {
+        gm: find(&["gm"], &|key, value| {
+            if key.to_ascii_lowercase().contains("sigma") {
+                return None;
+            }
+
+            number(key, value)
+        }),
+        mass: find(&["mass"], &|key, value| {
+            // Skip "Mass ratio", "Mass of atmosphere" and the like
+            let exponent = power_of_ten(key)?;
+            let grams = !key.contains("kg");
+
+            let (mass, _) = parse_number(value)?;
+            Some(mass * 10f64.powi(exponent) / if grams { 1000.0 } else { 1.0 })
+        }),
+        radius: find(
+            &["vol. mean radius", "mean radius", "radius (km)", "rad"],
+            &|_, value| {
+                let (radius, rest) = parse_number(value)?;
+
+                // Triaxial bodies, like "13.1 x11.1 x9.3", take the radius of a sphere of the same volume
+                let mut axes = vec![radius];
+                let mut rest = rest;
+
+                while let Some((axis, r)) =
+                    rest.trim_start().strip_prefix('x').and_then(parse_number)
+                {
+                    axes.push(axis);
+                    rest = r;
+                }
+
+                Some(axes.iter().product::<f64>().powf(1.0 / axes.len() as f64))
+            },
+        ),
+        density: find(&["density", "mean density"], &number),
+        rotation_rate: find(&["sid. rot. rat", "rot. rate"], &number).or(find(
+            &[
+                "sidereal rot. period",
+                "sid. rot. period",
+                "adopted sid. rot. per",
+                "rotper",
+            ],
+            &|key, value| {
+                let (period, unit) = parse_number(value)?;
+                let unit = unit.trim_start();
+
+                let hours = if unit.starts_with('d') {
+                    period * 24.0
+                } else if unit.starts_with('h') || key.eq_ignore_ascii_case("rotper") {
+                    period
+                } else {
+                    return None;
+                };
+
+                Some(TAU / (hours * 3600.0))
+            },
+        )),
+        obliquity: find(
+            &["obliquity to orbit", "obliquity to ecliptic"],
+            &|_, value| {
+                let (obliquity, unit) = parse_number(value)?;
+
+                // Mercury's is given in arcminutes
+                if unit.starts_with('\'') {
+                    Some(obliquity / 60.0)
+                } else {
+                    Some(obliquity)
+                }
+            },
+        ),
+        j2: find(&["j2"], &|key, value| {
+            let (j2, _) = parse_number(value)?;
+            Some(j2 * 10f64.powi(power_of_ten(key).unwrap_or(0)))
+        }),
+        temperature: find(
+            &[
+                "mean surface temp",
+                "mean temperature",
+                "atmos. temp. (1 bar)",
+            ],
+            &number,
+        ),
+    }
+}
+
+/// Every "key = value" on the line whose key starts with `label`, ignoring case.
+/// The value runs to the end of the line, as we cannot tell where it ends and the next key begins.
+fn key_values<'a>(line: &'a str, label: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+    let lowercase = line.to_ascii_lowercase();
+
+    let starts: Vec<usize> = lowercase
+        .match_indices(label)
+        .map(|(i, _)| i)
+        .filter(|&i| {
+            // The label has to start a key, and be a whole word of it
+            let before = lowercase[..i].chars().next_back();
+            let after = lowercase[i + label.len()..].chars().next();
+
+            before.is_none_or(|c| c.is_whitespace())
+                && after.is_none_or(|c| !c.is_ascii_alphanumeric() || label.ends_with('.'))
+        })
+        .collect();
+
+    starts.into_iter().filter_map(move |start| {
+        // The "=" that ends the key, skipping any inside parentheses, like in "Density (R=1195 km)"
+        let mut depth = 0;
+        let end = line[start..].char_indices().find_map(|(i, c)| match c {
+            '(' => {
+                depth += 1;
+                None
+            }
+            ')' => {
+                depth -= 1;
+                None
+            }
+            '=' if depth <= 0 => Some(start + i),
+            _ => None,
+        })?;
+
+        let key = line[start..end].trim();
+
+        // Too long to be a key, we must have found the label in some text
+        if key.len() > 32 {
+            return None;
+        }
+
+        Some((key, &line[end + 1..]))
+    })
+}
+
+/// The exponent of a "10^n" in a key, like "Mass x10^24 (kg)".
+fn power_of_ten(key: &str) -> Option<i32> {
+    let (_, exponent) = key.split_once("10^")?;
+    let digits: String = exponent
+        .chars()
+        .enumerate()
+        .take_while(|(i, c)| c.is_ascii_digit() || (*i == 0 && *c == '-'))
+        .map(|(_, c)| c)
+        .collect();
+
+    digits.parse().ok()
+}
+
+/// Parses the number a value starts with, like "1737.53+-0.03", "~1988410", "2.6616995x10^-6"
+/// or "1.08 (10^-4)", returning the rest of the value after it.
+fn parse_number(value: &str) -> Option<(f64, &str)> {
+    let value = value.trim_start().trim_start_matches('~').trim_start();
+
+    let candidate = value
+        .char_indices()
+        .take_while(|(_, c)| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+        .map(|(i, c)| i + c.len_utf8())
+        .last()?;
+
+    // The longest prefix that is a number, "1737.53+-0.03" is 1737.53 with an uncertainty
+    let (number, end) = (1..=candidate)
+        .rev()
+        .find_map(|end| value[..end].parse::<f64>().ok().map(|n| (n, end)))?;
+
+    let rest = &value[end..];
+
+    // A trailing power of ten
+    for prefix in ["x10^", "x 10^", " (10^", "(10^"] {
+        if let Some(exponent) = rest.strip_prefix(prefix) {
+            if let Some(power) = power_of_ten(&format!("10^{}", exponent)) {
+                let end = exponent.find(|c: char| c != '-' && !c.is_ascii_digit());
+                let rest = end.map(|e| &exponent[e..]).unwrap_or("");
+
+                return Some((number * 10f64.powi(power), rest.trim_start_matches(')')));
+            }
+        }
+    }
+
+    Some((number, rest))
+}
+
+/// NAIF ID of the planet a natural satellite orbits, e.g. 399 (Earth) for 301 (Moon).
+pub fn naif_parent(id: u32) -> Option<u32> {
+    if (100..1000).contains(&id) && id % 100 != 99 && !id.is_multiple_of(100) {
+        Some(id / 100 * 100 + 99)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::simulation::units::DAY;
+
+    fn response(name: &str) -> HorizonsResponse {
+        let text = fs::read_to_string(format!("data/responses/{}.txt", name)).unwrap();
+        parse_response(&text).unwrap()
+    }
+
+    fn assert_close(value: Option<f64>, expected: f64) {
+        let value = value.expect("missing value");
+
+        assert!(
+            (value - expected).abs() <= expected.abs() * 1e-9,
+            "{} != {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn parses_every_response() {
+        for entry in fs::read_dir("data/responses").unwrap() {
+            let path = entry.unwrap().path();
+            let response = parse_response(&fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+            assert_eq!(response.vectors.len(), 1, "{}", path.display());
+            assert_eq!(response.vectors[0].states.len(), 1, "{}", path.display());
+            assert_eq!(
+                response.vectors[0].center.id,
+                Some(10),
+                "{}",
+                path.display()
+            );
+
+            // The Sun has no orbit around itself
+            let elements = if response.target.as_ref().unwrap().id == Some(10) {
+                0
+            } else {
+                1
+            };
+
+            assert_eq!(response.elements.len(), elements, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn parses_tables() {
+        let earth = response("Earth – 399");
+
+        assert_eq!(
+            earth.target,
+            Some(HorizonsBody {
+                name: "Earth".to_string(),
+                id: Some(399)
+            })
+        );
+
+        let state = earth.vectors[0].states[0];
+        assert_eq!(state.julian_date, 2460676.5);
+        assert_eq!(state.position.x, -1.786834409731047E-01);
+        assert_eq!(state.velocity.z, 5.457174067040888E-09);
+
+        let elements = earth.elements[0].elements[0];
+        assert_eq!(elements.eccentricity, 1.756190256786036E-02);
+        assert_eq!(elements.time_of_periapsis, 2.460679129154102E+06);
+        assert_eq!(elements.sidereal_orbit_period, 3.657564355602954E+02);
+    }
+
+    #[test]
+    fn parses_body_names() {
+        let eris = HorizonsBody::parse("136199 Eris (2003 UB313)        {source: JPL#93}");
+        assert_eq!(eris.name, "Eris");
+        assert_eq!(eris.id, Some(136199));
+
+        let ceres = HorizonsBody::parse(" (2000001)                      {source: dawn_final}");
+        assert_eq!(ceres.name, "");
+        assert_eq!(ceres.id, Some(2000001));
+    }
+
+    #[test]
+    fn parses_earth_properties() {
+        let earth = response("Earth – 399").properties;
+
+        assert_close(earth.gm, 398600.435436);
+        assert_close(earth.mass, 5.97219e24);
+        assert_close(earth.radius, 6371.01);
+        assert_close(earth.density, 5.51);
+        assert_close(earth.rotation_rate, 0.00007292115);
+        assert_close(earth.obliquity, 23.4392911);
+        assert_close(earth.j2, 0.00108262545);
+        assert_close(earth.temperature, 287.6);
+    }
+
+    #[test]
+    fn parses_unusual_properties() {
+        let moon = response("Moon – 301").properties;
+        assert_close(moon.mass, 7.349e22);
+        assert_close(moon.density, 3.3437);
+
+        // GM and the listed mass agree to the precision of the latter
+        let mass = moon.derived_mass().unwrap();
+        assert!((mass / 7.349e22 - 1.0).abs() < 1e-3);
+
+        let phobos = response("Phobos – 401").properties;
+        assert_close(phobos.mass, 1.08e16);
+        assert_close(phobos.radius, (13.1f64 * 11.1 * 9.3).cbrt());
+
+        let pluto = response("Pluto – 999").properties;
+        assert_close(pluto.gm, 869.326);
+        assert_close(pluto.density, 1.86);
+        assert_close(pluto.rotation_rate, 0.0000113856);
+
+        assert_close(response("Mercury – 199").properties.obliquity, 2.11 / 60.0);
+        assert_close(
+            response("Uranus – 799").properties.rotation_rate,
+            -0.000101237,
+        );
+        assert_close(
+            response("Sun – 10").properties.rotation_rate,
+            TAU / (25.38 * DAY),
+        );
+        assert_close(
+            response("Eris – 136199").properties.rotation_rate,
+            TAU / (25.9 * 3600.0),
+        );
+
+        let adrastea = response("Adrastea – 515").properties;
+        assert_close(adrastea.radius, 8.2);
+        assert_eq!(adrastea.density, None);
+        assert_eq!(adrastea.j2, None);
+
+        let eris = response("Eris – 136199").properties;
+        assert_eq!(eris.gm, None);
+        assert_eq!(eris.radius, None);
+    }
+
+    #[test]
+    fn finds_parents() {
+        assert_eq!(naif_parent(301), Some(399));
+        assert_eq!(naif_parent(516), Some(599));
+        assert_eq!(naif_parent(599), None);
+        assert_eq!(naif_parent(10), None);
+        assert_eq!(naif_parent(136199), None);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod batch;
+pub mod cli;
+pub mod horizons;
+pub mod material;
+pub mod mpcorb;
+pub mod simulation;
+pub mod spawn;
+pub mod spk;
+pub mod ui;
+#[cfg(test)]
+mod validation;
@@ -1,17 +1,43 @@
+use std::{env, process::ExitCode};
+
 use bevy::prelude::*;
 
-use material::saturn_rings::SaturnRingMaterial;
-use simulation::SimulationPlugin;
-use spawn::SpawnBodyPlugin;
-use ui::SimulationUiPlugin;
+use solar_system::{
+    batch::{self, EXIT_ERROR, EXIT_USAGE},
+    cli::{Cli, Command, USAGE},
+    material::saturn_rings::SaturnRingMaterial,
+    simulation::SimulationPlugin,
+    spawn::SpawnBodyPlugin,
+    ui::SimulationUiPlugin,
+};
+
+fn main() -> ExitCode {
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(Command::Run(cli)) => cli,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\nSee solar-system --help", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
 
-mod material;
-mod simulation;
-mod spawn;
-mod ui;
+    if cli.headless.enabled {
+        return batch::main(&cli);
+    }
 
-fn main() {
-    App::new()
+    // Before opening a window, so a scenario that does not load is not a blank screen
+    let scenario = match cli.scenario() {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let exit = App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(bevy_flycam::NoCameraPlayerPlugin)
         .add_plugins(bevy_mod_imgui::ImguiPlugin::default())
@@ -26,5 +52,12 @@ fn main() {
             color: Color::BLACK,
             brightness: 0.0,
         })
+        .insert_resource(scenario)
+        .insert_resource(*cli)
         .run();
+
+    match exit {
+        AppExit::Success => ExitCode::SUCCESS,
+        AppExit::Error(code) => ExitCode::from(code.get()),
+    }
 }
@@ -0,0 +1,662 @@
+//! Imports minor planets from the orbit files of the Minor Planet Center, either `MPCORB.DAT` or
+//! `mpcorb_extended.json`, so we can add the main belt, near-Earth objects and trojans in bulk.
+//!
+//! The elements are heliocentric, in the ecliptic frame of J2000, like the ones we get from Horizons.
+//! The bodies are placed on their orbits from the epoch of the file when the data is loaded.
+
+use std::{f64::consts::PI, fs, path::Path, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::simulation::{
+    body::{Body, BodyData, BodyMetadata, BodyOrbitalElements, BodyType},
+    data::Dataset,
+    time::JulianDate,
+    units::{UnitSystem, ASTRONOMICAL_UNIT, DAY, KILOMETER},
+};
+
+/// NAIF IDs of numbered minor planets are their number plus this
+const NAIF_OFFSET: u32 = 2_000_000;
+
+/// Geometric albedo and density we assume, as the files give neither
+const ALBEDO: f64 = 0.14;
+const DENSITY: f64 = 2000.0;
+
+const COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// Orbit classes as the MPC defines them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrbitClass {
+    Atira,
+    Aten,
+    Apollo,
+    Amor,
+    /// Perihelion within 1.665 AU, but not a near-Earth object
+    MarsCrosser,
+    Hungaria,
+    MainBelt,
+    Phocaea,
+    Hilda,
+    JupiterTrojan,
+    /// Beyond Jupiter, like centaurs and trans-Neptunian objects
+    Distant,
+    Unclassified,
+}
+
+impl OrbitClass {
+    /// From the lowest 6 bits of the flags in `MPCORB.DAT`.
+    fn from_code(code: u16, semi_major_axis: f64) -> Self {
+        match code {
+            1 => Self::Atira,
+            2 => Self::Aten,
+            3 => Self::Apollo,
+            4 => Self::Amor,
+            5 => Self::MarsCrosser,
+            6 => Self::Hungaria,
+            7 => Self::Phocaea,
+            8 => Self::Hilda,
+            9 => Self::JupiterTrojan,
+            10 => Self::Distant,
+            // The main belt is not flagged
+            _ if (2.0..3.3).contains(&semi_major_axis) => Self::MainBelt,
+            _ => Self::Unclassified,
+        }
+    }
+
+    /// From `Orbit_type` in `mpcorb_extended.json`.
+    fn from_description(description: &str) -> Self {
+        match description {
+            "Atira" => Self::Atira,
+            "Aten" => Self::Aten,
+            "Apollo" => Self::Apollo,
+            "Amor" => Self::Amor,
+            "Object with perihelion distance < 1.665 AU" => Self::MarsCrosser,
+            "Hungaria" => Self::Hungaria,
+            "MBA" => Self::MainBelt,
+            "Phocaea" => Self::Phocaea,
+            "Hilda" => Self::Hilda,
+            "Jupiter Trojan" => Self::JupiterTrojan,
+            "Distant Object" => Self::Distant,
+            _ => Self::Unclassified,
+        }
+    }
+
+    /// Parses a class for filtering, "neo" stands for all four near-Earth classes.
+    pub fn parse(name: &str) -> Option<Vec<Self>> {
+        Some(match name.trim().to_ascii_lowercase().as_str() {
+            "neo" => vec![Self::Atira, Self::Aten, Self::Apollo, Self::Amor],
+            "atira" => vec![Self::Atira],
+            "aten" => vec![Self::Aten],
+            "apollo" => vec![Self::Apollo],
+            "amor" => vec![Self::Amor],
+            "mars_crosser" => vec![Self::MarsCrosser],
+            "hungaria" => vec![Self::Hungaria],
+            "main_belt" => vec![Self::MainBelt],
+            "phocaea" => vec![Self::Phocaea],
+            "hilda" => vec![Self::Hilda],
+            "trojan" => vec![Self::JupiterTrojan],
+            "distant" => vec![Self::Distant],
+            _ => return None,
+        })
+    }
+}
+
+/// One object, with its osculating elements. Angles are in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinorPlanet {
+    pub number: Option<u32>,
+    pub name: Option<String>,
+
+    /// Provisional designation, like "2024 YR4"
+    pub designation: Option<String>,
+
+    /// Absolute magnitude
+    pub magnitude: Option<f32>,
+
+    /// Julian Date (TT), which we take as TDB
+    pub epoch: f64,
+
+    pub mean_anomaly: f64,
+    pub argument_of_perihelion: f64,
+    pub longitude_of_ascending_node: f64,
+    pub inclination: f64,
+    pub eccentricity: f64,
+
+    /// Degrees per day
+    pub mean_motion: f64,
+
+    /// AU
+    pub semi_major_axis: f64,
+
+    pub orbit_class: OrbitClass,
+}
+
+impl MinorPlanet {
+    /// The name if it has one, else the provisional designation.
+    pub fn display_name(&self) -> String {
+        match (&self.name, &self.designation, self.number) {
+            (Some(name), _, _) => name.clone(),
+            (None, Some(designation), _) => designation.clone(),
+            (None, None, Some(number)) => format!("({})", number),
+            (None, None, None) => "<unknown>".to_string(),
+        }
+    }
+
+    /// Whether `designation` is our number, name or provisional designation.
+    pub fn is_designated(&self, designation: &str) -> bool {
+        let designation = designation
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')');
+
+        self.number.is_some_and(|n| designation.parse() == Ok(n))
+            || self
+                .name
+                .as_ref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(designation))
+            || self
+                .designation
+                .as_ref()
+                .is_some_and(|d| d.eq_ignore_ascii_case(designation))
+    }
+
+    /// Diameter estimated from the absolute magnitude, km.
+    pub fn diameter(&self) -> Option<f64> {
+        self.magnitude
+            .map(|h| 1329.0 / ALBEDO.sqrt() * 10f64.powf(-h as f64 / 5.0))
+    }
+
+    /// A body on the orbit, in the units our data is authored in.
+    pub fn to_body(&self, units: UnitSystem) -> Body {
+        let (length, mass, time) = units.base_units();
+
+        let radius = self.diameter().unwrap_or(1.0) * KILOMETER / 2.0;
+        let au = ASTRONOMICAL_UNIT / length;
+
+        Body {
+            data: BodyData {
+                position: Vec3::NAN,
+                velocity: Vec3::NAN,
+                mass: (4.0 / 3.0 * PI * radius.powi(3) * DENSITY / mass) as f32,
+                radius: (radius / length) as f32,
+                density: (DENSITY / 1000.0) as f32,
+                orbital_elements: Some(BodyOrbitalElements {
+                    eccentricity: self.eccentricity as f32,
+                    periapsis_distance: None,
+                    semi_major_axis: Some((self.semi_major_axis * au) as f32),
+                    inclination: self.inclination as f32,
+                    longitude_of_ascending_node: self.longitude_of_ascending_node as f32,
+                    true_anomaly: None,
+                    mean_anomaly: Some(self.mean_anomaly as f32),
+                    argument_of_perifocus: self.argument_of_perihelion as f32,
+                    epoch: Some(self.epoch),
+                    sidereal_orbit_period: (self.mean_motion > 0.0)
+                        .then(|| (360.0 / self.mean_motion * DAY / time) as f32),
+                }),
+                ..default()
+            },
+            metadata: BodyMetadata {
+                name: Some(self.display_name()),
+                id: self.number.map(|n| n + NAIF_OFFSET),
+                color: Color::srgba(COLOR[0], COLOR[1], COLOR[2], COLOR[3]),
+                body_type: BodyType::Other,
+                ..default()
+            },
+            satellites: None,
+        }
+    }
+}
+
+/// Which objects of a file to import. An empty filter selects everything.
+#[derive(Debug, Clone, Default)]
+pub struct MinorPlanetFilter {
+    /// Numbers, names or provisional designations
+    pub designations: Vec<String>,
+
+    /// Only objects at least this bright
+    pub max_magnitude: Option<f32>,
+
+    pub classes: Vec<OrbitClass>,
+
+    /// At most this many, the first ones in the file
+    pub limit: Option<usize>,
+}
+
+impl MinorPlanetFilter {
+    pub fn matches(&self, planet: &MinorPlanet) -> bool {
+        (self.designations.is_empty() || self.designations.iter().any(|d| planet.is_designated(d)))
+            && self
+                .max_magnitude
+                .is_none_or(|max| planet.magnitude.is_some_and(|h| h <= max))
+            && (self.classes.is_empty() || self.classes.contains(&planet.orbit_class))
+    }
+
+    pub fn select(&self, planets: Vec<MinorPlanet>) -> Vec<MinorPlanet> {
+        planets
+            .into_iter()
+            .filter(|p| self.matches(p))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// A file to import minor planets from on startup.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MinorPlanetImport {
+    pub path: Option<PathBuf>,
+    pub filter: MinorPlanetFilter,
+}
+
+/// Reads either format, telling them apart by the extension.
+pub fn load(path: &Path) -> Result<Vec<MinorPlanet>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+    let planets = match path.extension().is_some_and(|e| e == "json") {
+        true => parse_extended_json(&text),
+        false => parse_mpcorb(&text),
+    };
+
+    planets.map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Parses the fixed width format of `MPCORB.DAT`, with or without its header.
+pub fn parse_mpcorb(text: &str) -> Result<Vec<MinorPlanet>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    // The header ends with a line of dashes
+    let start = lines
+        .iter()
+        .position(|l| l.starts_with("-----"))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let mut planets = Vec::new();
+
+    for (number, line) in lines.iter().enumerate().skip(start) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        planets.push(parse_mpcorb_line(line).map_err(|e| format!("line {}: {}", number + 1, e))?);
+    }
+
+    Ok(planets)
+}
+
+fn parse_mpcorb_line(line: &str) -> Result<MinorPlanet, String> {
+    // Columns as documented by the MPC, counting from 1
+    let column = |start: usize, end: usize| {
+        line.get(start - 1..end.min(line.len()))
+            .unwrap_or("")
+            .trim()
+    };
+    let number = |start: usize, end: usize, name: &str| {
+        column(start, end)
+            .parse::<f64>()
+            .map_err(|_| format!("invalid {} \"{}\"", name, column(start, end)))
+    };
+
+    let packed = column(1, 7);
+    let (number_, designation) =
+        unpack_designation(packed).ok_or(format!("invalid designation \"{}\"", packed))?;
+
+    let epoch =
+        unpack_epoch(column(21, 25)).ok_or(format!("invalid epoch \"{}\"", column(21, 25)))?;
+
+    let semi_major_axis = number(93, 103, "semi-major axis")?;
+    let flags = u16::from_str_radix(column(162, 165), 16).unwrap_or(0);
+
+    // Like "(433) Eros", or just the provisional designation
+    let readable = column(167, 194);
+    let name = match readable.split_once(") ") {
+        Some((_, name)) if readable.starts_with('(') => Some(name.trim().to_string()),
+        _ => None,
+    };
+
+    Ok(MinorPlanet {
+        number: number_,
+        name,
+        designation,
+        magnitude: column(9, 13).parse().ok(),
+        epoch,
+        mean_anomaly: number(27, 35, "mean anomaly")?,
+        argument_of_perihelion: number(38, 46, "argument of perihelion")?,
+        longitude_of_ascending_node: number(49, 57, "longitude of the ascending node")?,
+        inclination: number(60, 68, "inclination")?,
+        eccentricity: number(71, 79, "eccentricity")?,
+        mean_motion: number(81, 91, "mean motion")?,
+        semi_major_axis,
+        orbit_class: OrbitClass::from_code(flags & 0x3f, semi_major_axis),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ExtendedOrbit {
+    number: Option<String>,
+    name: Option<String>,
+    #[serde(rename = "Principal_desig")]
+    principal_designation: Option<String>,
+    #[serde(rename = "H")]
+    magnitude: Option<f32>,
+    epoch: f64,
+    #[serde(rename = "M")]
+    mean_anomaly: f64,
+    #[serde(rename = "Peri")]
+    argument_of_perihelion: f64,
+    #[serde(rename = "Node")]
+    longitude_of_ascending_node: f64,
+    #[serde(rename = "i")]
+    inclination: f64,
+    #[serde(rename = "e")]
+    eccentricity: f64,
+    #[serde(rename = "n")]
+    mean_motion: f64,
+    #[serde(rename = "a")]
+    semi_major_axis: f64,
+    #[serde(rename = "Orbit_type")]
+    orbit_type: Option<String>,
+}
+
+/// Parses `mpcorb_extended.json`, a list of objects with the same elements as `MPCORB.DAT`.
+pub fn parse_extended_json(text: &str) -> Result<Vec<MinorPlanet>, String> {
+    let orbits: Vec<ExtendedOrbit> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    Ok(orbits
+        .into_iter()
+        .map(|o| MinorPlanet {
+            number: o
+                .number
+                .and_then(|n| n.trim_matches(['(', ')']).parse().ok()),
+            name: o.name,
+            designation: o.principal_designation,
+            magnitude: o.magnitude,
+            epoch: o.epoch,
+            mean_anomaly: o.mean_anomaly,
+            argument_of_perihelion: o.argument_of_perihelion,
+            longitude_of_ascending_node: o.longitude_of_ascending_node,
+            inclination: o.inclination,
+            eccentricity: o.eccentricity,
+            mean_motion: o.mean_motion,
+            semi_major_axis: o.semi_major_axis,
+            orbit_class: o
+                .orbit_type
+                .as_deref()
+                .map(OrbitClass::from_description)
+                .unwrap_or(OrbitClass::Unclassified),
+        })
+        .collect())
+}
+
+/// 0-9, A-Z and a-z, as used in packed numbers and dates.
+fn base62(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => Some(c as u32 - '0' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32 + 10),
+        'a'..='z' => Some(c as u32 - 'a' as u32 + 36),
+        _ => None,
+    }
+}
+
+/// Unpacks a packed designation into the number and the provisional designation,
+/// e.g. "00433" is 433, "m6958" is 486958 and "K24Y04R" is 2024 YR4.
+pub fn unpack_designation(packed: &str) -> Option<(Option<u32>, Option<String>)> {
+    let chars: Vec<char> = packed.chars().collect();
+
+    match chars.len() {
+        // Numbered, above 619999 as "~" and four base 62 digits
+        5 if chars[0] == '~' => {
+            let number = chars[1..]
+                .iter()
+                .try_fold(0, |n, c| Some(n * 62 + base62(*c)?))?;
+
+            Some((Some(number + 620_000), None))
+        }
+        5 => {
+            let rest: u32 = packed[1..].parse().ok()?;
+
+            Some((Some(base62(chars[0])? * 10_000 + rest), None))
+        }
+        7 => {
+            let century = match chars[0] {
+                'I' => 18,
+                'J' => 19,
+                'K' => 20,
+                _ => return None,
+            };
+            let year: u32 = packed[1..3].parse().ok()?;
+            let cycle = base62(chars[4])? * 10 + chars[5].to_digit(10)?;
+
+            let mut designation = format!("{}{:02} {}{}", century, year, chars[3], chars[6]);
+
+            if cycle > 0 {
+                designation += &cycle.to_string();
+            }
+
+            Some((None, Some(designation)))
+        }
+        _ => None,
+    }
+}
+
+/// Unpacks a packed date, e.g. "K2555" is 2025-05-05, into a Julian Date at 0h.
+pub fn unpack_epoch(packed: &str) -> Option<f64> {
+    let chars: Vec<char> = packed.chars().collect();
+
+    if chars.len() != 5 {
+        return None;
+    }
+
+    let century = match chars[0] {
+        'I' => 1800,
+        'J' => 1900,
+        'K' => 2000,
+        _ => return None,
+    };
+    let year = century + packed[1..3].parse::<i32>().ok()?;
+    let month = base62(chars[3])?;
+    let day = base62(chars[4])?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some(JulianDate::from_calendar(year, month, day, 0, 0, 0.0).value())
+}
+
+/// Adds the planets to our data, skipping any we already have.
+/// Returns how many were added.
+pub fn import_into(dataset: &mut Dataset, planets: &[MinorPlanet], units: UnitSystem) -> usize {
+    let mut added = 0;
+
+    for planet in planets {
+        let body = planet.to_body(units);
+
+        let exists = dataset.bodies.iter().any(|b| {
+            (b.metadata.id.is_some() && b.metadata.id == body.metadata.id)
+                || b.metadata.name == body.metadata.name
+        });
+
+        if exists {
+            info!("{} is already in our data", planet.display_name());
+            continue;
+        }
+
+        dataset.bodies.push(body);
+        added += 1;
+    }
+
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<MinorPlanet> {
+        load(Path::new("data/mpcorb/sample.dat")).unwrap()
+    }
+
+    #[test]
+    fn unpacks_designations() {
+        assert_eq!(unpack_designation("00433"), Some((Some(433), None)));
+        assert_eq!(unpack_designation("A0001"), Some((Some(100001), None)));
+        assert_eq!(unpack_designation("m6958"), Some((Some(486958), None)));
+        assert_eq!(unpack_designation("~0000"), Some((Some(620000), None)));
+        assert_eq!(
+            unpack_designation("K24Y04R"),
+            Some((None, Some("2024 YR4".to_string())))
+        );
+        assert_eq!(
+            unpack_designation("J95X00A"),
+            Some((None, Some("1995 XA".to_string())))
+        );
+        assert_eq!(
+            unpack_designation("K07Tf8A"),
+            Some((None, Some("2007 TA418".to_string())))
+        );
+        assert_eq!(unpack_designation("12"), None);
+    }
+
+    #[test]
+    fn unpacks_epochs() {
+        assert_eq!(unpack_epoch("K2555"), Some(2460800.5));
+        assert_eq!(unpack_epoch("J981V"), Some(2450844.5));
+        assert_eq!(unpack_epoch("K25D1"), None);
+    }
+
+    #[test]
+    fn parses_mpcorb() {
+        let planets = sample();
+
+        assert_eq!(planets.len(), 7);
+
+        let eros = &planets[2];
+        assert_eq!(eros.number, Some(433));
+        assert_eq!(eros.name.as_deref(), Some("Eros"));
+        assert_eq!(eros.magnitude, Some(10.38));
+        assert_eq!(eros.epoch, 2460800.5);
+        assert_eq!(eros.eccentricity, 0.2228359);
+        assert_eq!(eros.semi_major_axis, 1.4580574);
+        assert_eq!(eros.orbit_class, OrbitClass::Amor);
+
+        let classes: Vec<_> = planets.iter().map(|p| p.orbit_class).collect();
+        assert_eq!(
+            classes,
+            [
+                OrbitClass::MainBelt,
+                OrbitClass::MainBelt,
+                OrbitClass::Amor,
+                OrbitClass::JupiterTrojan,
+                OrbitClass::Aten,
+                OrbitClass::Distant,
+                OrbitClass::Apollo,
+            ]
+        );
+
+        let yr4 = &planets[6];
+        assert_eq!(yr4.number, None);
+        assert_eq!(yr4.display_name(), "2024 YR4");
+    }
+
+    #[test]
+    fn parses_extended_json() {
+        let planets = load(Path::new("data/mpcorb/sample.json")).unwrap();
+
+        assert_eq!(planets.len(), 3);
+
+        // The same objects as in the fixed width sample
+        let sample = sample();
+        for (planet, expected) in planets.iter().zip([&sample[0], &sample[2], &sample[6]]) {
+            assert_eq!(planet.number, expected.number);
+            assert_eq!(planet.display_name(), expected.display_name());
+            assert_eq!(planet.epoch, expected.epoch);
+            assert_eq!(planet.mean_anomaly, expected.mean_anomaly);
+            assert_eq!(planet.semi_major_axis, expected.semi_major_axis);
+            assert_eq!(planet.orbit_class, expected.orbit_class);
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let names = |filter: MinorPlanetFilter| {
+            filter
+                .select(sample())
+                .iter()
+                .map(|p| p.display_name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(MinorPlanetFilter::default()).len(), 7);
+
+        assert_eq!(
+            names(MinorPlanetFilter {
+                classes: OrbitClass::parse("neo").unwrap(),
+                ..default()
+            }),
+            ["Eros", "Apophis", "2024 YR4"]
+        );
+
+        assert_eq!(
+            names(MinorPlanetFilter {
+                max_magnitude: Some(8.0),
+                ..default()
+            }),
+            ["Ceres", "Pallas", "Hektor"]
+        );
+
+        assert_eq!(
+            names(MinorPlanetFilter {
+                designations: vec!["433".into(), "apophis".into(), "2024 yr4".into()],
+                limit: Some(2),
+                ..default()
+            }),
+            ["Eros", "Apophis"]
+        );
+    }
+
+    /// Imported bodies have to end up on their orbits once our data is resolved.
+    #[test]
+    fn imports_into_dataset() {
+        use crate::simulation::{
+            data::{load_data, prepare_dataset, DATA_PATH},
+            settings::SimulationParameters,
+        };
+
+        let mut dataset = load_data(Path::new(DATA_PATH)).unwrap();
+        let count = dataset.bodies.len();
+
+        // Ceres is already in our data
+        let added = import_into(&mut dataset, &sample(), UnitSystem::Astronomical);
+        assert_eq!(added, 6);
+        assert_eq!(dataset.bodies.len(), count + 6);
+
+        let parameters = SimulationParameters::default();
+        let dataset = prepare_dataset(dataset, &parameters);
+
+        let sun = dataset.bodies[0].data.position;
+
+        for planet in &sample()[1..] {
+            let body = dataset
+                .bodies
+                .iter()
+                .find(|b| b.metadata.name == Some(planet.display_name()))
+                .unwrap();
+
+            let distance = (body.data.position - sun).length() / parameters.unit_scale;
+            let (perihelion, aphelion) = (
+                planet.semi_major_axis * (1.0 - planet.eccentricity),
+                planet.semi_major_axis * (1.0 + planet.eccentricity),
+            );
+
+            assert!(
+                (perihelion * 0.999..=aphelion * 1.001).contains(&(distance as f64)),
+                "{} is {} AU from the Sun",
+                planet.display_name(),
+                distance
+            );
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{body::Body, time::SimulationTime};
+
+#[derive(Debug, Clone)]
+pub struct CloseApproach {
+    pub body: String,
+    pub other: String,
+
+    /// Julian Date
+    pub time: f64,
+
+    /// Astronomical Units
+    pub distance: f32,
+
+    /// Distance in the radius of the other body, infinite if it has no radius
+    pub distance_in_radii: f32,
+
+    /// Astronomical Units per day
+    pub relative_speed: f32,
+}
+
+/// Relative state of a pair on the previous physics step.
+#[derive(Debug, Clone, Copy)]
+struct RelativeState {
+    time: f64,
+    position: Vec3,
+    velocity: Vec3,
+}
+
+/// Closest approaches of the tracked bodies to every other body.
+#[derive(Resource, Debug)]
+pub struct CloseApproaches {
+    pub tracked: Vec<Entity>,
+
+    /// Approaches farther than this are not recorded, AU
+    pub threshold: f32,
+
+    pub approaches: Vec<CloseApproach>,
+
+    /// Keyed by (tracked body, other body)
+    previous: HashMap<(Entity, Entity), RelativeState>,
+}
+
+impl Default for CloseApproaches {
+    fn default() -> Self {
+        Self {
+            tracked: Vec::new(),
+            threshold: 0.05,
+            approaches: Vec::new(),
+            previous: HashMap::new(),
+        }
+    }
+}
+
+impl CloseApproaches {
+    pub fn track(&mut self, entity: Entity) {
+        if !self.tracked.contains(&entity) {
+            self.tracked.push(entity);
+        }
+    }
+
+    pub fn untrack(&mut self, entity: Entity) {
+        self.tracked.retain(|e| *e != entity);
+        self.previous.retain(|(e, _), _| *e != entity);
+    }
+
+    pub fn clear(&mut self) {
+        self.approaches.clear();
+    }
+}
+
+/// Finds the closest approach between two relative states, a step apart,
+/// given that the range rate turns from negative to positive between them.
+/// The crossing is located by linear interpolation of the range rate,
+/// and the relative position there by cubic Hermite interpolation.
+/// Returns (time, distance, relative speed).
+fn refine_minimum(from: &RelativeState, to: &RelativeState) -> (f64, f32, f32) {
+    let step = (to.time - from.time) as f32;
+
+    let rate_from = from.position.dot(from.velocity);
+    let rate_to = to.position.dot(to.velocity);
+
+    let s = if rate_to == rate_from {
+        0.0
+    } else {
+        (rate_from / (rate_from - rate_to)).clamp(0.0, 1.0)
+    };
+
+    let (s2, s3) = (s * s, s * s * s);
+
+    let position = from.position * (2.0 * s3 - 3.0 * s2 + 1.0)
+        + from.velocity * step * (s3 - 2.0 * s2 + s)
+        + to.position * (-2.0 * s3 + 3.0 * s2)
+        + to.velocity * step * (s3 - s2);
+
+    let velocity = from.velocity.lerp(to.velocity, s);
+
+    (
+        from.time + (to.time - from.time) * s as f64,
+        position.length(),
+        velocity.length(),
+    )
+}
+
+pub fn close_approach_system(
+    query: Query<(&Transform, &Body, Entity)>,
+    mut approaches: ResMut<CloseApproaches>,
+    time: Res<SimulationTime>,
+) {
+    let time = time.julian_date();
+
+    let name = |body: &Body| {
+        body.metadata
+            .name
+            .clone()
+            .unwrap_or("<unknown>".to_string())
+    };
+
+    let tracked = approaches.tracked.clone();
+
+    for entity in tracked {
+        let Ok((transform, body, _)) = query.get(entity) else {
+            continue;
+        };
+
+        for (other_transform, other_body, other) in query.iter() {
+            if other == entity {
+                continue;
+            }
+
+            let state = RelativeState {
+                time,
+                position: transform.translation - other_transform.translation,
+                velocity: body.data.velocity - other_body.data.velocity,
+            };
+
+            let previous = approaches.previous.insert((entity, other), state);
+
+            let Some(previous) = previous else {
+                continue;
+            };
+
+            // Getting closer on the previous step, moving away now
+            if previous.position.dot(previous.velocity) >= 0.0
+                || state.position.dot(state.velocity) < 0.0
+            {
+                continue;
+            }
+
+            let (time, distance, relative_speed) = refine_minimum(&previous, &state);
+
+            if distance > approaches.threshold {
+                continue;
+            }
+
+            let approach = CloseApproach {
+                body: name(body),
+                other: name(other_body),
+                time,
+                distance,
+                distance_in_radii: if other_body.data.radius > 0.0 {
+                    distance / other_body.data.radius
+                } else {
+                    f32::INFINITY
+                },
+                relative_speed,
+            };
+
+            info!(
+                "Close approach of {} to {} at JD {:.4}: {:.6} AU ({:.1} radii), {:.6} AU/day",
+                approach.body,
+                approach.other,
+                approach.time,
+                approach.distance,
+                approach.distance_in_radii,
+                approach.relative_speed
+            );
+
+            approaches.approaches.push(approach);
+        }
+    }
+}
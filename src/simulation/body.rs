@@ -1,42 +1,82 @@
-use std::cell::{Ref, RefCell};
 use std::fmt;
 use std::sync::{Arc, RwLock};
 
 use bevy::{color::Color, prelude::*};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use super::settings::{FollowBody, SimulationParameters};
-use super::util::{deserialize_color, deserialize_satellites, deserialize_vec3};
+use super::conics::{HillSphere, SphereOfInfluence};
+use super::elements::OsculatingElements;
+use super::passage::PassageHistory;
+use super::physics::PhysicsState;
+use super::settings::FollowBody;
+use super::util::{deserialize_color, deserialize_satellites, deserialize_vec3, undefined_vec3};
 
 #[derive(Debug, Component, Clone, Deserialize)]
-#[require(Mesh3d, MeshMaterial3d<StandardMaterial>)]
+#[require(
+    Mesh3d,
+    MeshMaterial3d<StandardMaterial>,
+    SphereOfInfluence,
+    HillSphere,
+    OsculatingElements,
+    PassageHistory,
+    PhysicsState
+)]
 pub struct Body {
     pub data: BodyData,
     pub metadata: BodyMetadata,
 
     #[serde(default, deserialize_with = "deserialize_satellites")]
-    pub satellites: Option<Vec<Arc<RwLock<Body>>>>,
+    pub satellites: Option<Satellites>,
 }
 
-#[derive(Debug, Clone, Component, Copy, Deserialize)]
+/// The moons of a body, as read from the data.
+pub type Satellites = Vec<Arc<RwLock<Body>>>;
+
+/// Orbital elements as given by JPL Horizons. Angles are in degrees.
+#[derive(Debug, Clone, Component, Copy, Serialize, Deserialize)]
 pub struct BodyOrbitalElements {
+    #[serde(default)]
+    pub eccentricity: f32,
+
+    /// In Astronomical Units, only needed if `semi_major_axis` is not given
+    #[serde(default)]
+    pub periapsis_distance: Option<f32>,
+
+    /// In Astronomical Units, only needed if `periapsis_distance` is not given
+    #[serde(default)]
+    pub semi_major_axis: Option<f32>,
+
     /// Tilt of the body's orbit
     pub inclination: f32,
     pub longitude_of_ascending_node: f32,
-    pub true_anomaly: f32,
+
+    /// Either `true_anomaly` or `mean_anomaly` is needed to place the body on its orbit
+    #[serde(default)]
+    pub true_anomaly: Option<f32>,
+    #[serde(default)]
+    pub mean_anomaly: Option<f32>,
 
     /// AKA argument of periapsis
     pub argument_of_perifocus: f32,
+
+    /// Julian Date (TDB) at which the elements are valid, defaults to the epoch of our data
+    #[serde(default)]
+    pub epoch: Option<f64>,
+
+    /// In days, as given by JPL Horizons, for comparison with what we measure
+    #[serde(default)]
+    pub sidereal_orbit_period: Option<f32>,
 }
 
 #[derive(Debug, Clone, Component, Copy, Deserialize)]
 pub struct BodyData {
-    /// Absolute position in world space, in Astronomical Units
-    #[serde(deserialize_with = "deserialize_vec3")]
+    /// Absolute position in world space, in Astronomical Units.
+    /// If omitted from the data, it is derived from the orbital elements when loading.
+    #[serde(default = "undefined_vec3", deserialize_with = "deserialize_vec3")]
     pub position: Vec3,
 
     /// Astronomical Units per day
-    #[serde(deserialize_with = "deserialize_vec3")]
+    #[serde(default = "undefined_vec3", deserialize_with = "deserialize_vec3")]
     pub velocity: Vec3,
 
     /// Astronomical Units per day
@@ -89,20 +129,9 @@ impl Default for BodyData {
 }
 
 impl BodyData {
-    /// No need for it now, but might be useful in the future
-    pub fn downscaled(&self, parameters: &SimulationParameters) -> Self {
-        Self {
-            position: self.position,
-            velocity: self.velocity,
-            acceleration: self.acceleration,
-            rotation: self.rotation,
-            obliquity: self.obliquity,
-            mass: self.mass / parameters.mass_scale,
-            radius: self.radius / parameters.unit_scale,
-            temperature: self.temperature,
-            density: self.density,
-            orbital_elements: self.orbital_elements,
-        }
+    /// Whether the data specified the state vectors, or they need to be derived from the orbital elements.
+    pub fn has_state_vectors(&self) -> bool {
+        !self.position.is_nan() && !self.velocity.is_nan()
     }
 }
 
@@ -113,6 +142,13 @@ pub struct BodyMetadata {
     // TODO usize
     pub id: Option<u32>,
 
+    /// Name of the body we orbit, which our orbital elements are relative to.
+    /// Defaults to the body we are a satellite of, or the Sun.
+    pub parent: Option<String>,
+
+    /// Whether our position and velocity are given relative to our parent instead of in world space.
+    pub relative_to_parent: bool,
+
     #[serde(default, deserialize_with = "deserialize_color")]
     pub color: Color,
 
@@ -129,6 +165,8 @@ impl Default for BodyMetadata {
             color: Color::WHITE,
             name: None,
             id: None,
+            parent: None,
+            relative_to_parent: false,
             texture: None,
             body_type: BodyType::Unknown,
         }
@@ -148,12 +186,13 @@ impl MetaLoader {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum BodyType {
     Star,
     Planet,
     DwarfPlanet,
     Moon,
+    Spacecraft,
     Other,
     #[default]
     Unknown,
@@ -166,6 +205,7 @@ impl fmt::Display for BodyType {
             BodyType::Planet => "Planet",
             BodyType::DwarfPlanet => "Dwarf Planet",
             BodyType::Moon => "Moon",
+            BodyType::Spacecraft => "Spacecraft",
             BodyType::Other => "Other",
             BodyType::Unknown => "Unknown",
         };
@@ -186,31 +226,49 @@ pub struct DwarfPlanet {}
 #[derive(Debug, Component, Clone, Default, Copy)]
 pub struct Moon {}
 
+/// Treated as massless by the patched-conic approximation,
+/// and the only kind of body that may change its primary at a sphere of influence boundary.
+#[derive(Debug, Component, Clone, Default, Copy)]
+pub struct Spacecraft {}
+
 #[derive(Debug, Component, Clone, Default, Copy)]
 pub struct Other {}
 
+/// A massless body spawned to watch how it moves, like at a Lagrange point. It is not part of the system:
+/// it is not listed with the planets, nothing orbits it, and it is not exported.
+#[derive(Debug, Component, Clone, Default, Copy)]
+pub struct TestBody {}
+
 #[derive(Debug, Component, Clone, Default, Copy)]
 pub struct Unknown {}
 
+/// Explicit link from a satellite to the body it orbits, as given by our data.
+/// Unlike Bevy's `Parent`, this does not make our transform relative to the parent's.
+#[derive(Debug, Component, Clone, Copy, PartialEq)]
+pub struct ParentBody(pub Entity);
+
 #[derive(Resource)]
 pub struct Sun(pub Entity);
 
 pub fn follow_body_system(
     mut camera: Query<&mut Transform, With<Camera>>,
     query: Query<(&Transform, &Body), Without<Camera>>,
-    sun: Res<Sun>,
+    sun: Option<Res<Sun>>,
     follow: Res<FollowBody>,
 ) {
-    if follow.is_active && follow.entity.is_some() {
-        if let Ok((transform, body)) = query.get(follow.entity.unwrap()) {
-            let sun = query.get(sun.0);
+    if !follow.is_active {
+        return;
+    }
+
+    if let Some(entity) = follow.entity {
+        if let Ok((transform, body)) = query.get(entity) {
+            let sun_radius = sun
+                .and_then(|sun| query.get(sun.0).ok())
+                .map(|(_, b)| b.data.radius)
+                .unwrap_or(0.2);
+
             camera.single_mut().translation = Vec3 {
-                x: transform.translation.x
-                    + body.data.radius
-                    + match sun {
-                        Ok((_, b)) => b.data.radius,
-                        Err(_) => 0.2,
-                    },
+                x: transform.translation.x + body.data.radius + sun_radius,
                 y: transform.translation.y,
                 z: transform.translation.z,
             }
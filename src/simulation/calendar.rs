@@ -0,0 +1,422 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    fmt,
+    fs::File,
+    io::Write,
+    path::Path,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    body::{Body, Star},
+    conics::SphereOfInfluence,
+    orbit::world_to_ecliptic,
+    time::SimulationTime,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarEventKind {
+    /// The body passes between the observer and the Sun
+    InferiorConjunction,
+    /// The body passes behind the Sun
+    SuperiorConjunction,
+    Opposition,
+    /// Furthest east of the Sun, visible in the evening
+    GreatestEasternElongation,
+    /// Furthest west of the Sun, visible in the morning
+    GreatestWesternElongation,
+    /// Apparent motion turns from prograde to retrograde
+    StationaryRetrograde,
+    /// Apparent motion turns from retrograde to prograde
+    StationaryDirect,
+}
+
+impl fmt::Display for CalendarEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarEventKind::InferiorConjunction => write!(f, "Inferior conjunction"),
+            CalendarEventKind::SuperiorConjunction => write!(f, "Superior conjunction"),
+            CalendarEventKind::Opposition => write!(f, "Opposition"),
+            CalendarEventKind::GreatestEasternElongation => {
+                write!(f, "Greatest eastern elongation")
+            }
+            CalendarEventKind::GreatestWesternElongation => {
+                write!(f, "Greatest western elongation")
+            }
+            CalendarEventKind::StationaryRetrograde => write!(f, "Stationary, turning retrograde"),
+            CalendarEventKind::StationaryDirect => write!(f, "Stationary, turning direct"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub kind: CalendarEventKind,
+    pub body: String,
+    pub observer: String,
+
+    /// Julian Date
+    pub time: f64,
+
+    /// Angle between the body and the Sun as seen from the observer, degrees
+    pub elongation: f32,
+}
+
+/// How far a quantity has to turn back before it counts as turned around, radians (about 2").
+/// Distant bodies barely move in a step, and rounding would make them turn around all the time.
+const TURN_RESOLUTION: f64 = 1e-5;
+
+/// Follows a quantity to find where it turns around.
+#[derive(Debug, Clone, Copy)]
+struct Turns {
+    /// Most extreme value in the current direction, and when it was reached
+    extreme: f64,
+    time: f64,
+
+    /// `None` until it has moved far enough to tell
+    increasing: Option<bool>,
+}
+
+impl Turns {
+    fn new(value: f64, time: f64) -> Self {
+        Self {
+            extreme: value,
+            time,
+            increasing: None,
+        }
+    }
+
+    /// Takes the new `value`, and `offset`, how far it is from the extreme (wrapped, for angles).
+    /// Returns how it was going before, if it has now turned back from the extreme by more than `TURN_RESOLUTION`.
+    fn update(&mut self, value: f64, offset: f64, time: f64) -> Option<Turns> {
+        let further = match self.increasing {
+            Some(true) => offset > 0.0,
+            Some(false) => offset < 0.0,
+            None => false,
+        };
+
+        if further {
+            self.extreme = value;
+            self.time = time;
+            return None;
+        }
+
+        if offset.abs() < TURN_RESOLUTION {
+            return None;
+        }
+
+        let before = *self;
+
+        *self = Self {
+            extreme: value,
+            time,
+            increasing: Some(offset > 0.0),
+        };
+
+        before.increasing.map(|_| before)
+    }
+}
+
+/// What a body looked like from the observer on the previous physics step.
+#[derive(Debug, Clone, Copy)]
+struct Configuration {
+    time: f64,
+
+    /// Whether the clock was running forwards
+    forwards: bool,
+
+    /// Ecliptic longitude of the body minus that of the Sun, radians in (-π, π]
+    longitude_difference: f64,
+
+    /// Ecliptic longitude of the body, radians
+    longitude: Turns,
+
+    /// Radians
+    elongation: Turns,
+}
+
+/// Conjunctions, oppositions, greatest elongations and stations of the bodies orbiting the Sun,
+/// as seen from the observer.
+#[derive(Resource, Debug, Default)]
+pub struct Timeline {
+    /// The body we are watching from, Earth if not selected
+    pub observer: Option<Entity>,
+
+    pub events: Vec<CalendarEvent>,
+
+    previous: HashMap<Entity, Configuration>,
+}
+
+impl Timeline {
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.previous.clear();
+    }
+
+    pub fn export_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "event,body,observer,jd,elongation_deg")?;
+
+        for event in &self.events {
+            writeln!(
+                file,
+                "{},{},{},{:.6},{:.4}",
+                event.kind, event.body, event.observer, event.time, event.elongation
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps an angle into (-π, π].
+fn wrap_angle(angle: f64) -> f64 {
+    let wrapped = angle.rem_euclid(TAU);
+
+    if wrapped > PI {
+        wrapped - TAU
+    } else {
+        wrapped
+    }
+}
+
+pub fn calendar_system(
+    query: Query<(&Transform, &Body, &SphereOfInfluence, Entity, Has<Star>)>,
+    mut timeline: ResMut<Timeline>,
+    time: Res<SimulationTime>,
+) {
+    let time = time.julian_date();
+
+    if timeline.observer.is_none() {
+        timeline.observer = query
+            .iter()
+            .find(|(_, b, _, _, _)| b.metadata.name.as_deref() == Some("Earth"))
+            .map(|(_, _, _, e, _)| e);
+    }
+
+    let Some((observer_transform, observer_body, observer_soi, observer, _)) =
+        timeline.observer.and_then(|o| query.get(o).ok())
+    else {
+        return;
+    };
+
+    // The star the observer orbits, or the one its primary orbits, if the observer is a moon
+    let mut star = observer_soi.primary;
+
+    while let Some((_, _, soi, _, is_star)) = star.and_then(|s| query.get(s).ok()) {
+        if is_star {
+            break;
+        }
+
+        star = soi.primary;
+    }
+
+    let Some((star_transform, _, _, star, _)) = star.and_then(|s| query.get(s).ok()) else {
+        return;
+    };
+
+    let observer_name = observer_body
+        .metadata
+        .name
+        .clone()
+        .unwrap_or("<unknown>".to_string());
+
+    let to_star = world_to_ecliptic(star_transform.translation - observer_transform.translation);
+    let star_distance = to_star.length();
+    let star_longitude = to_star.y.atan2(to_star.x);
+
+    let mut events: Vec<CalendarEvent> = Vec::new();
+
+    for (transform, body, soi, entity, _) in query.iter() {
+        if entity == observer || soi.primary != Some(star) {
+            continue;
+        }
+
+        let to_body = world_to_ecliptic(transform.translation - observer_transform.translation);
+        let longitude = to_body.y.atan2(to_body.x);
+        let longitude_difference = wrap_angle(longitude - star_longitude);
+        let elongation = to_body.angle_between(to_star);
+
+        // Inferior bodies orbit closer to the star than the observer does
+        let heliocentric_distance =
+            (transform.translation - star_transform.translation).length() as f64;
+        let is_inferior = heliocentric_distance < star_distance;
+
+        let previous = timeline.previous.get(&entity).copied();
+
+        // Paused, nothing moved
+        if previous.is_some_and(|p| p.time == time) {
+            continue;
+        }
+
+        let forwards = previous.is_none_or(|p| time > p.time);
+
+        let mut configuration = match previous {
+            Some(previous) if previous.forwards == forwards => Configuration {
+                time,
+                longitude_difference,
+                ..previous
+            },
+            // Starting over when the clock turns around, which would look like everything turning around
+            _ => Configuration {
+                time,
+                forwards,
+                longitude_difference,
+                longitude: Turns::new(longitude, time),
+                elongation: Turns::new(elongation, time),
+            },
+        };
+
+        let mut record = |kind: CalendarEventKind, time: f64, elongation: f64| {
+            events.push(CalendarEvent {
+                kind,
+                body: body
+                    .metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown>".to_string()),
+                observer: observer_name.clone(),
+                time,
+                elongation: elongation.to_degrees() as f32,
+            });
+        };
+
+        if let Some(previous) = previous {
+            // Linearly interpolate when the longitude difference crossed zero or ±180°
+            let crossing = |from: f64, to: f64| {
+                let fraction = if to == from {
+                    0.0
+                } else {
+                    (from / (from - to)).clamp(0.0, 1.0)
+                };
+
+                previous.time + (time - previous.time) * fraction
+            };
+
+            let (from, to) = (previous.longitude_difference, longitude_difference);
+
+            if from.signum() != to.signum() {
+                if from.abs() < FRAC_PI_2 && to.abs() < FRAC_PI_2 {
+                    let kind = if is_inferior && to_body.length() < star_distance {
+                        CalendarEventKind::InferiorConjunction
+                    } else {
+                        CalendarEventKind::SuperiorConjunction
+                    };
+
+                    record(kind, crossing(from, to), elongation);
+                } else if from.abs() > FRAC_PI_2 && to.abs() > FRAC_PI_2 && !is_inferior {
+                    // Measure from 180° instead, so the crossing is a zero crossing again
+                    let (from, to) = (wrap_angle(from + PI), wrap_angle(to + PI));
+
+                    record(
+                        CalendarEventKind::Opposition,
+                        crossing(from, to),
+                        elongation,
+                    );
+                }
+            }
+        }
+
+        // Whichever way the clock runs, a maximum of the elongation is a greatest elongation
+        let offset = elongation - configuration.elongation.extreme;
+
+        if let Some(turned) = configuration.elongation.update(elongation, offset, time) {
+            if is_inferior && turned.increasing == Some(true) {
+                let kind = if longitude_difference > 0.0 {
+                    CalendarEventKind::GreatestEasternElongation
+                } else {
+                    CalendarEventKind::GreatestWesternElongation
+                };
+
+                record(kind, turned.time, turned.extreme);
+            }
+        }
+
+        // And a maximum of the longitude is where the motion turns retrograde
+        let offset = wrap_angle(longitude - configuration.longitude.extreme);
+
+        if let Some(turned) = configuration.longitude.update(longitude, offset, time) {
+            let kind = match turned.increasing == Some(true) {
+                true => CalendarEventKind::StationaryRetrograde,
+                false => CalendarEventKind::StationaryDirect,
+            };
+
+            record(kind, turned.time, elongation);
+        }
+
+        timeline.previous.insert(entity, configuration);
+    }
+
+    for event in events {
+        info!(
+            "{}: {} seen from {} at JD {:.4}, elongation {:.2}°",
+            event.kind, event.body, event.observer, event.time, event.elongation
+        );
+
+        timeline.events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch,
+        cli::Cli,
+        simulation::{settings::SimulationParameters, time::simulation_time_system},
+    };
+
+    #[test]
+    fn turns_around_once() {
+        let mut app = batch::setup(&Cli::default()).unwrap();
+        app.add_systems(FixedUpdate, calendar_system.after(simulation_time_system))
+            .insert_resource(Timeline::default());
+
+        let world = app.world_mut();
+
+        let sun = world
+            .query::<(Entity, &Body)>()
+            .iter(world)
+            .find(|(_, b)| b.metadata.name.as_deref() == Some("Sun"))
+            .map(|(e, _)| e)
+            .unwrap();
+        world.entity_mut(sun).insert(Star {});
+
+        let mut target = world.resource::<SimulationTime>().current;
+        target.add_days(10.0);
+        batch::run_until(world, target);
+
+        // Venus was furthest east of the Sun on 2025-01-10 at 03:00 UTC, and only once
+        let timeline = world.resource::<Timeline>();
+        let elongations: Vec<_> = timeline
+            .events
+            .iter()
+            .filter(|e| e.kind == CalendarEventKind::GreatestEasternElongation)
+            .collect();
+        assert_eq!(elongations.len(), 1, "{:?}", elongations);
+        assert_eq!(elongations[0].body, "Venus");
+        assert!((elongations[0].time - 2460685.63).abs() < 0.5);
+
+        let events = timeline.events.len();
+
+        // Paused, then running backwards: nothing turns around
+        for time_step in [0.0, -1.0] {
+            world.resource_mut::<SimulationParameters>().time_step = time_step;
+
+            for _ in 0..5 {
+                world.run_schedule(FixedUpdate);
+            }
+        }
+
+        let timeline = world.resource::<Timeline>();
+        assert!(timeline.previous.len() > 5);
+        assert_eq!(
+            timeline.events.len(),
+            events,
+            "{:?}",
+            &timeline.events[events..]
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    body::{Body, ParentBody, Spacecraft, TestBody},
+    elements::OsculatingElements,
+    orbit::{hill_sphere_radius, propagate_kepler, sphere_of_influence_radius},
+    settings::{SimulationMode, SimulationParameters, UPDATE_FREQUENCY},
+};
+
+/// Where a body sits in the sphere of influence hierarchy.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct SphereOfInfluence {
+    /// The body whose sphere of influence we are in, `None` for the root of the hierarchy (the Sun).
+    pub primary: Option<Entity>,
+
+    /// Radius of our own sphere of influence, in Astronomical Units.
+    /// Always 0 for spacecraft and test bodies, as nothing can orbit them.
+    pub radius: f32,
+}
+
+impl Default for SphereOfInfluence {
+    fn default() -> Self {
+        Self {
+            primary: None,
+            radius: f32::INFINITY,
+        }
+    }
+}
+
+/// Radius of the region where a body's gravity dominates over the tidal pull of its primary.
+#[derive(Debug, Component, Clone, Default)]
+pub struct HillSphere {
+    /// Astronomical Units, infinite for the root of the hierarchy
+    pub radius: f32,
+
+    /// Bodies whose Hill sphere we are in, `None` until first computed
+    pub inside: Option<Vec<Entity>>,
+}
+
+/// Which spheres are drawn around the bodies.
+#[derive(Resource, Debug, Default)]
+pub struct SphereOverlays {
+    pub hill_sphere: bool,
+    pub sphere_of_influence: bool,
+}
+
+/// Emitted when a body enters the Hill sphere of another body.
+#[derive(Event, Debug, Clone)]
+pub struct HillSphereEntryEvent {
+    pub entity: Entity,
+    pub body: Entity,
+}
+
+/// Emitted when a spacecraft leaves the sphere of influence of its primary,
+/// or enters the sphere of influence of another body.
+#[derive(Event, Debug, Clone)]
+pub struct SoiTransitionEvent {
+    pub entity: Entity,
+    pub from: Option<Entity>,
+    pub to: Option<Entity>,
+}
+
+/// Computes each body's sphere of influence from its mass and its primary's mass.
+///
+/// Bodies are processed from the most massive to the least massive, so by the time we get to a body,
+/// the sphere of influence of every body that could be its primary is already known.
+/// The primary is the body's parent if our data specifies one,
+/// otherwise the body with the smallest sphere of influence that contains us.
+/// Spacecraft and test bodies are never a primary, and only spacecraft report transitions.
+/// Note: we use the current distance to the primary in place of the semi-major axis.
+#[allow(
+    clippy::type_complexity,
+    reason = "a query spelled out reads better than a type alias used once"
+)]
+pub fn sphere_of_influence_system(
+    mut query: Query<(
+        &Transform,
+        &Body,
+        &mut SphereOfInfluence,
+        Entity,
+        Has<Spacecraft>,
+        Has<TestBody>,
+        Option<&ParentBody>,
+    )>,
+    mut transitions: EventWriter<SoiTransitionEvent>,
+) {
+    let mut bodies: Vec<_> = query
+        .iter()
+        .filter(|(_, _, _, _, is_spacecraft, is_test_body, _)| !is_spacecraft && !is_test_body)
+        .map(|(t, b, _, e, _, _, parent)| (t.translation, b.data.mass, e, parent.copied()))
+        .collect();
+
+    bodies.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    // (position, mass, entity, radius)
+    let mut hierarchy: Vec<(Vec3, f32, Entity, f32)> = Vec::with_capacity(bodies.len());
+
+    for (position, mass, entity, parent) in bodies {
+        let parent = parent.and_then(|p| hierarchy.iter().find(|h| h.2 == p.0).copied());
+
+        let (primary, radius) = match parent.or_else(|| find_primary(position, &hierarchy)) {
+            Some((primary_position, primary_mass, primary_entity, _)) => (
+                Some(primary_entity),
+                sphere_of_influence_radius(position.distance(primary_position), mass, primary_mass),
+            ),
+            None => (None, f32::INFINITY),
+        };
+
+        hierarchy.push((position, mass, entity, radius));
+
+        if let Ok((_, _, mut soi, _, _, _, _)) = query.get_mut(entity) {
+            soi.primary = primary;
+            soi.radius = radius;
+        }
+    }
+
+    for (transform, _, mut soi, entity, is_spacecraft, is_test_body, _) in query.iter_mut() {
+        if !is_spacecraft && !is_test_body {
+            continue;
+        }
+
+        let primary = find_primary(transform.translation, &hierarchy).map(|(_, _, e, _)| e);
+
+        // A freshly spawned spacecraft has no primary yet, that is not a transition
+        if is_spacecraft && !is_test_body && soi.primary.is_some() && soi.primary != primary {
+            transitions.send(SoiTransitionEvent {
+                entity,
+                from: soi.primary,
+                to: primary,
+            });
+        }
+
+        soi.primary = primary;
+        soi.radius = 0.0;
+    }
+}
+
+fn find_primary(
+    position: Vec3,
+    hierarchy: &[(Vec3, f32, Entity, f32)],
+) -> Option<(Vec3, f32, Entity, f32)> {
+    hierarchy
+        .iter()
+        .filter(|(p, _, _, radius)| p.distance(position) < *radius)
+        .min_by(|a, b| a.3.total_cmp(&b.3))
+        .copied()
+}
+
+/// Propagates every body as a two-body conic relative to its current primary.
+/// The root of the hierarchy keeps moving in a straight line.
+pub fn patched_conics_system(
+    mut query: Query<(
+        &mut Transform,
+        &mut Body,
+        &SphereOfInfluence,
+        Entity,
+        Has<Spacecraft>,
+    )>,
+    parameters: Res<SimulationParameters>,
+) {
+    if parameters.mode != SimulationMode::PatchedConics {
+        return;
+    }
+
+    let dt = (parameters.time_step / UPDATE_FREQUENCY as f32) as f64;
+
+    let states: HashMap<Entity, (Vec3, Vec3, f32, Option<Entity>)> = query
+        .iter()
+        .map(|(t, b, soi, e, is_spacecraft)| {
+            // Spacecraft do not contribute to the gravitational parameter
+            let mass = if is_spacecraft { 0.0 } else { b.data.mass };
+
+            (e, (t.translation, b.data.velocity, mass, soi.primary))
+        })
+        .collect();
+
+    // Primaries need to be moved before their satellites, so sort by depth in the hierarchy
+    let depth = |mut entity: Entity| {
+        let mut depth = 0;
+
+        while let Some(primary) = states.get(&entity).and_then(|s| s.3) {
+            entity = primary;
+            depth += 1;
+
+            if depth > states.len() {
+                break;
+            }
+        }
+
+        depth
+    };
+
+    let mut order: Vec<_> = states.keys().copied().collect();
+    order.sort_by_key(|e| depth(*e));
+
+    let mut propagated: HashMap<Entity, (Vec3, Vec3, Vec3)> = HashMap::new();
+
+    for entity in order {
+        let (position, velocity, mass, primary) = states[&entity];
+
+        let primary_state = primary.and_then(|p| {
+            let (old_position, old_velocity, primary_mass, _) = states.get(&p)?;
+            let (new_position, new_velocity, _) = propagated.get(&p)?;
+
+            Some((
+                *old_position,
+                *old_velocity,
+                *primary_mass,
+                *new_position,
+                *new_velocity,
+            ))
+        });
+
+        let new_state = match primary_state {
+            Some((old_position, old_velocity, primary_mass, new_position, new_velocity)) => {
+                let mu = (parameters.gravitational_constant * (primary_mass + mass)) as f64;
+                let relative_position = (position - old_position).as_dvec3();
+
+                let (r, v) = propagate_kepler(
+                    relative_position,
+                    (velocity - old_velocity).as_dvec3(),
+                    mu,
+                    dt,
+                );
+
+                let acceleration = -mu * r / r.length().powi(3);
+
+                (
+                    new_position + r.as_vec3(),
+                    new_velocity + v.as_vec3(),
+                    acceleration.as_vec3(),
+                )
+            }
+            None => (position + velocity * dt as f32, velocity, Vec3::ZERO),
+        };
+
+        propagated.insert(entity, new_state);
+    }
+
+    for (mut transform, mut body, _, entity, _) in query.iter_mut() {
+        if let Some((position, velocity, acceleration)) = propagated.get(&entity) {
+            transform.translation = *position;
+            body.data.velocity = *velocity;
+            body.data.acceleration = *acceleration;
+        }
+    }
+}
+
+pub fn soi_transition_log_system(
+    mut transitions: EventReader<SoiTransitionEvent>,
+    query: Query<&Body>,
+) {
+    let name = |entity: Option<Entity>| {
+        entity
+            .and_then(|e| query.get(e).ok())
+            .and_then(|b| b.metadata.name.clone())
+            .unwrap_or("<unknown>".to_string())
+    };
+
+    for transition in transitions.read() {
+        info!(
+            "{} left the sphere of influence of {} for {}",
+            name(Some(transition.entity)),
+            name(transition.from),
+            name(transition.to)
+        );
+    }
+}
+
+/// Computes each body's Hill sphere from its osculating orbit around its primary,
+/// and reports bodies entering the Hill sphere of another body.
+/// Unbound bodies use their current distance in place of the periapsis. Test bodies are left out.
+#[allow(
+    clippy::type_complexity,
+    reason = "a query spelled out reads better than a type alias used once"
+)]
+pub fn hill_sphere_system(
+    mut query: Query<
+        (
+            &Transform,
+            &Body,
+            &OsculatingElements,
+            &mut HillSphere,
+            Entity,
+        ),
+        Without<TestBody>,
+    >,
+    mut entries: EventWriter<HillSphereEntryEvent>,
+) {
+    let states: HashMap<Entity, (Vec3, f32)> = query
+        .iter()
+        .map(|(t, b, _, _, e)| (e, (t.translation, b.data.mass)))
+        .collect();
+
+    for (transform, body, elements, mut hill, _) in query.iter_mut() {
+        hill.radius = match elements.primary.and_then(|p| states.get(&p)) {
+            Some((primary_position, primary_mass)) => {
+                if elements.eccentricity < 1.0 && elements.semi_major_axis > 0.0 {
+                    hill_sphere_radius(
+                        elements.semi_major_axis,
+                        elements.eccentricity,
+                        body.data.mass,
+                        *primary_mass,
+                    )
+                } else {
+                    hill_sphere_radius(
+                        transform.translation.distance(*primary_position),
+                        0.0,
+                        body.data.mass,
+                        *primary_mass,
+                    )
+                }
+            }
+            None => f32::INFINITY,
+        };
+    }
+
+    let spheres: Vec<(Vec3, f32, Entity)> = query
+        .iter()
+        .map(|(t, _, _, h, e)| (t.translation, h.radius, e))
+        .collect();
+
+    for (transform, _, _, mut hill, entity) in query.iter_mut() {
+        let inside: Vec<Entity> = spheres
+            .iter()
+            .filter(|(position, radius, other)| {
+                *other != entity
+                    && radius.is_finite()
+                    && position.distance(transform.translation) < *radius
+            })
+            .map(|(_, _, other)| *other)
+            .collect();
+
+        // Bodies that start out inside a Hill sphere, like moons, have not entered it
+        if let Some(previous) = &hill.inside {
+            for body in inside.iter().filter(|b| !previous.contains(b)) {
+                entries.send(HillSphereEntryEvent {
+                    entity,
+                    body: *body,
+                });
+            }
+        }
+
+        hill.inside = Some(inside);
+    }
+}
+
+pub fn hill_sphere_log_system(mut entries: EventReader<HillSphereEntryEvent>, query: Query<&Body>) {
+    let name = |entity: Entity| {
+        query
+            .get(entity)
+            .ok()
+            .and_then(|b| b.metadata.name.clone())
+            .unwrap_or("<unknown>".to_string())
+    };
+
+    for entry in entries.read() {
+        info!(
+            "{} entered the Hill sphere of {}",
+            name(entry.entity),
+            name(entry.body)
+        );
+    }
+}
@@ -1,161 +1,479 @@
 use std::{
-    fs::File,
-    io::Read,
+    fs,
     path::Path,
     sync::{Arc, RwLock},
 };
 
 use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
 
-use crate::simulation::body::{Body, BodyType};
+use crate::{
+    mpcorb::{self, MinorPlanetFilter, MinorPlanetImport},
+    simulation::body::Body,
+    spk::{seed_dataset, KernelSeed, SpkKernel},
+};
+
+use super::{
+    body::{BodyData, BodyMetadata, BodyOrbitalElements, MetaLoader},
+    orbit::{
+        ecliptic_to_world, elements_to_state, mean_to_true_anomaly, propagate_kepler,
+        KeplerianElements,
+    },
+    scenario::Scenario,
+    settings::SimulationParameters,
+    time::JulianDate,
+    units::UnitConversion,
+};
+
+/// Julian Date (TDB) of the snapshot in our data: 2025-01-01 00:00:00.
+/// Used if the data does not specify its epoch.
+pub const DATA_EPOCH: f64 = 2460676.5;
 
-use super::body::{BodyMetadata, MetaLoader};
+/// Where our data is, relative to the working directory.
+pub const DATA_PATH: &str = "data/compiled_data.json";
+
+/// The initial conditions, and the instant they are valid at.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dataset {
+    /// Julian Date (TDB)
+    #[serde(default = "default_epoch")]
+    pub epoch: f64,
+
+    pub bodies: Vec<Body>,
+}
 
-/// Initialize the celestial bodies with their initial data.
+fn default_epoch() -> f64 {
+    DATA_EPOCH
+}
+
+/// Older data is just the list of bodies, at `DATA_EPOCH`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DataFile {
+    Dataset(Dataset),
+    Bodies(Vec<Body>),
+}
+
+/// Initialize the celestial bodies of a scenario with their initial data.
 /// We get the data using the JPL Horizons API from our pretty little Python script.
-pub fn initialize_bodies(asset_server: &Res<AssetServer>) -> Option<Vec<Body>> {
-    let metadata_map: HashMap<&'static str, MetaLoader> = HashMap::from([
-        (
-            "Sun",
-            MetaLoader::new(Some(asset_server.load("sun.jpg")), BodyType::Star),
-        ),
-        ("Mercury", MetaLoader::new(None, BodyType::Planet)),
-        ("Venus", MetaLoader::new(None, BodyType::Planet)),
-        ("Earth", MetaLoader::new(None, BodyType::Planet)),
-        /**/ ("Moon", MetaLoader::new(None, BodyType::Moon)),
-        ("Mars", MetaLoader::new(None, BodyType::Planet)),
-        /**/ ("Phobos", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Deimos", MetaLoader::new(None, BodyType::Moon)),
-        ("Jupiter", MetaLoader::new(None, BodyType::Planet)),
-        /**/ ("Io", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Europa", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Ganymede", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Callisto", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Amalthea", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Thebe", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Adrastea", MetaLoader::new(None, BodyType::Moon)),
-        /**/ ("Metis", MetaLoader::new(None, BodyType::Moon)),
-        ("Saturn", MetaLoader::new(None, BodyType::Planet)),
-        ("Uranus", MetaLoader::new(None, BodyType::Planet)),
-        ("Neptune", MetaLoader::new(None, BodyType::Planet)),
-        ("Pluto", MetaLoader::new(None, BodyType::DwarfPlanet)),
-        ("Ceres", MetaLoader::new(None, BodyType::DwarfPlanet)),
-        ("Eris", MetaLoader::new(None, BodyType::DwarfPlanet)),
-        ("Haumea", MetaLoader::new(None, BodyType::DwarfPlanet)),
-        ("Makemake", MetaLoader::new(None, BodyType::DwarfPlanet)),
-    ]);
-
-    let bodies = load_data();
-
-    return match bodies {
-        Some(bodies) => Some(
-            bodies
-                .iter()
-                .map(|b| {
-                    let binding = MetaLoader::default();
-                    let meta = metadata_map
-                        .get(b.metadata.name.as_ref().unwrap().as_str())
-                        .unwrap_or(&binding);
-
-                    Body {
-                        metadata: BodyMetadata {
-                            texture: meta.texture.clone(),
-                            body_type: meta.body_type.clone(),
-                            ..b.metadata.clone()
-                        },
-                        data: b.data,
-                        satellites: match &b.satellites {
-                            Some(satellites) => Some(
-                                satellites
-                                    .iter()
-                                    .map(|s| {
-                                        let satellite = s.read().unwrap();
-
-                                        let s_binding = MetaLoader::default();
-                                        let s_meta = metadata_map
-                                            .get(satellite.metadata.name.as_ref().unwrap().as_str())
-                                            .unwrap_or(&s_binding);
-
-                                        Arc::new(RwLock::new(Body {
-                                            metadata: BodyMetadata {
-                                                texture: s_meta.texture.clone(),
-                                                body_type: s_meta.body_type.clone(),
-                                                ..satellite.metadata.clone()
-                                            },
-                                            data: satellite.data,
-                                            satellites: None,
-                                        }))
-                                    })
-                                    .collect(),
-                            ),
-                            None => None,
-                        },
-                    }
-                })
-                .collect(),
-        ),
-        None => None,
+pub fn initialize_bodies(
+    asset_server: Option<&AssetServer>,
+    parameters: &SimulationParameters,
+    scenario: &Scenario,
+    seed: &KernelSeed,
+    minor_planets: &MinorPlanetImport,
+) -> Result<Dataset, String> {
+    let metadata_map: HashMap<&str, MetaLoader> = scenario
+        .appearance
+        .iter()
+        .map(|(name, appearance)| {
+            (
+                name.as_str(),
+                MetaLoader::new(
+                    appearance
+                        .texture
+                        .as_ref()
+                        .zip(asset_server)
+                        .map(|(texture, asset_server)| asset_server.load(texture.clone())),
+                    appearance.body_type.clone(),
+                ),
+            )
+        })
+        .collect();
+
+    let mut dataset = match &scenario.data {
+        Some(path) => load_data(path)?,
+        None => Dataset {
+            epoch: scenario.epoch().map(|e| e.value()).unwrap_or(DATA_EPOCH),
+            bodies: Vec::new(),
+        },
+    };
+
+    dataset.bodies.extend(scenario.bodies());
+
+    if let Some(path) = &minor_planets.path {
+        import_minor_planets(&mut dataset, path, &minor_planets.filter, parameters);
+    }
+
+    if let Some(path) = &seed.kernel {
+        seed_from_kernel(&mut dataset, path, seed.epoch, parameters);
+    }
+
+    let dataset = prepare_dataset(dataset, parameters);
+
+    Ok(Dataset {
+        epoch: dataset.epoch,
+        bodies: dataset
+            .bodies
+            .iter()
+            .map(|b| {
+                let binding = MetaLoader::new(None, b.metadata.body_type.clone());
+                let meta = b
+                    .metadata
+                    .name
+                    .as_deref()
+                    .and_then(|name| metadata_map.get(name))
+                    .unwrap_or(&binding);
+
+                Body {
+                    metadata: BodyMetadata {
+                        texture: meta.texture.clone(),
+                        body_type: meta.body_type.clone(),
+                        ..b.metadata.clone()
+                    },
+                    data: b.data,
+                    satellites: b.satellites.as_ref().map(|satellites| {
+                        satellites
+                            .iter()
+                            .map(|s| {
+                                let satellite = s.read().unwrap();
+
+                                let s_binding =
+                                    MetaLoader::new(None, satellite.metadata.body_type.clone());
+                                let s_meta = satellite
+                                    .metadata
+                                    .name
+                                    .as_deref()
+                                    .and_then(|name| metadata_map.get(name))
+                                    .unwrap_or(&s_binding);
+
+                                Arc::new(RwLock::new(Body {
+                                    metadata: BodyMetadata {
+                                        texture: s_meta.texture.clone(),
+                                        body_type: s_meta.body_type.clone(),
+                                        ..satellite.metadata.clone()
+                                    },
+                                    data: satellite.data,
+                                    satellites: None,
+                                }))
+                            })
+                            .collect()
+                    }),
+                }
+            })
+            .collect(),
+    })
+}
+
+/// Loads our data with the state vectors of every body resolved, in the units the simulation runs in.
+/// Needs no assets, so we can also run without a window.
+pub fn load_dataset(path: &Path, parameters: &SimulationParameters) -> Result<Dataset, String> {
+    Ok(prepare_dataset(load_data(path)?, parameters))
+}
+
+/// Converts data as authored to the units the simulation runs in, and resolves its state vectors.
+pub fn prepare_dataset(mut dataset: Dataset, parameters: &SimulationParameters) -> Dataset {
+    convert_units(&mut dataset.bodies, parameters);
+    resolve_state_vectors(&mut dataset.bodies, dataset.epoch, parameters);
+
+    dataset
+}
+
+/// Adds the minor planets in `path` that pass `filter` to our data, at the epoch of the file.
+fn import_minor_planets(
+    dataset: &mut Dataset,
+    path: &Path,
+    filter: &MinorPlanetFilter,
+    parameters: &SimulationParameters,
+) {
+    let planets = match mpcorb::load(path) {
+        Ok(planets) => planets,
+        Err(e) => {
+            error!("Could not import minor planets: {}", e);
+            return;
+        }
+    };
+
+    let total = planets.len();
+    let selected = filter.select(planets);
+    let added = mpcorb::import_into(dataset, &selected, parameters.data_units);
+
+    info!(
+        "Imported {} of {} minor planets from {}",
+        added,
+        total,
+        path.display()
+    );
+}
+
+/// Moves our data to `epoch`, or leaves it at its own, with the states from an SPK kernel.
+/// If the kernel cannot be read, the data is left as it is.
+fn seed_from_kernel(
+    dataset: &mut Dataset,
+    path: &Path,
+    epoch: Option<JulianDate>,
+    parameters: &SimulationParameters,
+) {
+    let kernel = match SpkKernel::open(path) {
+        Ok(kernel) => kernel,
+        Err(e) => {
+            error!("Could not load the kernel, using our data as is: {}", e);
+            return;
+        }
     };
+
+    let epoch = epoch.map(|e| e.value()).unwrap_or(dataset.epoch);
+    let propagated = seed_dataset(dataset, &kernel, epoch, parameters.data_units);
+
+    info!(
+        "Placed the bodies at JD {} with {} ({} segments)",
+        epoch,
+        path.display(),
+        kernel.segments.len()
+    );
+
+    for name in propagated {
+        warn!(
+            "{} is not in the kernel, moved along its Keplerian orbit instead",
+            name
+        );
+    }
 }
 
-pub fn load_data() -> Option<Vec<Body>> {
-    let dir = std::env::current_dir().unwrap();
-    let path = dir.join(Path::new("data\\compiled_data.json"));
-    let data_file = File::open(path.clone());
+pub fn load_data(path: &Path) -> Result<Dataset, String> {
+    info!("Reading data from {}", path.display());
 
-    info!("Reading data from {}", path.to_str().unwrap());
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
 
-    match data_file {
-        Ok(mut file) => {
-            let mut contents = String::new();
-            file.read_to_string(&mut contents).unwrap();
+    let data =
+        match serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))? {
+            DataFile::Dataset(dataset) => dataset,
+            DataFile::Bodies(bodies) => Dataset {
+                epoch: DATA_EPOCH,
+                bodies,
+            },
+        };
 
-            let data: Vec<Body> = serde_json::from_str(&contents).unwrap();
+    info!(
+        "Successfully loaded data for {} bodies at JD {}:",
+        data.bodies.len()
+            + data.bodies.iter().fold(0, |acc, b| {
+                acc + b.satellites.as_ref().map(|s| s.len()).unwrap_or(0)
+            }),
+        data.epoch
+    );
 
-            info!(
-                "Successfully loaded data for {} bodies:",
-                data.len()
-                    + data.iter().fold(0, |acc, b| {
-                        acc + b
-                            .satellites
-                            .as_ref()
-                            .and_then(|s| Some(s.len()))
-                            .unwrap_or(0)
-                    })
-            );
+    for body in &data.bodies {
+        let name = body
+            .metadata
+            .name
+            .clone()
+            .unwrap_or(format!("<unknown {}>", body.metadata.body_type));
+
+        info!("    {}", name);
 
-            for body in &data {
-                let name = body
+        for satellite in body.satellites.iter().flatten() {
+            info!(
+                "        {}",
+                satellite
+                    .read()
+                    .unwrap()
                     .metadata
                     .name
                     .clone()
-                    .unwrap_or(format!("<unknown {}>", body.metadata.body_type));
-
-                info!("    {}", name);
-
-                for satellite in body.satellites.as_ref().unwrap() {
-                    info!(
-                        "        {}",
-                        satellite
-                            .read()
-                            .unwrap()
-                            .metadata
-                            .name
-                            .clone()
-                            .unwrap_or(format!(
-                                "<unknown {} (satellite of {})>",
-                                body.metadata.body_type, name
-                            ))
+                    .unwrap_or(format!(
+                        "<unknown {} (satellite of {})>",
+                        body.metadata.body_type, name
+                    ))
+            );
+        }
+    }
+
+    Ok(data)
+}
+
+/// Converts our data from the units it is authored in to the units the simulation runs in.
+fn convert_units(bodies: &mut [Body], parameters: &SimulationParameters) {
+    let conversion = UnitConversion::new(parameters.data_units, parameters);
+
+    for body in bodies {
+        body.data = body.data.converted(&conversion);
+
+        for satellite in body.satellites.iter().flatten() {
+            let mut satellite = satellite.write().unwrap();
+            satellite.data = satellite.data.converted(&conversion);
+        }
+    }
+}
+
+/// Derives the world space state vectors of the bodies that only have orbital elements specified in our data,
+/// or whose state vectors are relative to their parent.
+/// Parents are resolved before their satellites.
+fn resolve_state_vectors(bodies: &mut [Body], epoch: f64, parameters: &SimulationParameters) {
+    // (body index, satellite index)
+    let mut unresolved: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut resolved: HashMap<String, BodyData> = HashMap::new();
+
+    let mut visit = |body: &Body, index: (usize, Option<usize>)| {
+        if !body.data.has_state_vectors() || body.metadata.relative_to_parent {
+            unresolved.push(index);
+        } else if let Some(name) = &body.metadata.name {
+            resolved.insert(name.clone(), body.data);
+        }
+    };
+
+    for (i, body) in bodies.iter().enumerate() {
+        visit(body, (i, None));
+
+        for (j, satellite) in body.satellites.iter().flatten().enumerate() {
+            visit(&satellite.read().unwrap(), (i, Some(j)));
+        }
+    }
+
+    while !unresolved.is_empty() {
+        let count = unresolved.len();
+
+        unresolved.retain(|&(i, j)| {
+            let satellite = j.map(|j| bodies[i].satellites.as_ref().unwrap()[j].clone());
+            let mut satellite_guard = satellite.as_ref().map(|s| s.write().unwrap());
+
+            let parent_name = match &satellite_guard {
+                Some(s) => s.metadata.parent.clone().or(bodies[i].metadata.name.clone()),
+                None => bodies[i].metadata.parent.clone(),
+            }
+            .unwrap_or("Sun".to_string());
+
+            let Some(parent) = resolved.get(&parent_name).copied() else {
+                return true;
+            };
+
+            let body = match satellite_guard.as_deref_mut() {
+                Some(s) => s,
+                None => &mut bodies[i],
+            };
+
+            let name = body
+                .metadata
+                .name
+                .clone()
+                .unwrap_or("<unknown>".to_string());
+
+            let relative_state = if body.data.has_state_vectors() {
+                Some((body.data.position, body.data.velocity))
+            } else {
+                body.data
+                    .orbital_elements
+                    .and_then(|e| state_from_elements(&e, body.data.mass, &parent, epoch, parameters))
+            };
+
+            match relative_state {
+                Some((position, velocity)) => {
+                    body.data.position = parent.position + position;
+                    body.data.velocity = parent.velocity + velocity;
+                }
+                None => {
+                    error!(
+                        "{} has neither state vectors nor complete orbital elements, placing it at its parent",
+                        name
                     );
+
+                    body.data.position = parent.position;
+                    body.data.velocity = parent.velocity;
                 }
             }
 
-            Some(data)
-        }
-        Err(e) => {
-            error!("Error opening file: {}", e);
+            resolved.insert(name, body.data);
 
-            None
+            false
+        });
+
+        if unresolved.len() == count {
+            break;
         }
     }
+
+    for (i, j) in unresolved {
+        let satellite = j.map(|j| bodies[i].satellites.as_ref().unwrap()[j].clone());
+        let mut satellite_guard = satellite.as_ref().map(|s| s.write().unwrap());
+
+        let body = match satellite_guard.as_deref_mut() {
+            Some(s) => s,
+            None => &mut bodies[i],
+        };
+
+        error!(
+            "Could not find the parent of {}, placing it at the origin",
+            body.metadata
+                .name
+                .clone()
+                .unwrap_or("<unknown>".to_string())
+        );
+
+        body.data.position = Vec3::ZERO;
+        body.data.velocity = Vec3::ZERO;
+    }
+}
+
+/// Converts orbital elements in our data to a state vector in world space, relative to the parent,
+/// at the epoch of our data.
+fn state_from_elements(
+    elements: &BodyOrbitalElements,
+    mass: f32,
+    parent: &BodyData,
+    epoch: f64,
+    parameters: &SimulationParameters,
+) -> Option<(Vec3, Vec3)> {
+    let eccentricity = elements.eccentricity as f64;
+
+    let periapsis_distance = match (elements.periapsis_distance, elements.semi_major_axis) {
+        (Some(q), _) => q as f64,
+        (None, Some(a)) => a as f64 * (1.0 - eccentricity),
+        (None, None) => return None,
+    };
+
+    let true_anomaly = match (elements.true_anomaly, elements.mean_anomaly) {
+        (Some(nu), _) => (nu as f64).to_radians(),
+        (None, Some(m)) => mean_to_true_anomaly((m as f64).to_radians(), eccentricity),
+        (None, None) => return None,
+    };
+
+    let mu = (parameters.gravitational_constant * (parent.mass + mass)) as f64;
+
+    let (position, velocity) = elements_to_state(
+        &KeplerianElements {
+            eccentricity,
+            inclination: (elements.inclination as f64).to_radians(),
+            longitude_of_ascending_node: (elements.longitude_of_ascending_node as f64).to_radians(),
+            argument_of_periapsis: (elements.argument_of_perifocus as f64).to_radians(),
+            true_anomaly,
+            ..default()
+        },
+        periapsis_distance,
+        mu,
+    );
+
+    // Move the body along its orbit from the epoch of the elements to the epoch of our data
+    let (position, velocity) = propagate_kepler(
+        position,
+        velocity,
+        mu,
+        epoch - elements.epoch.unwrap_or(epoch),
+    );
+
+    Some((ecliptic_to_world(position), ecliptic_to_world(velocity)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_data_that_does_not_load() {
+        let missing = load_data(Path::new("data/missing.json")).unwrap_err();
+        assert!(
+            missing.starts_with("Could not read data/missing.json"),
+            "{}",
+            missing
+        );
+
+        let invalid = load_data(Path::new("scenarios/jupiter_system.ron")).unwrap_err();
+        assert!(
+            invalid.starts_with("scenarios/jupiter_system.ron: "),
+            "{}",
+            invalid
+        );
+
+        assert!(load_data(Path::new(DATA_PATH)).is_ok());
+    }
 }
@@ -0,0 +1,556 @@
+use std::{fmt, fs::File, io::Write, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    body::{Body, Star},
+    conics::SphereOfInfluence,
+    time::SimulationTime,
+};
+
+/// Known eclipses as seen from Earth, (kind, Julian Date of the maximum), to compare our results against.
+pub const KNOWN_EARTH_ECLIPSES: [(EclipseKind, f64); 8] = [
+    (EclipseKind::Lunar, 2460748.790), // 2025-03-14, total
+    (EclipseKind::Solar, 2460763.950), // 2025-03-29, partial
+    (EclipseKind::Lunar, 2460926.258), // 2025-09-07, total
+    (EclipseKind::Solar, 2460940.320), // 2025-09-21, partial
+    (EclipseKind::Solar, 2461089.008), // 2026-02-17, annular
+    (EclipseKind::Lunar, 2461102.981), // 2026-03-03, total
+    (EclipseKind::Solar, 2461265.240), // 2026-08-12, total
+    (EclipseKind::Lunar, 2461280.675), // 2026-08-28, partial
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EclipseKind {
+    /// The observer is in the shadow of one of its satellites
+    Solar,
+    /// One of the observer's satellites is in the observer's shadow
+    Lunar,
+    /// A body passes in front of another body, as seen from the observer
+    Occultation,
+}
+
+/// How deep the eclipse got, ordered from the least to the most complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EclipseType {
+    Penumbral,
+    Partial,
+    Annular,
+    Total,
+}
+
+impl fmt::Display for EclipseKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EclipseKind::Solar => write!(f, "Solar eclipse"),
+            EclipseKind::Lunar => write!(f, "Lunar eclipse"),
+            EclipseKind::Occultation => write!(f, "Occultation"),
+        }
+    }
+}
+
+impl fmt::Display for EclipseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EclipseType::Penumbral => write!(f, "penumbral"),
+            EclipseType::Partial => write!(f, "partial"),
+            EclipseType::Annular => write!(f, "annular"),
+            EclipseType::Total => write!(f, "total"),
+        }
+    }
+}
+
+/// An eclipse or occultation. Times are Julian Dates.
+#[derive(Debug, Clone)]
+pub struct Eclipse {
+    pub kind: EclipseKind,
+    pub eclipse_type: EclipseType,
+    pub observer: String,
+
+    /// The body in front, casting the shadow
+    pub occulter: String,
+
+    /// The body behind, in the shadow
+    pub occulted: String,
+
+    pub start: f64,
+    pub maximum: f64,
+
+    /// `None` while the eclipse is still going on
+    pub end: Option<f64>,
+
+    /// Distance from the axis of the shadow (AU) or angular separation (radians) at the maximum
+    pub minimum_separation: f32,
+}
+
+/// Emitted when an eclipse or occultation is over.
+#[derive(Event, Debug, Clone)]
+pub struct EclipseEvent(pub Eclipse);
+
+#[derive(Resource, Debug, Default)]
+pub struct Eclipses {
+    /// The body we are watching from, Earth if not selected
+    pub observer: Option<Entity>,
+
+    /// Keyed by (kind, occulter, occulted)
+    pub ongoing: HashMap<(EclipseKind, Entity, Entity), Eclipse>,
+    pub finished: Vec<Eclipse>,
+}
+
+impl Eclipses {
+    pub fn clear(&mut self) {
+        self.ongoing.clear();
+        self.finished.clear();
+    }
+
+    pub fn export_csv(&self, path: &Path) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(
+            file,
+            "kind,type,observer,occulter,occulted,start_jd,maximum_jd,end_jd,minimum_separation"
+        )?;
+
+        for eclipse in self.finished.iter().chain(self.ongoing.values()) {
+            writeln!(
+                file,
+                "{},{},{},{},{},{:.6},{:.6},{},{}",
+                eclipse.kind,
+                eclipse.eclipse_type,
+                eclipse.observer,
+                eclipse.occulter,
+                eclipse.occulted,
+                eclipse.start,
+                eclipse.maximum,
+                eclipse
+                    .end
+                    .map(|end| format!("{:.6}", end))
+                    .unwrap_or_default(),
+                eclipse.minimum_separation
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Eclipse {
+    /// Days between our maximum and the nearest known eclipse of the same kind, if there is one within a week.
+    pub fn known_eclipse_offset(&self) -> Option<f64> {
+        if self.observer != "Earth" {
+            return None;
+        }
+
+        KNOWN_EARTH_ECLIPSES
+            .iter()
+            .filter(|(kind, _)| *kind == self.kind)
+            .map(|(_, maximum)| self.maximum - maximum)
+            .filter(|offset| offset.abs() < 7.0)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+}
+
+/// Returns how deep `target` is in the shadow `occulter` casts away from `star`, if at all,
+/// and its distance from the axis of the shadow.
+/// Arguments are (position, radius) tuples.
+/// `whole_body` means the target has to be fully inside the umbra for a total eclipse,
+/// like the Moon in the Earth's shadow, otherwise touching the umbra is enough,
+/// like the Earth in the Moon's shadow.
+pub fn shadow(
+    star: (Vec3, f32),
+    occulter: (Vec3, f32),
+    target: (Vec3, f32),
+    whole_body: bool,
+) -> Option<(EclipseType, f32)> {
+    let (star_position, star_radius) = star;
+    let (occulter_position, occulter_radius) = occulter;
+    let (target_position, target_radius) = target;
+
+    let axis = occulter_position - star_position;
+    let distance = axis.length();
+
+    if distance == 0.0 {
+        return None;
+    }
+
+    let direction = axis / distance;
+    let offset = target_position - occulter_position;
+    let along = offset.dot(direction);
+
+    // The shadow only points away from the star
+    if along <= 0.0 {
+        return None;
+    }
+
+    let separation = (offset - direction * along).length();
+
+    // Negative past the tip of the umbra, where the antumbra begins
+    let umbra = occulter_radius - along * (star_radius - occulter_radius) / distance;
+    let penumbra = occulter_radius + along * (star_radius + occulter_radius) / distance;
+
+    if separation >= penumbra + target_radius {
+        return None;
+    }
+
+    let eclipse_type = if whole_body {
+        if umbra > 0.0 && separation + target_radius <= umbra {
+            EclipseType::Total
+        } else if umbra > 0.0 && separation < umbra + target_radius {
+            EclipseType::Partial
+        } else {
+            EclipseType::Penumbral
+        }
+    } else if separation < umbra.abs() + target_radius {
+        if umbra > 0.0 {
+            EclipseType::Total
+        } else {
+            EclipseType::Annular
+        }
+    } else {
+        EclipseType::Partial
+    };
+
+    Some((eclipse_type, separation))
+}
+
+/// Returns how much of `occulted` is hidden behind `occulter` as seen from `observer`, if any,
+/// and their angular separation.
+/// Arguments are (position, radius) tuples.
+pub fn occultation(
+    observer: Vec3,
+    occulter: (Vec3, f32),
+    occulted: (Vec3, f32),
+) -> Option<(EclipseType, f32)> {
+    let to_occulter = occulter.0 - observer;
+    let to_occulted = occulted.0 - observer;
+
+    let (occulter_distance, occulted_distance) = (to_occulter.length(), to_occulted.length());
+
+    if occulter_distance >= occulted_distance || occulter_distance <= occulter.1 {
+        return None;
+    }
+
+    let occulter_angle = (occulter.1 / occulter_distance).min(1.0).asin();
+    let occulted_angle = (occulted.1 / occulted_distance).min(1.0).asin();
+    let separation = to_occulter.angle_between(to_occulted);
+
+    if separation >= occulter_angle + occulted_angle {
+        return None;
+    }
+
+    let eclipse_type = if separation + occulted_angle <= occulter_angle {
+        EclipseType::Total
+    } else if separation + occulter_angle <= occulted_angle {
+        EclipseType::Annular
+    } else {
+        EclipseType::Partial
+    };
+
+    Some((eclipse_type, separation))
+}
+
+/// Detects eclipses and occultations as seen from the observer.
+/// Solar and lunar eclipses are only considered between the observer and its own satellites,
+/// the penumbra of any farther body would only graze us during what is really a transit.
+pub fn eclipse_detection_system(
+    query: Query<(&Transform, &Body, &SphereOfInfluence, Entity, Has<Star>)>,
+    mut eclipses: ResMut<Eclipses>,
+    mut events: EventWriter<EclipseEvent>,
+    time: Res<SimulationTime>,
+) {
+    let time = time.julian_date();
+
+    if eclipses.observer.is_none() {
+        eclipses.observer = query
+            .iter()
+            .find(|(_, b, _, _, _)| b.metadata.name.as_deref() == Some("Earth"))
+            .map(|(_, _, _, e, _)| e);
+    }
+
+    let Some((observer_transform, observer_body, _, observer, _)) =
+        eclipses.observer.and_then(|o| query.get(o).ok())
+    else {
+        return;
+    };
+
+    let observer_position = observer_transform.translation;
+    let observer_radius = observer_body.data.radius;
+
+    let name = |body: &Body| {
+        body.metadata
+            .name
+            .clone()
+            .unwrap_or("<unknown>".to_string())
+    };
+
+    // (kind, occulter, occulted) -> (type, separation)
+    let mut current: HashMap<(EclipseKind, Entity, Entity), (EclipseType, f32)> = HashMap::new();
+
+    let stars: Vec<_> = query
+        .iter()
+        .filter(|(_, _, _, _, is_star)| *is_star)
+        .map(|(t, b, _, _, _)| (t.translation, b.data.radius))
+        .collect();
+
+    for (transform, body, soi, entity, is_star) in query.iter() {
+        if entity == observer || is_star {
+            continue;
+        }
+
+        let satellite = (transform.translation, body.data.radius);
+
+        if soi.primary == Some(observer) {
+            for star in &stars {
+                if let Some(shadow) = shadow(
+                    *star,
+                    satellite,
+                    (observer_position, observer_radius),
+                    false,
+                ) {
+                    current.insert((EclipseKind::Solar, entity, observer), shadow);
+                }
+
+                if let Some(shadow) =
+                    shadow(*star, (observer_position, observer_radius), satellite, true)
+                {
+                    current.insert((EclipseKind::Lunar, observer, entity), shadow);
+                }
+            }
+        }
+
+        for (other_transform, other_body, _, other, other_is_star) in query.iter() {
+            if other == entity || other == observer || other_is_star {
+                continue;
+            }
+
+            if let Some(occultation) = occultation(
+                observer_position,
+                satellite,
+                (other_transform.translation, other_body.data.radius),
+            ) {
+                current.insert((EclipseKind::Occultation, entity, other), occultation);
+            }
+        }
+    }
+
+    let observer_name = name(observer_body);
+
+    for (key, (eclipse_type, separation)) in current.iter() {
+        let (kind, occulter, occulted) = *key;
+
+        let eclipse = eclipses.ongoing.entry(*key).or_insert_with(|| Eclipse {
+            kind,
+            eclipse_type: *eclipse_type,
+            observer: observer_name.clone(),
+            occulter: query.get(occulter).map(|q| name(q.1)).unwrap_or_default(),
+            occulted: query.get(occulted).map(|q| name(q.1)).unwrap_or_default(),
+            start: time,
+            maximum: time,
+            end: None,
+            minimum_separation: *separation,
+        });
+
+        eclipse.eclipse_type = eclipse.eclipse_type.max(*eclipse_type);
+
+        if *separation < eclipse.minimum_separation {
+            eclipse.minimum_separation = *separation;
+            eclipse.maximum = time;
+        }
+    }
+
+    let over: Vec<_> = eclipses
+        .ongoing
+        .keys()
+        .filter(|key| !current.contains_key(*key))
+        .copied()
+        .collect();
+
+    for key in over {
+        if let Some(mut eclipse) = eclipses.ongoing.remove(&key) {
+            eclipse.end = Some(time);
+
+            events.send(EclipseEvent(eclipse.clone()));
+            eclipses.finished.push(eclipse);
+        }
+    }
+}
+
+pub fn eclipse_log_system(mut events: EventReader<EclipseEvent>) {
+    for EclipseEvent(eclipse) in events.read() {
+        info!(
+            "{} ({}) of {} by {} seen from {}: JD {:.4} to {:.4}, maximum at {:.4}",
+            eclipse.kind,
+            eclipse.eclipse_type,
+            eclipse.occulted,
+            eclipse.occulter,
+            eclipse.observer,
+            eclipse.start,
+            eclipse.end.unwrap_or(eclipse.maximum),
+            eclipse.maximum
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch,
+        cli::{Cli, Command},
+        simulation::time::{simulation_time_system, JulianDate},
+    };
+
+    // Radii in AU
+    const SUN: f32 = 4.65e-3;
+    const EARTH: f32 = 4.26e-5;
+    const MOON: f32 = 1.16e-5;
+
+    #[test]
+    fn finds_lunar_eclipse_types() {
+        // The Moon 0.00257 AU behind the Earth, where the umbra is about 3.1e-5 AU
+        // and the penumbra about 5.5e-5 AU wide
+        let star = (Vec3::ZERO, SUN);
+        let earth = (Vec3::X, EARTH);
+        let moon = |offset: f32| (Vec3::new(1.002_57, offset, 0.0), MOON);
+
+        let cases = [
+            (0.0, Some(EclipseType::Total)),
+            (3e-5, Some(EclipseType::Partial)),
+            (5e-5, Some(EclipseType::Penumbral)),
+            (7e-5, None),
+        ];
+
+        for (offset, expected) in cases {
+            let result = shadow(star, earth, moon(offset), true);
+            assert_eq!(result.map(|(t, _)| t), expected, "{} AU off", offset);
+
+            if let Some((_, separation)) = result {
+                assert!((separation - offset).abs() < 1e-6);
+            }
+        }
+
+        // No shadow on the side facing the star
+        assert_eq!(
+            shadow(star, earth, (Vec3::new(0.997_43, 0.0, 0.0), MOON), true),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_solar_eclipse_types() {
+        // The umbra of the Moon ends about 0.0025 AU behind it, so the Earth is inside it
+        // when the Moon is near perigee and past its tip near apogee
+        let star = (Vec3::ZERO, SUN);
+        let earth = |offset: f32| (Vec3::new(1.0, offset, 0.0), EARTH);
+        let moon = |distance: f32| (Vec3::new(1.0 - distance, 0.0, 0.0), MOON);
+
+        let cases = [
+            (0.0024, 0.0, Some(EclipseType::Total)),
+            (0.0027, 0.0, Some(EclipseType::Annular)),
+            (0.0027, 5.5e-5, Some(EclipseType::Partial)),
+            (0.0027, 8e-5, None),
+        ];
+
+        for (distance, offset, expected) in cases {
+            assert_eq!(
+                shadow(star, moon(distance), earth(offset), false).map(|(t, _)| t),
+                expected,
+                "Moon {} AU away, Earth {} AU off",
+                distance,
+                offset
+            );
+        }
+    }
+
+    #[test]
+    fn finds_occultation_types() {
+        // The occulter covers 0.1 radians
+        let observer = Vec3::ZERO;
+        let occulter = (Vec3::X * 10.0, 1.0);
+
+        let cases = [
+            ((Vec3::X * 20.0, 1.0), Some(EclipseType::Total)),
+            ((Vec3::X * 20.0, 4.0), Some(EclipseType::Annular)),
+            ((Vec3::new(20.0, 2.4, 0.0), 1.0), Some(EclipseType::Partial)),
+            ((Vec3::new(20.0, 4.0, 0.0), 1.0), None),
+            // In front of the occulter
+            ((Vec3::X * 5.0, 1.0), None),
+        ];
+
+        for (occulted, expected) in cases {
+            assert_eq!(
+                occultation(observer, occulter, occulted).map(|(t, _)| t),
+                expected,
+                "{:?}",
+                occulted
+            );
+        }
+
+        let (_, separation) =
+            occultation(observer, occulter, (Vec3::new(20.0, 2.4, 0.0), 1.0)).unwrap();
+        assert!((separation - 0.12_f32.atan()).abs() < 1e-6);
+
+        // From inside the occulter
+        assert_eq!(
+            occultation(Vec3::X * 9.5, occulter, (Vec3::X * 20.0, 1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_known_eclipses() {
+        // We only look every 72 minutes, so the maxima are off by up to half of that on top of the drift
+        let Ok(Command::Run(cli)) =
+            Cli::parse(["--step", "0.05", "--integrator", "rk4"].map(String::from))
+        else {
+            panic!("could not parse the arguments");
+        };
+
+        let mut app = batch::setup(&cli).unwrap();
+        app.add_systems(
+            FixedUpdate,
+            eclipse_detection_system.after(simulation_time_system),
+        )
+        .add_event::<EclipseEvent>()
+        .insert_resource(Eclipses::default());
+
+        let world = app.world_mut();
+
+        let sun = world
+            .query::<(Entity, &Body)>()
+            .iter(world)
+            .find(|(_, b)| b.metadata.name.as_deref() == Some("Sun"))
+            .map(|(e, _)| e)
+            .unwrap();
+        world.entity_mut(sun).insert(Star {});
+
+        // A couple of weeks past the last one, 2026-09-10
+        batch::run_until(world, JulianDate::new(2461293.5));
+
+        let eclipses: Vec<&Eclipse> = world
+            .resource::<Eclipses>()
+            .finished
+            .iter()
+            .filter(|e| e.kind != EclipseKind::Occultation)
+            .collect();
+
+        for (kind, maximum) in KNOWN_EARTH_ECLIPSES {
+            assert!(
+                eclipses
+                    .iter()
+                    .any(|e| e.kind == kind && (e.maximum - maximum).abs() < 1.0 / 24.0),
+                "{} at JD {} not found in {:#?}",
+                kind,
+                maximum,
+                eclipses
+            );
+        }
+
+        assert_eq!(
+            eclipses.len(),
+            KNOWN_EARTH_ECLIPSES.len(),
+            "{:#?}",
+            eclipses
+        );
+    }
+}
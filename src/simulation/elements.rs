@@ -0,0 +1,75 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    body::Body,
+    conics::SphereOfInfluence,
+    orbit::{state_to_elements, world_to_ecliptic},
+    settings::SimulationParameters,
+};
+
+/// Osculating orbital elements relative to the body's primary, updated every physics step.
+/// Angles are in degrees relative to the ecliptic, like in our JSON data.
+#[derive(Debug, Component, Clone, Copy, Default)]
+pub struct OsculatingElements {
+    /// The body these elements are relative to, `None` if the body has no primary
+    pub primary: Option<Entity>,
+
+    /// World units (AU / `unit_scale`), negative for hyperbolic orbits
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub longitude_of_ascending_node: f32,
+
+    /// AKA argument of perifocus
+    pub argument_of_periapsis: f32,
+    pub true_anomaly: f32,
+    pub mean_anomaly: f32,
+
+    /// Sidereal orbital period in days, infinite for unbound orbits
+    pub period: f32,
+}
+
+pub fn osculating_elements_system(
+    mut query: Query<(
+        &Transform,
+        &Body,
+        &SphereOfInfluence,
+        &mut OsculatingElements,
+        Entity,
+    )>,
+    parameters: Res<SimulationParameters>,
+) {
+    let states: HashMap<Entity, (Vec3, Vec3, f32)> = query
+        .iter()
+        .map(|(t, b, _, _, e)| (e, (t.translation, b.data.velocity, b.data.mass)))
+        .collect();
+
+    for (transform, body, soi, mut elements, _) in query.iter_mut() {
+        let Some((primary_position, primary_velocity, primary_mass)) =
+            soi.primary.and_then(|p| states.get(&p))
+        else {
+            *elements = OsculatingElements::default();
+            continue;
+        };
+
+        let mu = (parameters.gravitational_constant * (primary_mass + body.data.mass)) as f64;
+
+        let e = state_to_elements(
+            world_to_ecliptic(transform.translation - *primary_position),
+            world_to_ecliptic(body.data.velocity - *primary_velocity),
+            mu,
+        );
+
+        *elements = OsculatingElements {
+            primary: soi.primary,
+            semi_major_axis: e.semi_major_axis as f32,
+            eccentricity: e.eccentricity as f32,
+            inclination: e.inclination.to_degrees() as f32,
+            longitude_of_ascending_node: e.longitude_of_ascending_node.to_degrees() as f32,
+            argument_of_periapsis: e.argument_of_periapsis.to_degrees() as f32,
+            true_anomaly: e.true_anomaly.to_degrees() as f32,
+            mean_anomaly: e.mean_anomaly.to_degrees() as f32,
+            period: e.period as f32,
+        };
+    }
+}
@@ -0,0 +1,636 @@
+//! Exports the state of bodies over time to CSV or JSON Lines, for plotting in other tools.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{math::DVec3, prelude::*, utils::HashMap};
+use serde_json::{json, Map, Value};
+
+use super::{
+    body::{Body, Sun, TestBody},
+    elements::OsculatingElements,
+    frame::{barycenter, FrameCenter},
+    orbit::world_to_ecliptic,
+    settings::SimulationParameters,
+    time::{JulianDate, SimulationTime},
+};
+
+/// Version of the columns and header, bumped whenever either changes.
+pub const EXPORT_VERSION: u32 = 1;
+
+pub const COLUMNS: [&str; 23] = [
+    "julian_date",
+    "utc",
+    "body",
+    "x",
+    "y",
+    "z",
+    "vx",
+    "vy",
+    "vz",
+    "distance",
+    "speed",
+    "primary",
+    "semi_major_axis",
+    "eccentricity",
+    "inclination",
+    "longitude_of_ascending_node",
+    "argument_of_periapsis",
+    "true_anomaly",
+    "mean_anomaly",
+    "period",
+    "periapsis",
+    "apoapsis",
+    "specific_orbital_energy",
+];
+
+/// Units of the columns, in the same order.
+const UNITS: [&str; 23] = [
+    "days (TDB)",
+    "UTC",
+    "",
+    "AU",
+    "AU",
+    "AU",
+    "AU/day",
+    "AU/day",
+    "AU/day",
+    "AU",
+    "AU/day",
+    "",
+    "AU",
+    "",
+    "degrees",
+    "degrees",
+    "degrees",
+    "degrees",
+    "degrees",
+    "days",
+    "AU",
+    "AU",
+    "AU^2/day^2",
+];
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "json_lines" => Some(Self::JsonLines),
+            _ => None,
+        }
+    }
+
+    /// JSON Lines for `.jsonl` files, CSV for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("jsonl") => Self::JsonLines,
+            _ => Self::Csv,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportSettings {
+    pub path: PathBuf,
+    pub format: ExportFormat,
+
+    /// Names of the bodies to export, all of them if empty
+    pub bodies: Vec<String>,
+
+    /// Simulated days between rows
+    pub interval: f64,
+
+    /// What positions and velocities are relative to
+    pub center: FrameCenter,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("exports/export.csv"),
+            format: ExportFormat::default(),
+            bodies: Vec::new(),
+            interval: 1.0,
+            center: FrameCenter::Sun,
+        }
+    }
+}
+
+impl ExportSettings {
+    /// The header, which records the frame and units so the file stands on its own.
+    pub fn header(&self) -> String {
+        let center = match self.center {
+            FrameCenter::Sun => "Sun",
+            FrameCenter::Barycenter => "Solar System barycenter",
+        };
+        let frame = format!("Ecliptic J2000, centered on the {}", center);
+        let elements = "Osculating elements are relative to the primary, \
+            whose sphere of influence the body is in";
+
+        match self.format {
+            ExportFormat::Csv => {
+                let units = COLUMNS
+                    .iter()
+                    .zip(UNITS)
+                    .filter(|(_, unit)| !unit.is_empty())
+                    .map(|(column, unit)| format!("{} ({})", column, unit))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!(
+                    "# Solar system export, version {}\n# Frame: {}\n# {}\n# Interval: {} days\n# Units: {}\n{}\n",
+                    EXPORT_VERSION,
+                    frame,
+                    elements,
+                    self.interval,
+                    units,
+                    COLUMNS.join(",")
+                )
+            }
+            ExportFormat::JsonLines => {
+                let units: Map<String, Value> = COLUMNS
+                    .iter()
+                    .zip(UNITS)
+                    .filter(|(_, unit)| !unit.is_empty())
+                    .map(|(column, unit)| (column.to_string(), Value::from(unit)))
+                    .collect();
+
+                let header = json!({
+                    "header": {
+                        "version": EXPORT_VERSION,
+                        "frame": frame,
+                        "elements": elements,
+                        "interval": self.interval,
+                        "units": units,
+                        "columns": COLUMNS,
+                    }
+                });
+
+                format!("{}\n", header)
+            }
+        }
+    }
+}
+
+/// The state of one body at one time, relative to the center of the export.
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub time: JulianDate,
+    pub body: String,
+
+    /// Ecliptic, AU
+    pub position: DVec3,
+
+    /// Ecliptic, AU/day
+    pub velocity: DVec3,
+
+    /// Name of the primary the elements are relative to
+    pub primary: Option<String>,
+    pub elements: OsculatingElements,
+
+    /// G * (M + m) of the body and its primary, AU^3/day^2
+    pub mu: f64,
+}
+
+impl Row {
+    /// The values of the row, in the order of `COLUMNS`. Missing and non-finite values are null.
+    pub fn values(&self) -> Vec<Value> {
+        let mut values = vec![
+            Value::from(self.time.value()),
+            Value::from(self.time.tdb_to_utc().to_string()),
+            Value::from(self.body.as_str()),
+            Value::from(self.position.x),
+            Value::from(self.position.y),
+            Value::from(self.position.z),
+            Value::from(self.velocity.x),
+            Value::from(self.velocity.y),
+            Value::from(self.velocity.z),
+            Value::from(self.position.length()),
+            Value::from(self.velocity.length()),
+        ];
+
+        let Some(primary) = &self.primary else {
+            values.resize(COLUMNS.len(), Value::Null);
+            return values;
+        };
+
+        let e = &self.elements;
+        let a = e.semi_major_axis as f64;
+        let eccentricity = e.eccentricity as f64;
+
+        values.extend([
+            Value::from(primary.as_str()),
+            Value::from(a),
+            Value::from(eccentricity),
+            Value::from(e.inclination as f64),
+            Value::from(e.longitude_of_ascending_node as f64),
+            Value::from(e.argument_of_periapsis as f64),
+            Value::from(e.true_anomaly as f64),
+            Value::from(e.mean_anomaly as f64),
+            Value::from(e.period as f64),
+            Value::from(a * (1.0 - eccentricity)),
+            match eccentricity < 1.0 {
+                true => Value::from(a * (1.0 + eccentricity)),
+                false => Value::Null,
+            },
+            Value::from(-self.mu / (2.0 * a)),
+        ]);
+
+        values
+    }
+
+    pub fn format(&self, format: ExportFormat) -> String {
+        let values = self.values();
+
+        match format {
+            ExportFormat::Csv => {
+                let fields: Vec<String> = values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::Null => String::new(),
+                        Value::String(s) if s.contains([',', '"', '\n']) => {
+                            format!("\"{}\"", s.replace('"', "\"\""))
+                        }
+                        Value::String(s) => s,
+                        value => value.to_string(),
+                    })
+                    .collect();
+
+                format!("{}\n", fields.join(","))
+            }
+            ExportFormat::JsonLines => {
+                let object: Map<String, Value> = COLUMNS
+                    .iter()
+                    .map(|column| column.to_string())
+                    .zip(values)
+                    .collect();
+
+                format!("{}\n", Value::Object(object))
+            }
+        }
+    }
+}
+
+/// Writes the selected bodies to a file every `interval` simulated days while exporting.
+#[derive(Resource, Default)]
+pub struct Exporter {
+    pub settings: ExportSettings,
+
+    pub rows: usize,
+
+    /// Why the last export failed
+    pub error: Option<String>,
+
+    writer: Option<BufWriter<File>>,
+    last: Option<JulianDate>,
+}
+
+impl Exporter {
+    /// Creates the file and writes the header, rows follow as the simulation runs.
+    pub fn start(&mut self) -> Result<(), String> {
+        self.stop();
+
+        let path = &self.settings.path;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        }
+
+        let file = File::create(path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+
+        let mut writer = BufWriter::new(file);
+
+        writer
+            .write_all(self.settings.header().as_bytes())
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+
+        info!(
+            "Exporting {} to {} every {} days",
+            match self.settings.bodies.is_empty() {
+                true => "all bodies".to_string(),
+                false => self.settings.bodies.join(", "),
+            },
+            path.display(),
+            self.settings.interval
+        );
+
+        self.writer = Some(writer);
+        self.rows = 0;
+        self.last = None;
+        self.error = None;
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        let Some(mut writer) = self.writer.take() else {
+            return;
+        };
+
+        match writer.flush() {
+            Ok(_) => info!(
+                "Exported {} rows to {}",
+                self.rows,
+                self.settings.path.display()
+            ),
+            Err(e) => error!("Could not write {}: {}", self.settings.path.display(), e),
+        }
+    }
+
+    pub fn is_exporting(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    fn write(&mut self, rows: &[Row]) -> Result<(), String> {
+        let writer = self.writer.as_mut().unwrap();
+
+        for row in rows {
+            writer
+                .write_all(row.format(self.settings.format).as_bytes())
+                .map_err(|e| format!("Could not write {}: {}", self.settings.path.display(), e))?;
+        }
+
+        self.rows += rows.len();
+
+        Ok(())
+    }
+}
+
+pub fn export_system(
+    mut exporter: ResMut<Exporter>,
+    time: Res<SimulationTime>,
+    parameters: Res<SimulationParameters>,
+    sun: Option<Res<Sun>>,
+    query: Query<(Entity, &Transform, &Body, &OsculatingElements), Without<TestBody>>,
+) {
+    if !exporter.is_exporting() {
+        return;
+    }
+
+    if let Some(last) = exporter.last {
+        if time.current.days_since(&last).abs() < exporter.settings.interval {
+            return;
+        }
+    }
+
+    let (center_position, center_velocity) = match exporter.settings.center {
+        FrameCenter::Sun => sun
+            .and_then(|sun| query.get(sun.0).ok())
+            .map(|(_, t, b, _)| (t.translation, b.data.velocity))
+            .unwrap_or_default(),
+        FrameCenter::Barycenter => barycenter(
+            query
+                .iter()
+                .map(|(_, t, b, _)| (t.translation, b.data.velocity, b.data.mass)),
+        ),
+    };
+
+    let bodies: HashMap<Entity, (&str, f32)> = query
+        .iter()
+        .map(|(entity, _, body, _)| {
+            (
+                entity,
+                (
+                    body.metadata.name.as_deref().unwrap_or("<unknown>"),
+                    body.data.mass,
+                ),
+            )
+        })
+        .collect();
+
+    // The simulation is in units of `unit_scale` AU, the export in AU
+    let length = parameters.unit_scale as f64;
+
+    let row = |(_, transform, body, elements): (Entity, &Transform, &Body, &OsculatingElements)| {
+        let primary = elements.primary.and_then(|p| bodies.get(&p));
+
+        Row {
+            time: time.current,
+            body: body
+                .metadata
+                .name
+                .clone()
+                .unwrap_or("<unknown>".to_string()),
+            position: world_to_ecliptic(transform.translation - center_position) * length,
+            velocity: world_to_ecliptic(body.data.velocity - center_velocity) * length,
+            primary: primary.map(|(name, _)| name.to_string()),
+            elements: OsculatingElements {
+                semi_major_axis: elements.semi_major_axis * parameters.unit_scale,
+                ..*elements
+            },
+            mu: primary.map_or(0.0, |(_, mass)| {
+                (parameters.gravitational_constant * (mass + body.data.mass)) as f64
+                    * length.powi(3)
+            }),
+        }
+    };
+
+    let rows: Vec<Row> = match exporter.settings.bodies.is_empty() {
+        true => query.iter().map(row).collect(),
+        false => exporter
+            .settings
+            .bodies
+            .iter()
+            .filter_map(|name| {
+                let found = query
+                    .iter()
+                    .find(|(_, _, body, _)| body.metadata.name.as_ref() == Some(name));
+
+                // Only worth saying once
+                if found.is_none() && exporter.last.is_none() {
+                    warn!("Could not find body \"{}\" to export", name);
+                }
+
+                found.map(row)
+            })
+            .collect(),
+    };
+
+    match exporter.write(&rows) {
+        Ok(_) => exporter.last = Some(time.current),
+        Err(e) => {
+            error!("Stopped exporting: {}", e);
+            exporter.error = Some(e);
+            exporter.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn earth() -> Row {
+        Row {
+            time: JulianDate::new(2451545.0),
+            body: "Earth".to_string(),
+            position: DVec3::new(1.0, 0.0, 0.0),
+            velocity: DVec3::new(0.0, 0.0172, 0.0),
+            primary: Some("Sun".to_string()),
+            elements: OsculatingElements {
+                semi_major_axis: 1.0,
+                eccentricity: 0.5,
+                period: 365.25,
+                ..default()
+            },
+            mu: 2.959e-4,
+        }
+    }
+
+    #[test]
+    fn writes_csv() {
+        let settings = ExportSettings::default();
+        let header = settings.header();
+
+        assert!(header.contains("# Frame: Ecliptic J2000, centered on the Sun"));
+        assert!(header.contains("x (AU)"));
+        assert!(header.ends_with(&format!("{}\n", COLUMNS.join(","))));
+
+        let line = earth().format(ExportFormat::Csv);
+        let fields: Vec<&str> = line.trim_end().split(',').collect();
+
+        assert_eq!(fields.len(), COLUMNS.len());
+        assert_eq!(fields[0], "2451545.0");
+        assert_eq!(fields[2], "Earth");
+        assert_eq!(fields[9], "1.0");
+        assert_eq!(fields[11], "Sun");
+        assert_eq!(fields[20], "0.5");
+        assert_eq!(fields[21], "1.5");
+
+        // Without a primary there are no elements
+        let sun = Row {
+            primary: None,
+            body: "Sun, \"Sol\"".to_string(),
+            ..earth()
+        };
+        assert!(sun
+            .format(ExportFormat::Csv)
+            .starts_with("2451545.0,2000-01-01 11:58:55.816,\"Sun, \"\"Sol\"\"\",1.0,"));
+        assert!(sun.format(ExportFormat::Csv).ends_with(",,,,,,,,,,,,\n"));
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let settings = ExportSettings {
+            format: ExportFormat::JsonLines,
+            center: FrameCenter::Barycenter,
+            ..default()
+        };
+
+        let header: Value = serde_json::from_str(&settings.header()).unwrap();
+        assert_eq!(header["header"]["units"]["vx"], "AU/day");
+        assert_eq!(header["header"]["columns"][22], "specific_orbital_energy");
+        assert!(header["header"]["frame"]
+            .as_str()
+            .unwrap()
+            .contains("barycenter"));
+
+        // Unbound orbits have no apoapsis
+        let row = Row {
+            elements: OsculatingElements {
+                semi_major_axis: -2.0,
+                eccentricity: 1.5,
+                ..default()
+            },
+            ..earth()
+        };
+
+        let line = row.format(ExportFormat::JsonLines);
+        assert_eq!(line.lines().count(), 1);
+
+        let object: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(object["body"], "Earth");
+        assert_eq!(object["periapsis"], 1.0);
+        assert_eq!(object["apoapsis"], Value::Null);
+        assert!(object["specific_orbital_energy"].as_f64().unwrap() > 0.0);
+    }
+
+    /// The first row exported for Io, running the Jovian system headless.
+    fn export_io(unit_scale: f32) -> Value {
+        use crate::{
+            batch,
+            cli::{Cli, Command},
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "export-{}-{}.jsonl",
+            std::process::id(),
+            unit_scale
+        ));
+
+        let args = format!(
+            "--scenario scenarios/jupiter_system.ron --unit-scale {} --export {} --export-bodies Io",
+            unit_scale,
+            path.display()
+        );
+        let Ok(Command::Run(cli)) = Cli::parse(args.split_whitespace().map(String::from)) else {
+            panic!("could not parse {}", args);
+        };
+
+        let mut app = batch::setup(&cli).unwrap();
+        batch::run(&mut app, 0.1);
+        app.world_mut().resource_mut::<Exporter>().stop();
+
+        let exported = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        serde_json::from_str(exported.lines().nth(1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn exports_astronomical_units() {
+        let au = export_io(1.0);
+        let scaled = export_io(0.001);
+
+        for column in [
+            "x",
+            "vy",
+            "distance",
+            "semi_major_axis",
+            "apoapsis",
+            "specific_orbital_energy",
+        ] {
+            let (expected, value) = (
+                au[column].as_f64().unwrap(),
+                scaled[column].as_f64().unwrap(),
+            );
+
+            assert!(
+                (value - expected).abs() < 1e-3 * expected.abs(),
+                "{}: {} instead of {}",
+                column,
+                value,
+                expected
+            );
+        }
+
+        // Io is 422 000 km from Jupiter
+        assert!((au["semi_major_axis"].as_f64().unwrap() - 0.00282).abs() < 1e-4);
+    }
+
+    #[test]
+    fn picks_formats() {
+        assert_eq!(ExportFormat::parse("jsonl"), Some(ExportFormat::JsonLines));
+        assert_eq!(ExportFormat::parse("xml"), None);
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out/earth.jsonl")),
+            ExportFormat::JsonLines
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("earth.csv")),
+            ExportFormat::Csv
+        );
+    }
+}
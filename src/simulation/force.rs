@@ -0,0 +1,284 @@
+use bevy::prelude::*;
+
+use super::settings::{ForceLawKind, SimulationParameters};
+
+/// MOND acceleration scale, 1.2e-10 m/s^2 in Astronomical Units per day^2
+pub const MOND_ACCELERATION: f32 = 5.988e-12;
+
+/// How a body of a given mass accelerates another body.
+pub trait ForceLaw {
+    /// Returns the acceleration caused by a body of `mass` (Solar Mass) at `offset` (AU) from us.
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3;
+
+    /// Returns the potential (AU^2/day^2) at `distance` (AU) from a body of `mass` (Solar Mass),
+    /// or `None` if the law has no potential and so conserves no energy.
+    fn potential(&self, _distance: f64, _mass: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// The potential of a law that clamps the squared distance to `softening_factor`, from its potential and the
+/// magnitude of its acceleration beyond that. Within it the acceleration stays what it is at the softening distance.
+fn clamped_potential(
+    distance: f64,
+    softening_factor: f32,
+    potential: impl Fn(f64) -> f64,
+    acceleration: impl Fn(f64) -> f64,
+) -> f64 {
+    let softening_distance = (softening_factor as f64).sqrt();
+
+    if distance >= softening_distance {
+        return potential(distance);
+    }
+
+    potential(softening_distance)
+        - acceleration(softening_distance) * (softening_distance - distance)
+}
+
+/// Newton's law of universal gravitation.
+/// The squared distance is clamped to the softening factor, so close encounters do not blow up.
+pub struct Newtonian {
+    pub gravitational_constant: f32,
+    pub softening_factor: f32,
+}
+
+impl ForceLaw for Newtonian {
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3 {
+        let distance_squared = offset.length_squared().max(self.softening_factor);
+
+        offset.normalize() * (self.gravitational_constant * mass / distance_squared)
+    }
+
+    fn potential(&self, distance: f64, mass: f64) -> Option<f64> {
+        let k = self.gravitational_constant as f64 * mass;
+
+        Some(clamped_potential(
+            distance,
+            self.softening_factor,
+            |r| -k / r,
+            |r| k / (r * r),
+        ))
+    }
+}
+
+/// Newtonian gravity of a Plummer sphere, AKA the standard N-body softening.
+/// Unlike clamping, this is smooth, at the cost of weakening gravity at distances comparable to the softening length.
+pub struct PlummerSoftened {
+    pub gravitational_constant: f32,
+
+    /// Square of the softening length, AU^2
+    pub softening_length_squared: f32,
+}
+
+impl ForceLaw for PlummerSoftened {
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3 {
+        let denominator = (offset.length_squared() + self.softening_length_squared).powf(1.5);
+
+        offset * (self.gravitational_constant * mass / denominator)
+    }
+
+    fn potential(&self, distance: f64, mass: f64) -> Option<f64> {
+        let k = self.gravitational_constant as f64 * mass;
+
+        Some(-k / (distance * distance + self.softening_length_squared as f64).sqrt())
+    }
+}
+
+/// Newtonian gravity with an additional Yukawa term, from the potential -GM/r * (1 + α e^(-r/λ)).
+pub struct Yukawa {
+    pub gravitational_constant: f32,
+    pub softening_factor: f32,
+
+    /// α, relative to Newtonian gravity
+    pub strength: f32,
+
+    /// λ, Astronomical Units
+    pub range: f32,
+}
+
+impl ForceLaw for Yukawa {
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3 {
+        let distance_squared = offset.length_squared().max(self.softening_factor);
+        let distance = distance_squared.sqrt();
+        let x = distance / self.range;
+
+        offset.normalize()
+            * (self.gravitational_constant * mass / distance_squared)
+            * (1.0 + self.strength * (1.0 + x) * (-x).exp())
+    }
+
+    fn potential(&self, distance: f64, mass: f64) -> Option<f64> {
+        let k = self.gravitational_constant as f64 * mass;
+        let (strength, range) = (self.strength as f64, self.range as f64);
+
+        Some(clamped_potential(
+            distance,
+            self.softening_factor,
+            |r| -k / r * (1.0 + strength * (-r / range).exp()),
+            |r| k / (r * r) * (1.0 + strength * (1.0 + r / range) * (-r / range).exp()),
+        ))
+    }
+}
+
+/// Modified Newtonian Dynamics, using the "simple" interpolating function.
+/// Note: this is applied to each pair of bodies separately,
+/// instead of to the total Newtonian field as a proper MOND theory would.
+/// The pull between two bodies is then not equal and opposite, so there is no potential and no conserved energy.
+pub struct Mond {
+    pub gravitational_constant: f32,
+    pub softening_factor: f32,
+
+    /// a0, Astronomical Units per day^2
+    pub acceleration_scale: f32,
+}
+
+impl ForceLaw for Mond {
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3 {
+        let distance_squared = offset.length_squared().max(self.softening_factor);
+        let newtonian = self.gravitational_constant * mass / distance_squared;
+
+        if newtonian <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        let y = newtonian / self.acceleration_scale;
+
+        offset.normalize() * newtonian * (0.5 + (0.25 + 1.0 / y).sqrt())
+    }
+}
+
+/// Gravity falling off as 1/r^n, for "what if" experiments.
+/// The constant is chosen so the force matches Newtonian gravity at 1 AU.
+pub struct PowerLaw {
+    pub gravitational_constant: f32,
+    pub softening_factor: f32,
+    pub exponent: f32,
+}
+
+impl ForceLaw for PowerLaw {
+    fn acceleration(&self, offset: Vec3, mass: f32) -> Vec3 {
+        let distance_squared = offset.length_squared().max(self.softening_factor);
+
+        offset.normalize() * (self.gravitational_constant * mass)
+            / distance_squared.powf(self.exponent / 2.0)
+    }
+
+    fn potential(&self, distance: f64, mass: f64) -> Option<f64> {
+        let k = self.gravitational_constant as f64 * mass;
+        let n = self.exponent as f64;
+
+        Some(clamped_potential(
+            distance,
+            self.softening_factor,
+            |r| match n == 1.0 {
+                true => k * r.ln(),
+                false => -k / ((n - 1.0) * r.powf(n - 1.0)),
+            },
+            |r| k / r.powf(n),
+        ))
+    }
+}
+
+impl SimulationParameters {
+    /// Returns the force law selected by `force_law`, configured with our parameters.
+    pub fn force_law(&self) -> Box<dyn ForceLaw> {
+        let gravitational_constant = self.gravitational_constant;
+        let softening_factor = self.softening_factor;
+
+        match self.force_law {
+            ForceLawKind::Newtonian => Box::new(Newtonian {
+                gravitational_constant,
+                softening_factor,
+            }),
+            ForceLawKind::Plummer => Box::new(PlummerSoftened {
+                gravitational_constant,
+                softening_length_squared: softening_factor,
+            }),
+            ForceLawKind::Yukawa => Box::new(Yukawa {
+                gravitational_constant,
+                softening_factor,
+                strength: self.yukawa_strength,
+                range: self.yukawa_range,
+            }),
+            ForceLawKind::Mond => Box::new(Mond {
+                gravitational_constant,
+                softening_factor,
+                acceleration_scale: self.mond_acceleration,
+            }),
+            ForceLawKind::PowerLaw => Box::new(PowerLaw {
+                gravitational_constant,
+                softening_factor,
+                exponent: self.power_law_exponent,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn potentials_match_accelerations() {
+        let laws: [(&str, Box<dyn ForceLaw>); 4] = [
+            (
+                "newtonian",
+                Box::new(Newtonian {
+                    gravitational_constant: 2.959e-4,
+                    softening_factor: 0.01,
+                }),
+            ),
+            (
+                "plummer",
+                Box::new(PlummerSoftened {
+                    gravitational_constant: 2.959e-4,
+                    softening_length_squared: 0.01,
+                }),
+            ),
+            (
+                "yukawa",
+                Box::new(Yukawa {
+                    gravitational_constant: 2.959e-4,
+                    softening_factor: 0.01,
+                    strength: 0.5,
+                    range: 2.0,
+                }),
+            ),
+            (
+                "power law",
+                Box::new(PowerLaw {
+                    gravitational_constant: 2.959e-4,
+                    softening_factor: 0.01,
+                    exponent: 2.5,
+                }),
+            ),
+        ];
+
+        // Within and beyond the softening distance of 0.1 AU
+        for (name, law) in laws {
+            for distance in [0.05, 0.5, 1.0, 5.0] {
+                let h = 1e-6;
+                let slope = (law.potential(distance + h, 1.0).unwrap()
+                    - law.potential(distance - h, 1.0).unwrap())
+                    / (2.0 * h);
+                let acceleration = law.acceleration(Vec3::X * distance as f32, 1.0).x as f64;
+
+                assert!(
+                    (slope - acceleration).abs() < 1e-4 * acceleration,
+                    "{} at {} AU: {} instead of {}",
+                    name,
+                    distance,
+                    slope,
+                    acceleration
+                );
+            }
+        }
+
+        let mond = Mond {
+            gravitational_constant: 2.959e-4,
+            softening_factor: 0.01,
+            acceleration_scale: MOND_ACCELERATION,
+        };
+        assert_eq!(mond.potential(1.0, 1.0), None);
+    }
+}
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use super::{
+    body::{Body, Sun},
+    settings::FollowBody,
+};
+
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum FrameCenter {
+    #[default]
+    Sun,
+    Barycenter,
+}
+
+impl FrameCenter {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sun" => Some(Self::Sun),
+            "barycenter" => Some(Self::Barycenter),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ReferenceFrame {
+    /// Shift the initial conditions into the barycentric frame on startup, zeroing the total momentum.
+    /// Otherwise the whole system slowly drifts away, as the Sun starts at rest in our data.
+    /// Set by the scenario and `--no-barycentric-correction`.
+    pub barycentric_correction: bool,
+
+    /// What the grid and the camera are kept centred on
+    pub center: FrameCenter,
+
+    /// Where the center was on the previous frame, so we can move the camera along with it
+    pub center_position: Option<(FrameCenter, Vec3)>,
+}
+
+impl Default for ReferenceFrame {
+    fn default() -> Self {
+        Self {
+            barycentric_correction: true,
+            center: FrameCenter::default(),
+            center_position: None,
+        }
+    }
+}
+
+/// Returns the position and velocity of the center of mass of `bodies`.
+pub fn barycenter(bodies: impl Iterator<Item = (Vec3, Vec3, f32)>) -> (Vec3, Vec3) {
+    let (mut position, mut velocity, mut mass) = (Vec3::ZERO, Vec3::ZERO, 0.0);
+
+    for (p, v, m) in bodies {
+        position += p * m;
+        velocity += v * m;
+        mass += m;
+    }
+
+    if mass == 0.0 {
+        return (Vec3::ZERO, Vec3::ZERO);
+    }
+
+    (position / mass, velocity / mass)
+}
+
+pub fn barycentric_correction_system(
+    mut query: Query<(&mut Transform, &mut Body)>,
+    frame: Res<ReferenceFrame>,
+) {
+    if !frame.barycentric_correction {
+        return;
+    }
+
+    let (position, velocity) = barycenter(
+        query
+            .iter()
+            .map(|(t, b)| (t.translation, b.data.velocity, b.data.mass)),
+    );
+
+    info!(
+        "Shifting to the barycentric frame: offset {} AU, {} AU/day",
+        position, velocity
+    );
+
+    for (mut transform, mut body) in query.iter_mut() {
+        transform.translation -= position;
+        body.data.position -= position;
+        body.data.velocity -= velocity;
+    }
+}
+
+/// Keeps the camera moving along with the center of the frame, unless we are following a body.
+pub fn frame_center_system(
+    mut camera: Query<&mut Transform, With<Camera>>,
+    query: Query<(&Transform, &Body), Without<Camera>>,
+    mut frame: ResMut<ReferenceFrame>,
+    sun: Option<Res<Sun>>,
+    follow: Res<FollowBody>,
+) {
+    let center = match frame.center {
+        FrameCenter::Sun => sun
+            .and_then(|sun| query.get(sun.0).ok())
+            .map(|(t, _)| t.translation)
+            .unwrap_or(Vec3::ZERO),
+        FrameCenter::Barycenter => {
+            barycenter(
+                query
+                    .iter()
+                    .map(|(t, b)| (t.translation, b.data.velocity, b.data.mass)),
+            )
+            .0
+        }
+    };
+
+    // Switching centers should not teleport the camera
+    if let Some((previous_center, previous)) = frame.center_position {
+        if previous_center == frame.center && !(follow.is_active && follow.entity.is_some()) {
+            for mut transform in camera.iter_mut() {
+                transform.translation += center - previous;
+            }
+        }
+    }
+
+    frame.center_position = Some((frame.center, center));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch,
+        cli::{Cli, Command},
+    };
+
+    /// Name, velocity and mass of every body of the Jovian system, once it is set up.
+    fn bodies(args: &str) -> Vec<(String, Vec3, f32)> {
+        let args = format!("--scenario scenarios/jupiter_system.ron {}", args);
+        let Ok(Command::Run(cli)) = Cli::parse(args.split_whitespace().map(String::from)) else {
+            panic!("could not parse {}", args);
+        };
+
+        let mut app = batch::setup(&cli).unwrap();
+        let world = app.world_mut();
+
+        let mut bodies: Vec<_> = world
+            .query::<&Body>()
+            .iter(world)
+            .map(|b| {
+                (
+                    b.metadata.name.clone().unwrap_or_default(),
+                    b.data.velocity,
+                    b.data.mass,
+                )
+            })
+            .collect();
+
+        bodies.sort_by(|a, b| a.0.cmp(&b.0));
+        bodies
+    }
+
+    fn momentum(bodies: &[(String, Vec3, f32)]) -> Vec3 {
+        bodies.iter().map(|(_, v, m)| *v * *m).sum()
+    }
+
+    fn sun_velocity(bodies: &[(String, Vec3, f32)]) -> Vec3 {
+        bodies.iter().find(|(name, _, _)| name == "Sun").unwrap().1
+    }
+
+    #[test]
+    fn corrects_only_when_asked() {
+        // Jupiter carries almost all the momentum, the Sun has to move against it
+        let corrected = bodies("");
+        assert!(momentum(&corrected).length() < 1e-10);
+        assert!(sun_velocity(&corrected).length() > 1e-6);
+
+        // The Sun stays at rest as the scenario has it
+        let uncorrected = bodies("--no-barycentric-correction");
+        assert_eq!(sun_velocity(&uncorrected), Vec3::ZERO);
+        assert!(momentum(&uncorrected).length() > 1e-6);
+
+        // The correction takes the velocity of the barycenter off every body, the Sun started at rest
+        let shift = sun_velocity(&corrected);
+        for ((name, velocity, _), (_, corrected, _)) in uncorrected.iter().zip(&corrected) {
+            assert!((*velocity + shift - *corrected).length() < 1e-8, "{}", name);
+        }
+    }
+}
@@ -2,22 +2,33 @@ use std::f32::consts::PI;
 
 use bevy::prelude::*;
 
-use super::{body::Body, trajectory::LiveTrajectoryPreview};
+use super::{
+    body::Body,
+    conics::{HillSphere, SphereOfInfluence, SphereOverlays},
+    frame::ReferenceFrame,
+    lagrange::LagrangePoints,
+    trajectory::LiveTrajectoryPreview,
+};
 
 pub fn body_gizmo_system(
     mut gizmos: Gizmos,
-    query: Query<(&Body, &Transform)>,
+    query: Query<(&Body, &Transform, &HillSphere, &SphereOfInfluence)>,
     trajectory: ResMut<super::trajectory::Trajectories>,
     t: Res<LiveTrajectoryPreview>,
+    frame: Res<ReferenceFrame>,
+    lagrange: Res<LagrangePoints>,
+    overlays: Res<SphereOverlays>,
 ) {
+    let center = frame.center_position.map(|(_, p)| p).unwrap_or(Vec3::ZERO);
+
     gizmos.grid(
-        Isometry3d::from_rotation(Quat::from_rotation_x(PI / 2.0)),
+        Isometry3d::new(center, Quat::from_rotation_x(PI / 2.0)),
         UVec2::splat(50),
         Vec2::new(1.0, 1.0),
         LinearRgba::new(0.15, 0.15, 0.15, 0.2),
     );
 
-    for (body, transform) in query.iter() {
+    for (body, transform, _, _) in query.iter() {
         gizmos.sphere(
             Isometry3d {
                 rotation: Quat::IDENTITY,
@@ -35,6 +46,18 @@ pub fn body_gizmo_system(
         );
     }
 
+    for (body, transform, hill, soi) in query.iter() {
+        let isometry = Isometry3d::from_translation(transform.translation);
+
+        if overlays.hill_sphere && hill.radius.is_finite() && hill.radius > 0.0 {
+            gizmos.sphere(isometry, hill.radius, body.metadata.color.with_alpha(0.15));
+        }
+
+        if overlays.sphere_of_influence && soi.radius.is_finite() && soi.radius > 0.0 {
+            gizmos.sphere(isometry, soi.radius, body.metadata.color.with_alpha(0.08));
+        }
+    }
+
     for t in trajectory.0.iter() {
         let points: Vec<Vec3> = t.positions.iter().map(|p| p.end).collect();
 
@@ -44,4 +67,28 @@ pub fn body_gizmo_system(
     for trajectory in t.values.iter() {
         gizmos.linestrip(trajectory.points.clone(), LinearRgba::RED);
     }
+
+    if let (Some(points), Some(primary), Some(secondary)) =
+        (lagrange.points, lagrange.primary, lagrange.secondary)
+    {
+        if let (Ok((_, primary, _, _)), Ok((_, secondary, _, _))) =
+            (query.get(primary), query.get(secondary))
+        {
+            let size = primary.translation.distance(secondary.translation) * 0.01;
+
+            gizmos.line(
+                primary.translation,
+                secondary.translation,
+                LinearRgba::new(0.1, 1.0, 0.1, 0.2),
+            );
+
+            for point in points {
+                gizmos.sphere(
+                    Isometry3d::from_translation(point),
+                    size,
+                    LinearRgba::new(0.1, 1.0, 0.1, 1.0),
+                );
+            }
+        }
+    }
 }
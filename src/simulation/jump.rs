@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use super::{
+    settings::SimulationParameters,
+    time::{tick_duration, JulianDate, SimulationTime},
+    units::DAY,
+};
+
+/// How long a frame may spend integrating towards the target date, so the app stays responsive
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+/// Integrates the system forwards or backwards to a date as fast as it can,
+/// running physics updates back to back for a slice of every frame.
+#[derive(Resource, Debug, Default)]
+pub struct DateJump {
+    target: Option<JulianDate>,
+
+    /// Where the jump started from, set on its first slice
+    start: Option<JulianDate>,
+
+    /// The time step before the jump, we flip its sign to jump backwards and restore it afterwards
+    time_step: Option<f32>,
+
+    cancelled: bool,
+
+    /// From 0 to 1
+    pub progress: f32,
+}
+
+impl DateJump {
+    /// Starts a jump to `target`, or redirects the one in progress there.
+    /// A target that is not a number would never be reached, so it is ignored.
+    pub fn jump_to(&mut self, target: JulianDate) {
+        if !target.value().is_finite() {
+            warn!("Cannot jump to {}", target.value());
+            return;
+        }
+
+        self.target = Some(target);
+        self.start = None;
+        self.cancelled = false;
+        self.progress = 0.0;
+    }
+
+    /// Stops the jump where it is on the next frame.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.target.is_some()
+    }
+
+    pub fn target(&self) -> Option<JulianDate> {
+        self.target
+    }
+}
+
+/// Run condition for systems that only matter to what is on the screen,
+/// like the trajectory previews, which would just slow a jump down.
+pub fn not_jumping(jump: Res<DateJump>) -> bool {
+    !jump.is_active()
+}
+
+fn finish_jump(world: &mut World) {
+    let time_step = world.resource::<DateJump>().time_step;
+
+    if let Some(time_step) = time_step {
+        world.resource_mut::<SimulationParameters>().time_step = time_step;
+    }
+
+    *world.resource_mut::<DateJump>() = DateJump::default();
+}
+
+/// Runs whole physics updates until the clock is as close to the target as a single update gets,
+/// or until the frame budget runs out, in which case it carries on next frame.
+pub fn date_jump_system(world: &mut World) {
+    let (target, cancelled) = {
+        let jump = world.resource::<DateJump>();
+        (jump.target, jump.cancelled)
+    };
+
+    let Some(target) = target else {
+        return;
+    };
+
+    let current = world.resource::<SimulationTime>().current;
+
+    if cancelled {
+        info!("Jump to {} cancelled at {}", target, current);
+        finish_jump(world);
+        return;
+    }
+
+    let time_step = world.resource::<SimulationParameters>().time_step;
+
+    let start = {
+        let mut jump = world.resource_mut::<DateJump>();
+        jump.time_step.get_or_insert(time_step);
+
+        match jump.start {
+            Some(start) => start,
+            None => {
+                info!("Jumping from {} to {}", current, target);
+                *jump.start.insert(current)
+            }
+        }
+    };
+
+    let total = target.days_since(&start).abs();
+    let started = Instant::now();
+    let mut remaining = target.days_since(&current);
+
+    loop {
+        if !remaining.is_finite() {
+            error!("Cannot jump to {} from {}", target, current);
+            finish_jump(world);
+            return;
+        }
+
+        let tick = {
+            let mut parameters = world.resource_mut::<SimulationParameters>();
+            parameters.time_step = parameters.time_step.abs().copysign(remaining as f32);
+
+            tick_duration(&parameters)
+        };
+
+        if tick == 0.0 {
+            error!("Cannot jump to {} with a time step of 0", target);
+            finish_jump(world);
+            return;
+        }
+
+        // Stop on whichever update lands closest to the target
+        if remaining.abs() <= tick.abs() / 2.0 {
+            info!(
+                "Jumped to {}, {:.1} s off the target",
+                world.resource::<SimulationTime>().current,
+                remaining * DAY
+            );
+            finish_jump(world);
+            return;
+        }
+
+        if started.elapsed() >= FRAME_BUDGET {
+            break;
+        }
+
+        world.run_schedule(FixedUpdate);
+
+        remaining = target.days_since(&world.resource::<SimulationTime>().current);
+    }
+
+    world.resource_mut::<DateJump>().progress = if total > 0.0 {
+        (1.0 - remaining.abs() / total).clamp(0.0, 1.0) as f32
+    } else {
+        1.0
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_targets_that_are_not_numbers() {
+        let mut jump = DateJump::default();
+
+        for target in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            jump.jump_to(JulianDate::new(target));
+            assert!(!jump.is_active(), "{}", target);
+        }
+
+        jump.jump_to(JulianDate::new(2460700.5));
+        assert!(jump.is_active());
+    }
+}
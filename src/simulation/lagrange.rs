@@ -0,0 +1,347 @@
+use bevy::{math::DVec3, prelude::*};
+
+use super::{
+    body::{Body, BodyData, BodyMetadata, Spacecraft, TestBody},
+    settings::{SimulationMode, SimulationParameters},
+};
+
+const LAGRANGE_TOLERANCE: f64 = 1e-12;
+const LAGRANGE_MAX_ITERATIONS: usize = 50;
+
+/// The Lagrange points of the selected pair of bodies.
+#[derive(Resource, Default, Debug)]
+pub struct LagrangePoints {
+    /// The pair we compute the points for, the order does not matter
+    pub primary: Option<Entity>,
+    pub secondary: Option<Entity>,
+
+    /// L1 to L5 in world space, `None` if the pair is not selected or does not exist
+    pub points: Option<[Vec3; 5]>,
+
+    /// Velocity of L1 to L5, so that a body placed there co-rotates with the pair
+    pub velocities: [Vec3; 5],
+}
+
+/// Requests a massless test body at one of the Lagrange points, 0 for L1, 4 for L5.
+#[derive(Event, Debug, Clone)]
+pub struct SpawnAtLagrangePointEvent {
+    pub point: usize,
+}
+
+/// Returns the position and velocity of the five Lagrange points of `secondary` orbiting `primary`,
+/// assuming they are on a circular orbit around their barycenter.
+/// Arguments are (position, velocity, mass) tuples.
+pub fn lagrange_points(
+    primary: (Vec3, Vec3, f32),
+    secondary: (Vec3, Vec3, f32),
+) -> Option<([Vec3; 5], [Vec3; 5])> {
+    let (p1, v1, m1) = (primary.0.as_dvec3(), primary.1.as_dvec3(), primary.2 as f64);
+    let (p2, v2, m2) = (
+        secondary.0.as_dvec3(),
+        secondary.1.as_dvec3(),
+        secondary.2 as f64,
+    );
+
+    let total_mass = m1 + m2;
+    let offset = p2 - p1;
+    let distance = offset.length();
+    let angular_momentum = offset.cross(v2 - v1);
+
+    if total_mass <= 0.0 || distance == 0.0 || angular_momentum.length_squared() == 0.0 {
+        return None;
+    }
+
+    let mu = m2 / total_mass;
+    let barycenter = (p1 * m1 + p2 * m2) / total_mass;
+    let barycenter_velocity = (v1 * m1 + v2 * m2) / total_mass;
+
+    // The co-rotating frame: x points to the secondary, y along its motion, z along the orbit normal
+    let x = offset / distance;
+    let z = angular_momentum.normalize();
+    let y = z.cross(x);
+    let angular_velocity = angular_momentum / (distance * distance);
+
+    let hill = (mu / 3.0).cbrt();
+    let collinear = [
+        collinear_point(mu, 1.0 - mu - hill),
+        collinear_point(mu, 1.0 - mu + hill),
+        collinear_point(mu, -1.0 - 5.0 * mu / 12.0),
+    ];
+
+    let half_sqrt_3 = 3.0_f64.sqrt() / 2.0;
+
+    let rotating: [DVec3; 5] = [
+        DVec3::new(collinear[0], 0.0, 0.0),
+        DVec3::new(collinear[1], 0.0, 0.0),
+        DVec3::new(collinear[2], 0.0, 0.0),
+        DVec3::new(0.5 - mu, half_sqrt_3, 0.0),
+        DVec3::new(0.5 - mu, -half_sqrt_3, 0.0),
+    ];
+
+    let mut positions = [Vec3::ZERO; 5];
+    let mut velocities = [Vec3::ZERO; 5];
+
+    for (i, point) in rotating.iter().enumerate() {
+        let relative = (x * point.x + y * point.y + z * point.z) * distance;
+
+        positions[i] = (barycenter + relative).as_vec3();
+        velocities[i] = (barycenter_velocity + angular_velocity.cross(relative)).as_vec3();
+    }
+
+    Some((positions, velocities))
+}
+
+/// Solves for the equilibrium on the line through the pair, in units of their separation,
+/// with the barycenter at the origin, the primary at -mu and the secondary at 1 - mu.
+fn collinear_point(mu: f64, guess: f64) -> f64 {
+    let force = |x: f64| {
+        let r1 = x + mu;
+        let r2 = x - 1.0 + mu;
+
+        x - (1.0 - mu) * r1 / r1.abs().powi(3) - mu * r2 / r2.abs().powi(3)
+    };
+
+    let derivative = |x: f64| {
+        let r1 = (x + mu).abs();
+        let r2 = (x - 1.0 + mu).abs();
+
+        1.0 + 2.0 * (1.0 - mu) / r1.powi(3) + 2.0 * mu / r2.powi(3)
+    };
+
+    let mut x = guess;
+
+    for _ in 0..LAGRANGE_MAX_ITERATIONS {
+        let step = force(x) / derivative(x);
+        x -= step;
+
+        if step.abs() < LAGRANGE_TOLERANCE {
+            break;
+        }
+    }
+
+    x
+}
+
+pub fn lagrange_points_system(
+    query: Query<(&Transform, &Body)>,
+    mut lagrange: ResMut<LagrangePoints>,
+) {
+    let (Some(primary), Some(secondary)) = (lagrange.primary, lagrange.secondary) else {
+        lagrange.points = None;
+        return;
+    };
+
+    let (Ok((primary_transform, primary)), Ok((secondary_transform, secondary))) =
+        (query.get(primary), query.get(secondary))
+    else {
+        lagrange.points = None;
+        return;
+    };
+
+    let mut pair = [
+        (
+            primary_transform.translation,
+            primary.data.velocity,
+            primary.data.mass,
+        ),
+        (
+            secondary_transform.translation,
+            secondary.data.velocity,
+            secondary.data.mass,
+        ),
+    ];
+
+    // The more massive body is the one being orbited
+    if pair[1].2 > pair[0].2 {
+        pair.swap(0, 1);
+    }
+
+    match lagrange_points(pair[0], pair[1]) {
+        Some((points, velocities)) => {
+            lagrange.points = Some(points);
+            lagrange.velocities = velocities;
+        }
+        None => lagrange.points = None,
+    }
+}
+
+pub fn spawn_at_lagrange_point_system(
+    mut commands: Commands,
+    mut events: EventReader<SpawnAtLagrangePointEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    lagrange: Res<LagrangePoints>,
+    parameters: Res<SimulationParameters>,
+) {
+    for event in events.read() {
+        let Some(points) = lagrange.points else {
+            continue;
+        };
+
+        let Some(position) = points.get(event.point).copied() else {
+            error!("There is no Lagrange point L{}", event.point + 1);
+            continue;
+        };
+
+        let name = format!("L{} test body", event.point + 1);
+
+        info!("Spawning {} at {}", name, position);
+
+        let mut entity = commands.spawn((
+            Body {
+                data: BodyData {
+                    position,
+                    velocity: lagrange.velocities[event.point],
+                    radius: 1e-6,
+                    mass: 0.0,
+                    ..default()
+                },
+                metadata: BodyMetadata {
+                    name: Some(name),
+                    color: Color::linear_rgb(0.1, 1.0, 0.1),
+                    id: None,
+                    ..default()
+                },
+                satellites: None,
+            },
+            Mesh3d(meshes.add(Sphere { radius: 1e-6 })),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::linear_rgb(0.1, 1.0, 0.1),
+                emissive: LinearRgba::rgb(0.0, 100.0, 0.0),
+                ..default()
+            })),
+            Transform::from_translation(position),
+            TestBody {},
+        ));
+
+        // Moved along a conic around its primary like a spacecraft
+        if parameters.mode == SimulationMode::PatchedConics {
+            entity.insert(Spacecraft {});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::simulation::{
+        body::Planet,
+        conics::{
+            hill_sphere_system, sphere_of_influence_system, HillSphere, HillSphereEntryEvent,
+            SoiTransitionEvent, SphereOfInfluence,
+        },
+    };
+
+    #[test]
+    fn finds_the_earth_moon_points() {
+        // In units of the separation and of the orbital period over 2π, with the barycenter at the origin
+        let mu = 0.012_150_585;
+        let primary = (Vec3::new(-mu, 0.0, 0.0), Vec3::new(0.0, -mu, 0.0), 1.0 - mu);
+        let secondary = (
+            Vec3::new(1.0 - mu, 0.0, 0.0),
+            Vec3::new(0.0, 1.0 - mu, 0.0),
+            mu,
+        );
+
+        let (positions, velocities) = lagrange_points(primary, secondary).unwrap();
+
+        // The collinear points of the circular restricted three-body problem for this mass ratio
+        let expected = [
+            Vec3::new(0.836_915, 0.0, 0.0),
+            Vec3::new(1.155_682, 0.0, 0.0),
+            Vec3::new(-1.005_063, 0.0, 0.0),
+            Vec3::new(0.5 - mu, 3.0_f32.sqrt() / 2.0, 0.0),
+            Vec3::new(0.5 - mu, -3.0_f32.sqrt() / 2.0, 0.0),
+        ];
+
+        for (i, (position, expected)) in positions.iter().zip(expected).enumerate() {
+            assert!(
+                position.distance(expected) < 1e-5,
+                "L{} at {} instead of {}",
+                i + 1,
+                position,
+                expected
+            );
+
+            // Co-rotating at one radian per unit of time
+            assert!(velocities[i].distance(Vec3::Z.cross(*position)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_bodies_stay_out_of_the_system() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Assets<StandardMaterial>>();
+        world.init_resource::<Events<SpawnAtLagrangePointEvent>>();
+        world.init_resource::<Events<SoiTransitionEvent>>();
+        world.init_resource::<Events<HillSphereEntryEvent>>();
+        world.insert_resource(SimulationParameters {
+            mode: SimulationMode::PatchedConics,
+            ..default()
+        });
+        world.insert_resource(LagrangePoints {
+            points: Some([Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z]),
+            ..default()
+        });
+
+        let sun = world
+            .spawn((
+                Body {
+                    data: BodyData {
+                        mass: 1.0,
+                        ..default()
+                    },
+                    metadata: default(),
+                    satellites: None,
+                },
+                Planet {},
+            ))
+            .id();
+
+        world.send_event(SpawnAtLagrangePointEvent { point: 0 });
+        world
+            .run_system_once(spawn_at_lagrange_point_system)
+            .unwrap();
+        world.run_system_once(sphere_of_influence_system).unwrap();
+        world.run_system_once(hill_sphere_system).unwrap();
+
+        let mut query = world.query_filtered::<Entity, With<TestBody>>();
+        let test_body = query.single(&world);
+
+        assert!(world.get::<Body>(test_body).unwrap().metadata.id.is_none());
+        assert!(world.get::<Planet>(test_body).is_none());
+
+        // It goes around the Sun, but the Sun does not go around it
+        assert_eq!(
+            world.get::<SphereOfInfluence>(test_body).unwrap().primary,
+            Some(sun)
+        );
+        assert!(world
+            .get::<SphereOfInfluence>(sun)
+            .unwrap()
+            .primary
+            .is_none());
+        assert!(world.resource::<Events<SoiTransitionEvent>>().is_empty());
+
+        assert!(world.get::<HillSphere>(test_body).unwrap().inside.is_none());
+        assert!(world
+            .get::<HillSphere>(sun)
+            .unwrap()
+            .inside
+            .as_ref()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn needs_an_orbit() {
+        let primary = (Vec3::ZERO, Vec3::ZERO, 1.0);
+
+        assert!(lagrange_points(primary, (Vec3::X, Vec3::X, 0.1)).is_none());
+        assert!(lagrange_points(primary, (Vec3::ZERO, Vec3::Y, 0.1)).is_none());
+        assert!(lagrange_points((Vec3::ZERO, Vec3::ZERO, 0.0), (Vec3::X, Vec3::Y, 0.0)).is_none());
+    }
+}
@@ -1,38 +1,133 @@
 use bevy::prelude::*;
 
+pub mod approach;
 pub mod body;
+pub mod calendar;
+pub mod conics;
 pub mod data;
+pub mod eclipse;
+pub mod elements;
+pub mod export;
+pub mod force;
+pub mod frame;
 mod gizmo;
+pub mod jump;
+pub mod lagrange;
+pub mod orbit;
+pub mod passage;
 pub mod physics;
 pub mod player;
+pub mod recording;
+pub mod scenario;
 pub mod settings;
 mod setup;
+pub mod snapshot;
+pub mod time;
 pub mod trajectory;
+pub mod units;
 mod util;
 
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreStartup, settings::params_override_system)
+        app.add_systems(PreStartup, crate::cli::cli_system)
             .add_systems(
                 Startup,
-                (setup::initialize_bodies_system, setup::spawn_player_system),
+                (
+                    setup::initialize_bodies_system,
+                    setup::spawn_player_system,
+                    frame::barycentric_correction_system.after(setup::initialize_bodies_system),
+                    crate::spk::load_rails_system,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    recording::playback_system,
+                    body::follow_body_system.after(recording::playback_system),
+                    frame::frame_center_system.after(recording::playback_system),
+                    gizmo::body_gizmo_system.after(frame::frame_center_system),
+                    conics::soi_transition_log_system,
+                    conics::hill_sphere_log_system,
+                    eclipse::eclipse_log_system,
+                    passage::passage_log_system,
+                    lagrange::spawn_at_lagrange_point_system,
+                    jump::date_jump_system.run_if(recording::not_playing_back),
+                    scenario::switch_scenario_system,
+                    snapshot::quick_save_system,
+                    snapshot::snapshot_system.after(snapshot::quick_save_system),
+                ),
             )
-            .add_systems(Update, (body::follow_body_system, gizmo::body_gizmo_system))
             .add_systems(
                 FixedUpdate,
                 (
-                    physics::gravity_system,
-                    trajectory::precalculate_trajectory_system,
-                    trajectory::live_trajectory_projection_system,
-                    settings::elapsed_time_update_system,
+                    physics::gravity_system.run_if(recording::not_playing_back),
+                    trajectory::precalculate_trajectory_system.run_if(jump::not_jumping),
+                    trajectory::live_trajectory_projection_system.run_if(jump::not_jumping),
+                    crate::spk::rails_system
+                        .after(conics::patched_conics_system)
+                        .run_if(recording::not_playing_back),
+                    time::simulation_time_system
+                        .after(crate::spk::rails_system)
+                        .run_if(recording::not_playing_back),
+                    conics::sphere_of_influence_system
+                        .after(physics::gravity_system)
+                        .run_if(recording::not_playing_back),
+                    conics::patched_conics_system
+                        .after(conics::sphere_of_influence_system)
+                        .run_if(recording::not_playing_back),
+                    recording::record_system
+                        .after(time::simulation_time_system)
+                        .run_if(recording::not_playing_back),
+                    elements::osculating_elements_system.after(crate::spk::rails_system),
+                    conics::hill_sphere_system.after(elements::osculating_elements_system),
+                    eclipse::eclipse_detection_system
+                        .after(time::simulation_time_system)
+                        .run_if(recording::not_playing_back),
+                    calendar::calendar_system
+                        .after(time::simulation_time_system)
+                        .run_if(recording::not_playing_back),
+                    approach::close_approach_system
+                        .after(time::simulation_time_system)
+                        .run_if(recording::not_playing_back),
+                    passage::passage_system
+                        .after(time::simulation_time_system)
+                        .run_if(recording::not_playing_back),
+                    lagrange::lagrange_points_system.after(crate::spk::rails_system),
+                    export::export_system
+                        .after(time::simulation_time_system)
+                        .after(elements::osculating_elements_system)
+                        .run_if(recording::not_playing_back),
                 ),
             )
+            .add_event::<conics::SoiTransitionEvent>()
+            .add_event::<conics::HillSphereEntryEvent>()
+            .add_event::<eclipse::EclipseEvent>()
+            .add_event::<passage::PassageEvent>()
+            .add_event::<lagrange::SpawnAtLagrangePointEvent>()
+            .init_resource::<crate::cli::Cli>()
             .insert_resource(settings::SimulationParameters::default())
+            .insert_resource(scenario::Scenario::default())
+            .insert_resource(scenario::PendingScenario::default())
+            .insert_resource(snapshot::SnapshotRequests::default())
+            .insert_resource(recording::Recorder::default())
+            .insert_resource(recording::Playback::default())
+            .insert_resource(export::Exporter::default())
             .insert_resource(settings::FollowBody::default())
             .insert_resource(settings::SelectedBody::default())
-            .insert_resource(settings::ElapsedTime::default())
+            .insert_resource(time::SimulationTime::default())
+            .insert_resource(jump::DateJump::default())
+            .insert_resource(crate::spk::KernelSeed::default())
+            .insert_resource(crate::spk::Rails::default())
+            .insert_resource(crate::mpcorb::MinorPlanetImport::default())
+            .insert_resource(frame::ReferenceFrame::default())
+            .insert_resource(units::DisplayUnits::default())
+            .insert_resource(lagrange::LagrangePoints::default())
+            .insert_resource(conics::SphereOverlays::default())
+            .insert_resource(eclipse::Eclipses::default())
+            .insert_resource(calendar::Timeline::default())
+            .insert_resource(approach::CloseApproaches::default())
             .insert_resource(trajectory::Trajectories::default())
             .insert_resource(trajectory::CalculateTrajectory::default())
             .insert_resource(trajectory::LiveTrajectoryPreview::default())
@@ -0,0 +1,561 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::math::{DQuat, DVec3, Vec3};
+
+/// Convergence threshold for the universal anomaly Newton iteration.
+const KEPLER_TOLERANCE: f64 = 1e-12;
+const KEPLER_MAX_ITERATIONS: usize = 50;
+
+/// Stumpff function C(z).
+fn stumpff_c(z: f64) -> f64 {
+    if z > 1e-6 {
+        (1.0 - z.sqrt().cos()) / z
+    } else if z < -1e-6 {
+        ((-z).sqrt().cosh() - 1.0) / -z
+    } else {
+        1.0 / 2.0 - z / 24.0 + z * z / 720.0
+    }
+}
+
+/// Stumpff function S(z).
+fn stumpff_s(z: f64) -> f64 {
+    if z > 1e-6 {
+        let sz = z.sqrt();
+        (sz - sz.sin()) / (sz * sz * sz)
+    } else if z < -1e-6 {
+        let sz = (-z).sqrt();
+        (sz.sinh() - sz) / (sz * sz * sz)
+    } else {
+        1.0 / 6.0 - z / 120.0 + z * z / 5040.0
+    }
+}
+
+/// Propagates a two-body state vector by `dt` along its conic, using universal variables,
+/// so elliptic, parabolic and hyperbolic orbits are all handled the same way.\
+/// `position` and `velocity` are relative to the primary, `mu` is G * (M + m).
+pub fn propagate_kepler(position: DVec3, velocity: DVec3, mu: f64, dt: f64) -> (DVec3, DVec3) {
+    let r0 = position.length();
+
+    if r0 == 0.0 || mu <= 0.0 || dt == 0.0 {
+        return (position + velocity * dt, velocity);
+    }
+
+    let v0 = velocity.length();
+    let sqrt_mu = mu.sqrt();
+    let vr0 = position.dot(velocity) / r0;
+
+    // Reciprocal of the semi-major axis; negative for hyperbolic orbits
+    let alpha = 2.0 / r0 - v0 * v0 / mu;
+
+    // Initial guess for the universal anomaly
+    let mut chi = if alpha.abs() > 1e-12 {
+        sqrt_mu * alpha.abs() * dt
+    } else {
+        sqrt_mu * dt / r0
+    };
+
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let z = alpha * chi * chi;
+        let c = stumpff_c(z);
+        let s = stumpff_s(z);
+
+        let f = r0 * vr0 / sqrt_mu * chi * chi * c
+            + (1.0 - alpha * r0) * chi * chi * chi * s
+            + r0 * chi
+            - sqrt_mu * dt;
+        let df = r0 * vr0 / sqrt_mu * chi * (1.0 - z * s) + (1.0 - alpha * r0) * chi * chi * c + r0;
+
+        let delta = f / df;
+        chi -= delta;
+
+        if delta.abs() < KEPLER_TOLERANCE {
+            break;
+        }
+    }
+
+    let z = alpha * chi * chi;
+    let c = stumpff_c(z);
+    let s = stumpff_s(z);
+
+    // Lagrange coefficients
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = dt - chi * chi * chi / sqrt_mu * s;
+
+    let new_position = f * position + g * velocity;
+    let r = new_position.length();
+
+    let df = sqrt_mu / (r * r0) * (alpha * chi * chi * chi * s - chi);
+    let dg = 1.0 - chi * chi / r * c;
+
+    let new_velocity = df * position + dg * velocity;
+
+    (new_position, new_velocity)
+}
+
+/// Radius of the Laplace sphere of influence of a body with `mass`,
+/// orbiting a primary with `primary_mass` at `distance`.
+pub fn sphere_of_influence_radius(distance: f32, mass: f32, primary_mass: f32) -> f32 {
+    if primary_mass <= 0.0 {
+        return 0.0;
+    }
+
+    distance * (mass / primary_mass).powf(2.0 / 5.0)
+}
+
+/// Radius of the Hill sphere of a body with `mass`, orbiting a primary with `primary_mass`,
+/// at its periapsis, where it is the smallest.
+pub fn hill_sphere_radius(
+    semi_major_axis: f32,
+    eccentricity: f32,
+    mass: f32,
+    primary_mass: f32,
+) -> f32 {
+    if primary_mass <= 0.0 {
+        return 0.0;
+    }
+
+    semi_major_axis * (1.0 - eccentricity) * (mass / (3.0 * primary_mass)).cbrt()
+}
+
+/// Our JSON data has "y" and "z" flipped compared to world space (see `deserialize_vec3`),
+/// orbital elements are defined relative to the ecliptic, so we need to flip them back.
+pub fn world_to_ecliptic(v: Vec3) -> DVec3 {
+    DVec3::new(v.x as f64, v.z as f64, v.y as f64)
+}
+
+pub fn ecliptic_to_world(v: DVec3) -> Vec3 {
+    Vec3::new(v.x as f32, v.z as f32, v.y as f32)
+}
+
+/// Classical orbital elements. Angles are in radians.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeplerianElements {
+    /// Negative for hyperbolic orbits
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub true_anomaly: f64,
+    pub mean_anomaly: f64,
+
+    /// Infinite for unbound orbits
+    pub period: f64,
+}
+
+const ELEMENTS_EPSILON: f64 = 1e-10;
+
+/// Derives the osculating elements from a state vector relative to the primary, in the ecliptic frame.
+/// `mu` is G * (M + m).
+pub fn state_to_elements(position: DVec3, velocity: DVec3, mu: f64) -> KeplerianElements {
+    let r = position.length();
+    let v2 = velocity.length_squared();
+
+    if r == 0.0 || mu <= 0.0 {
+        return KeplerianElements::default();
+    }
+
+    let h = position.cross(velocity);
+    let node = DVec3::new(-h.y, h.x, 0.0);
+    let e_vec = ((v2 - mu / r) * position - position.dot(velocity) * velocity) / mu;
+    let eccentricity = e_vec.length();
+
+    let energy = v2 / 2.0 - mu / r;
+    let semi_major_axis = if energy.abs() > ELEMENTS_EPSILON {
+        -mu / (2.0 * energy)
+    } else {
+        f64::INFINITY
+    };
+
+    let inclination = (h.z / h.length()).clamp(-1.0, 1.0).acos();
+
+    let is_equatorial = node.length() < ELEMENTS_EPSILON;
+    let is_circular = eccentricity < ELEMENTS_EPSILON;
+
+    let longitude_of_ascending_node = if is_equatorial {
+        0.0
+    } else {
+        node.y.atan2(node.x).rem_euclid(TAU)
+    };
+
+    // For degenerate orbits the undefined angles are folded into the ones that remain defined
+    let argument_of_periapsis = match (is_equatorial, is_circular) {
+        (_, true) => 0.0,
+        (true, false) => {
+            let longitude_of_periapsis = e_vec.y.atan2(e_vec.x);
+            if h.z < 0.0 {
+                (-longitude_of_periapsis).rem_euclid(TAU)
+            } else {
+                longitude_of_periapsis.rem_euclid(TAU)
+            }
+        }
+        (false, false) => angle_between(node, e_vec, h),
+    };
+
+    let true_anomaly = match (is_equatorial, is_circular) {
+        (false, true) => angle_between(node, position, h),
+        (true, true) => {
+            let true_longitude = position.y.atan2(position.x);
+            if h.z < 0.0 {
+                (-true_longitude).rem_euclid(TAU)
+            } else {
+                true_longitude.rem_euclid(TAU)
+            }
+        }
+        (_, false) => angle_between(e_vec, position, h),
+    };
+
+    let mean_anomaly = true_to_mean_anomaly(true_anomaly, eccentricity);
+
+    let period = if semi_major_axis > 0.0 && semi_major_axis.is_finite() {
+        TAU * (semi_major_axis.powi(3) / mu).sqrt()
+    } else {
+        f64::INFINITY
+    };
+
+    KeplerianElements {
+        semi_major_axis,
+        eccentricity,
+        inclination,
+        longitude_of_ascending_node,
+        argument_of_periapsis,
+        true_anomaly,
+        mean_anomaly,
+        period,
+    }
+}
+
+/// Angle from `from` to `to`, measured counter-clockwise around `normal`, in [0, 2π).
+fn angle_between(from: DVec3, to: DVec3, normal: DVec3) -> f64 {
+    let sin = from.cross(to).dot(normal.normalize());
+    let cos = from.dot(to);
+
+    sin.atan2(cos).rem_euclid(TAU)
+}
+
+pub fn true_to_mean_anomaly(true_anomaly: f64, eccentricity: f64) -> f64 {
+    let half = true_anomaly / 2.0;
+
+    if eccentricity < 1.0 {
+        let eccentric_anomaly = 2.0
+            * ((1.0 - eccentricity).sqrt() * half.sin())
+                .atan2((1.0 + eccentricity).sqrt() * half.cos());
+
+        (eccentric_anomaly - eccentricity * eccentric_anomaly.sin()).rem_euclid(TAU)
+    } else if eccentricity > 1.0 {
+        let hyperbolic_anomaly =
+            2.0 * (((eccentricity - 1.0) / (eccentricity + 1.0)).sqrt() * half.tan()).atanh();
+
+        eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+    } else {
+        let d = half.tan();
+
+        d + d * d * d / 3.0
+    }
+}
+
+/// Inverse of `true_to_mean_anomaly`, solving Kepler's equation with Newton's method.
+pub fn mean_to_true_anomaly(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    if eccentricity < 1.0 {
+        let mean_anomaly = mean_anomaly.rem_euclid(TAU);
+        let mut eccentric_anomaly = if eccentricity > 0.8 { PI } else { mean_anomaly };
+
+        for _ in 0..KEPLER_MAX_ITERATIONS {
+            let delta = (eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly)
+                / (1.0 - eccentricity * eccentric_anomaly.cos());
+            eccentric_anomaly -= delta;
+
+            if delta.abs() < KEPLER_TOLERANCE {
+                break;
+            }
+        }
+
+        let half = eccentric_anomaly / 2.0;
+
+        (2.0 * ((1.0 + eccentricity).sqrt() * half.sin())
+            .atan2((1.0 - eccentricity).sqrt() * half.cos()))
+        .rem_euclid(TAU)
+    } else if eccentricity > 1.0 {
+        let mut hyperbolic_anomaly = (mean_anomaly / eccentricity).asinh();
+
+        for _ in 0..KEPLER_MAX_ITERATIONS {
+            let delta =
+                (eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly - mean_anomaly)
+                    / (eccentricity * hyperbolic_anomaly.cosh() - 1.0);
+            hyperbolic_anomaly -= delta;
+
+            if delta.abs() < KEPLER_TOLERANCE {
+                break;
+            }
+        }
+
+        2.0 * (((eccentricity + 1.0) / (eccentricity - 1.0)).sqrt()
+            * (hyperbolic_anomaly / 2.0).tanh())
+        .atan()
+    } else {
+        // Barker's equation
+        let w = 1.5 * mean_anomaly;
+        let y = (w + (w * w + 1.0).sqrt()).cbrt();
+
+        2.0 * (y - 1.0 / y).atan()
+    }
+}
+
+/// Converts orbital elements to a state vector relative to the primary, in the ecliptic frame.
+/// Only `eccentricity`, `inclination`, `longitude_of_ascending_node`, `argument_of_periapsis`
+/// and `true_anomaly` are used, the size of the orbit is given by `periapsis_distance`,
+/// so parabolic orbits can be described as well. `mu` is G * (M + m).
+pub fn elements_to_state(
+    elements: &KeplerianElements,
+    periapsis_distance: f64,
+    mu: f64,
+) -> (DVec3, DVec3) {
+    let e = elements.eccentricity;
+    let nu = elements.true_anomaly;
+
+    // Semi-latus rectum
+    let p = periapsis_distance * (1.0 + e);
+
+    let r = p / (1.0 + e * nu.cos());
+    let position = DVec3::new(r * nu.cos(), r * nu.sin(), 0.0);
+    let velocity = (mu / p).sqrt() * DVec3::new(-nu.sin(), e + nu.cos(), 0.0);
+
+    // Perifocal to ecliptic frame
+    let rotation = DQuat::from_rotation_z(elements.longitude_of_ascending_node)
+        * DQuat::from_rotation_x(elements.inclination)
+        * DQuat::from_rotation_z(elements.argument_of_periapsis);
+
+    (rotation * position, rotation * velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// G * M of the Sun, AU^3/day^2
+    const MU: f64 = 2.959_122e-4;
+
+    fn assert_angle(actual: f64, expected: f64, what: &str) {
+        let difference = (actual - expected + PI).rem_euclid(TAU) - PI;
+        assert!(
+            difference.abs() < 1e-9,
+            "{}: {} instead of {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn matches_horizons_elements() {
+        // The Earth relative to the Sun on 2025-01-01, from data/responses/Earth – 399.txt
+        let position = DVec3::new(
+            -1.786_834_409_731_047e-1,
+            9.669_827_953_774_551e-1,
+            -5.109_423_915_082_682e-5,
+        );
+        let velocity = DVec3::new(
+            -1.720_473_858_166_942e-2,
+            -3.193_533_189_307_208e-3,
+            5.457_174_067_040_888e-9,
+        );
+
+        // The Keplerian GM Horizons used, the Sun plus the Earth and the Moon
+        let elements = state_to_elements(position, velocity, 2.959_130_970_533_642e-4);
+
+        assert!((elements.eccentricity - 1.756_190_256_786_036e-2).abs() < 1e-9);
+        assert!((elements.semi_major_axis - 1.000_912_549_427_924).abs() < 1e-9);
+        assert!((elements.period - 3.657_564_355_602_954e2).abs() < 1e-6);
+
+        let angles = [
+            (
+                elements.inclination,
+                2.977_082_685_807_642e-3,
+                "inclination",
+            ),
+            (
+                elements.longitude_of_ascending_node,
+                1.901_716_375_775_361e2,
+                "node",
+            ),
+            (
+                elements.argument_of_periapsis,
+                2.729_783_142_442_708e2,
+                "argument of periapsis",
+            ),
+            (
+                elements.mean_anomaly,
+                3.574_122_246_804_211e2,
+                "mean anomaly",
+            ),
+            (
+                elements.true_anomaly,
+                3.573_193_277_152_79e2,
+                "true anomaly",
+            ),
+        ];
+
+        for (actual, degrees, what) in angles {
+            assert_angle(actual, f64::to_radians(degrees), what);
+        }
+    }
+
+    #[test]
+    fn mean_anomaly_round_trips() {
+        for eccentricity in [0.0_f64, 0.3, 0.9, 0.99, 1.0, 1.5, 3.0] {
+            // Unbound orbits only reach up to the asymptote at acos(-1 / e)
+            let limit = if eccentricity < 1.0 {
+                PI
+            } else {
+                (-1.0 / eccentricity).acos() - 0.1
+            };
+
+            for fraction in [-0.9, -0.5, -0.1, 0.0, 0.1, 0.5, 0.9] {
+                let true_anomaly = fraction * limit;
+                let mean_anomaly = true_to_mean_anomaly(true_anomaly, eccentricity);
+
+                assert_angle(
+                    mean_to_true_anomaly(mean_anomaly, eccentricity),
+                    true_anomaly,
+                    &format!("e = {}, ν = {}", eccentricity, true_anomaly),
+                );
+            }
+        }
+
+        // Kepler's equation by hand: E = π / 2 gives M = π / 2 - e
+        let eccentricity: f64 = 0.5;
+        let true_anomaly = 2.0
+            * ((1.0 + eccentricity).sqrt() * (PI / 4.0).sin())
+                .atan2((1.0 - eccentricity).sqrt() * (PI / 4.0).cos());
+        assert_angle(
+            mean_to_true_anomaly(PI / 2.0 - eccentricity, eccentricity),
+            true_anomaly,
+            "E = π / 2",
+        );
+    }
+
+    #[test]
+    fn elements_round_trip() {
+        for (eccentricity, periapsis_distance) in [(0.2, 1.0), (0.7, 0.5), (1.0, 0.3), (2.0, 0.8)] {
+            let elements = KeplerianElements {
+                eccentricity,
+                inclination: 0.4,
+                longitude_of_ascending_node: 1.2,
+                argument_of_periapsis: 2.5,
+                true_anomaly: 0.8,
+                ..Default::default()
+            };
+
+            let (position, velocity) = elements_to_state(&elements, periapsis_distance, MU);
+            let derived = state_to_elements(position, velocity, MU);
+            let what = format!("e = {}", eccentricity);
+
+            assert!(
+                (derived.eccentricity - eccentricity).abs() < 1e-9,
+                "{}: e = {}",
+                what,
+                derived.eccentricity
+            );
+            assert_angle(derived.inclination, 0.4, &what);
+            assert_angle(derived.longitude_of_ascending_node, 1.2, &what);
+            assert_angle(derived.argument_of_periapsis, 2.5, &what);
+            assert_angle(derived.true_anomaly, 0.8, &what);
+
+            // A parabola rounds to either side of e = 1, which changes what the mean anomaly means
+            if eccentricity != 1.0 {
+                assert_angle(
+                    derived.mean_anomaly,
+                    true_to_mean_anomaly(0.8, eccentricity),
+                    &what,
+                );
+            }
+
+            if eccentricity < 1.0 {
+                let semi_major_axis = periapsis_distance / (1.0 - eccentricity);
+                assert!((derived.semi_major_axis - semi_major_axis).abs() < 1e-9);
+                assert!(
+                    (derived.period - TAU * (semi_major_axis.powi(3) / MU).sqrt()).abs() < 1e-6
+                );
+            } else {
+                assert!(derived.period.is_infinite());
+            }
+        }
+
+        // Circular and equatorial, the undefined angles end up in the true anomaly
+        let (position, velocity) = elements_to_state(
+            &KeplerianElements {
+                true_anomaly: 1.0,
+                ..Default::default()
+            },
+            1.0,
+            MU,
+        );
+        let derived = state_to_elements(position, velocity, MU);
+        assert!(derived.eccentricity < 1e-9);
+        assert_angle(derived.true_anomaly, 1.0, "circular");
+    }
+
+    #[test]
+    fn kepler_propagation_follows_the_conic() {
+        for (eccentricity, periapsis_distance) in [(0.0, 1.0), (0.6, 0.4), (1.0, 0.5), (1.8, 1.0)] {
+            let elements = KeplerianElements {
+                eccentricity,
+                inclination: 0.3,
+                longitude_of_ascending_node: 0.7,
+                argument_of_periapsis: 1.9,
+                true_anomaly: -0.5,
+                ..Default::default()
+            };
+
+            let (position, velocity) = elements_to_state(&elements, periapsis_distance, MU);
+
+            // Where the mean anomaly says we should be, 100 days later
+            let dt = 100.0;
+            let (new_position, new_velocity) = propagate_kepler(position, velocity, MU, dt);
+            let mean_motion = if eccentricity == 1.0 {
+                (MU / (2.0 * periapsis_distance.powi(3))).sqrt()
+            } else {
+                (MU / (periapsis_distance / (1.0 - eccentricity)).abs().powi(3)).sqrt()
+            };
+            let mean_anomaly =
+                true_to_mean_anomaly(elements.true_anomaly, eccentricity) + mean_motion * dt;
+
+            let (expected_position, expected_velocity) = elements_to_state(
+                &KeplerianElements {
+                    true_anomaly: mean_to_true_anomaly(mean_anomaly, eccentricity),
+                    ..elements
+                },
+                periapsis_distance,
+                MU,
+            );
+
+            assert!(
+                (new_position - expected_position).length() < 1e-9,
+                "e = {}: {} instead of {}",
+                eccentricity,
+                new_position,
+                expected_position
+            );
+            assert!((new_velocity - expected_velocity).length() < 1e-11);
+
+            // And back again
+            let (back_position, back_velocity) =
+                propagate_kepler(new_position, new_velocity, MU, -dt);
+            assert!((back_position - position).length() < 1e-9);
+            assert!((back_velocity - velocity).length() < 1e-11);
+        }
+
+        // A whole period brings a bound orbit back to where it started
+        let (position, velocity) = elements_to_state(
+            &KeplerianElements {
+                eccentricity: 0.5,
+                ..Default::default()
+            },
+            0.5,
+            MU,
+        );
+        let period = state_to_elements(position, velocity, MU).period;
+        let (new_position, _) = propagate_kepler(position, velocity, MU, period);
+        assert!((new_position - position).length() < 1e-9);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{
+    body::Body, conics::SphereOfInfluence, orbit::world_to_ecliptic, time::SimulationTime,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassageKind {
+    Periapsis,
+    Apoapsis,
+    /// Crossing the ecliptic northwards
+    AscendingNode,
+    /// Crossing the ecliptic southwards
+    DescendingNode,
+}
+
+impl fmt::Display for PassageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassageKind::Periapsis => write!(f, "Periapsis"),
+            PassageKind::Apoapsis => write!(f, "Apoapsis"),
+            PassageKind::AscendingNode => write!(f, "Ascending node"),
+            PassageKind::DescendingNode => write!(f, "Descending node"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Passage {
+    pub kind: PassageKind,
+    pub primary: Entity,
+
+    /// Julian Date
+    pub time: f64,
+
+    /// Distance from the primary, AU
+    pub distance: f32,
+}
+
+/// Emitted when a body passes an apsis or a node of its orbit around its primary.
+#[derive(Event, Debug, Clone)]
+pub struct PassageEvent {
+    pub entity: Entity,
+    pub passage: Passage,
+}
+
+/// Relative state to the primary on the previous physics step.
+#[derive(Debug, Clone, Copy)]
+struct PassageState {
+    primary: Entity,
+    time: f64,
+
+    /// Radial velocity, AU^2 / day, positive when moving away from the primary
+    range_rate: f32,
+
+    /// Height above the ecliptic, AU
+    height: f64,
+
+    distance: f32,
+}
+
+/// Apsis and node passages of a body, relative to whatever was its primary at the time.
+#[derive(Debug, Component, Clone, Default)]
+pub struct PassageHistory {
+    pub passages: Vec<Passage>,
+    previous: Option<PassageState>,
+}
+
+impl PassageHistory {
+    /// Average time between consecutive passages of `kind` around the current primary, in days.
+    /// Periapsis to periapsis gives the anomalistic period, node to node the draconic period.
+    pub fn measured_period(&self, kind: PassageKind) -> Option<f64> {
+        let primary = self.previous?.primary;
+
+        let times: Vec<f64> = self
+            .passages
+            .iter()
+            .filter(|p| p.kind == kind && p.primary == primary)
+            .map(|p| p.time)
+            .collect();
+
+        if times.len() < 2 {
+            return None;
+        }
+
+        Some((times[times.len() - 1] - times[0]) / (times.len() - 1) as f64)
+    }
+}
+
+/// Interpolates the time at which `from` turned into `to`, crossing zero.
+fn crossing_time(from: (f64, f64), to: (f64, f64)) -> f64 {
+    if to.1 == from.1 {
+        return to.0;
+    }
+
+    let fraction = (from.1 / (from.1 - to.1)).clamp(0.0, 1.0);
+
+    from.0 + (to.0 - from.0) * fraction
+}
+
+pub fn passage_system(
+    mut query: Query<(
+        &Transform,
+        &Body,
+        &SphereOfInfluence,
+        &mut PassageHistory,
+        Entity,
+    )>,
+    mut events: EventWriter<PassageEvent>,
+    time: Res<SimulationTime>,
+) {
+    let time = time.julian_date();
+
+    let states: HashMap<Entity, (Vec3, Vec3)> = query
+        .iter()
+        .map(|(t, b, _, _, e)| (e, (t.translation, b.data.velocity)))
+        .collect();
+
+    for (transform, body, soi, mut history, entity) in query.iter_mut() {
+        let Some((primary, (primary_position, primary_velocity))) = soi
+            .primary
+            .and_then(|p| states.get(&p).map(|state| (p, *state)))
+        else {
+            history.previous = None;
+            continue;
+        };
+
+        let position = transform.translation - primary_position;
+        let velocity = body.data.velocity - primary_velocity;
+
+        let state = PassageState {
+            primary,
+            time,
+            range_rate: position.dot(velocity),
+            height: world_to_ecliptic(position).z,
+            distance: position.length(),
+        };
+
+        let previous = history.previous.replace(state);
+
+        // A new primary means a new orbit, nothing to compare against
+        let Some(previous) = previous.filter(|p| p.primary == primary) else {
+            continue;
+        };
+
+        let mut passages: Vec<Passage> = Vec::new();
+
+        if previous.range_rate < 0.0 && state.range_rate >= 0.0 {
+            passages.push(Passage {
+                kind: PassageKind::Periapsis,
+                primary,
+                time: crossing_time(
+                    (previous.time, previous.range_rate as f64),
+                    (time, state.range_rate as f64),
+                ),
+                distance: previous.distance.min(state.distance),
+            });
+        } else if previous.range_rate > 0.0 && state.range_rate <= 0.0 {
+            passages.push(Passage {
+                kind: PassageKind::Apoapsis,
+                primary,
+                time: crossing_time(
+                    (previous.time, previous.range_rate as f64),
+                    (time, state.range_rate as f64),
+                ),
+                distance: previous.distance.max(state.distance),
+            });
+        }
+
+        if previous.height < 0.0 && state.height >= 0.0 {
+            passages.push(Passage {
+                kind: PassageKind::AscendingNode,
+                primary,
+                time: crossing_time((previous.time, previous.height), (time, state.height)),
+                distance: state.distance,
+            });
+        } else if previous.height > 0.0 && state.height <= 0.0 {
+            passages.push(Passage {
+                kind: PassageKind::DescendingNode,
+                primary,
+                time: crossing_time((previous.time, previous.height), (time, state.height)),
+                distance: state.distance,
+            });
+        }
+
+        for passage in passages {
+            history.passages.push(passage);
+            events.send(PassageEvent { entity, passage });
+        }
+    }
+}
+
+pub fn passage_log_system(mut events: EventReader<PassageEvent>, query: Query<&Body>) {
+    let name = |entity: Entity| {
+        query
+            .get(entity)
+            .ok()
+            .and_then(|b| b.metadata.name.clone())
+            .unwrap_or("<unknown>".to_string())
+    };
+
+    // Moons pass an apsis every few days, so keep this out of the default log level
+    for event in events.read() {
+        debug!(
+            "{} passed its {} around {} at JD {:.4}, {:.6} AU",
+            name(event.entity),
+            event.passage.kind.to_string().to_lowercase(),
+            name(event.passage.primary),
+            event.passage.time,
+            event.passage.distance
+        );
+    }
+}
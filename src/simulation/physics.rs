@@ -1,24 +1,57 @@
-use bevy::prelude::*;
+use bevy::{math::DVec3, prelude::*};
 
 use super::{
     body::Body,
-    settings::{Integrator, SimulationParameters, UPDATE_FREQUENCY},
+    force::ForceLaw,
+    settings::{Integrator, SimulationMode, SimulationParameters, UPDATE_FREQUENCY},
 };
 
-pub fn gravity_system(
-    mut body_query: Query<(&mut Transform, &mut Body, Entity)>,
-    parameters: Res<SimulationParameters>,
-) {
+/// Position and velocity in double precision, which the integrators work on.
+///
+/// `Transform` and `BodyData::velocity` follow it, rounded, for everything else to read.
+/// When something else moves a body they no longer match, and the next step starts from them instead.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhysicsState {
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+impl PhysicsState {
+    /// This state, or the rounded one if it was changed by something other than the physics.
+    pub fn synced(&self, translation: Vec3, velocity: Vec3) -> Self {
+        if self.position.as_vec3() == translation && self.velocity.as_vec3() == velocity {
+            *self
+        } else {
+            Self {
+                position: translation.as_dvec3(),
+                velocity: velocity.as_dvec3(),
+            }
+        }
+    }
+}
+
+type BodyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        &'static mut Body,
+        &'static mut PhysicsState,
+        Entity,
+    ),
+>;
+
+pub fn gravity_system(mut body_query: BodyQuery, parameters: Res<SimulationParameters>) {
     let p = &parameters;
 
-    let total_updates =
-        (p.time_step.abs() * p.updates_per_step / UPDATE_FREQUENCY as f32).round() as i32;
+    // Bodies are propagated by the patched-conic approximation instead
+    if p.mode == SimulationMode::PatchedConics {
+        return;
+    }
+
+    let (total_updates, _) = substeps(p);
 
-    // We want to do multiple updates per step to improve accuracy.
-    // Do note that this will run each physics update, thus (time_step / 60 * updates_per_step) times / second.
-    // That would be 10 updates per frame with 10.0 updates_per_step and 60.0 time_scaling.
-    // We also make sure that we run at least 1 update per frame.
-    for _ in 0..total_updates.max(1) {
+    for _ in 0..total_updates {
         match p.integrator {
             Integrator::Euler => euler(body_query.reborrow(), p),
             Integrator::Leapfrog => leapfrog(body_query.reborrow(), p),
@@ -27,10 +60,27 @@ pub fn gravity_system(
     }
 }
 
+/// Returns how many integration steps to take per physics update, and how many days each of them covers.
+///
+/// We want to do multiple updates per step to improve accuracy.
+/// Do note that this will run each physics update, thus (time_step / 60 * updates_per_step) times / second.
+/// That would be 10 updates per frame with 10.0 updates_per_step and 60.0 time_scaling.
+/// We also make sure that we run at least 1 update per frame.
+pub fn substeps(parameters: &SimulationParameters) -> (i32, f32) {
+    let total_updates = (parameters.time_step.abs() * parameters.updates_per_step
+        / UPDATE_FREQUENCY as f32)
+        .round() as i32;
+
+    (
+        total_updates.max(1),
+        parameters.time_step / UPDATE_FREQUENCY as f32 / parameters.updates_per_step,
+    )
+}
+
 pub fn compute_acceleration(
     translation: Vec3,
     bodies: &[(Transform, Body, Entity)],
-    parameters: &SimulationParameters,
+    force_law: &dyn ForceLaw,
     entity: Entity,
 ) -> Vec3 {
     let mut acceleration = Vec3::ZERO;
@@ -40,56 +90,266 @@ pub fn compute_acceleration(
             continue;
         }
 
-        let distance_squared = other_transform
-            .translation
-            .distance_squared(translation)
-            .max(parameters.softening_factor);
-
-        let force_direction = (other_transform.translation - translation).normalize();
-
-        acceleration += force_direction
-            * (parameters.gravitational_constant * other_body.data.mass / distance_squared);
+        acceleration += force_law.acceleration(
+            other_transform.translation - translation,
+            other_body.data.mass,
+        );
     }
 
     acceleration
 }
 
-fn euler(
-    mut body_query: Query<(&mut Transform, &mut Body, Entity)>,
-    parameters: &Res<SimulationParameters>,
-) {
-    let bodies: Vec<_> = body_query
+/// The mass and state of every body, in an order that only depends on their state,
+/// not on how the ECS happens to store them.
+///
+/// Float addition is not associative, so the attractions are summed in this order.
+/// A restored snapshot then evolves exactly the same.
+fn sorted_bodies(body_query: &BodyQuery) -> Vec<(Entity, f32, PhysicsState)> {
+    let mut bodies: Vec<_> = body_query
         .iter()
-        .map(|(t, p, e)| (t.clone(), p.clone(), e))
+        .map(|(transform, body, state, entity)| {
+            (
+                entity,
+                body.data.mass,
+                state.synced(transform.translation, body.data.velocity),
+            )
+        })
         .collect();
 
-    // Make sure that we account for the total updates per frame.
-    let multiplier = parameters.time_step / UPDATE_FREQUENCY as f32 / parameters.updates_per_step;
-
-    for (mut transform_outer, mut body_outer, entity_outer) in body_query.iter_mut() {
-        let acceleration = compute_acceleration(
-            transform_outer.translation,
-            &bodies,
-            parameters,
-            entity_outer,
-        );
+    bodies.sort_by(|(_, a_mass, a), (_, b_mass, b)| {
+        a_mass.total_cmp(b_mass).then_with(|| {
+            let (a, b) = (a.position, b.position);
+            a.x.total_cmp(&b.x)
+                .then(a.y.total_cmp(&b.y))
+                .then(a.z.total_cmp(&b.z))
+        })
+    });
 
-        body_outer.data.acceleration = acceleration;
-        body_outer.data.velocity += acceleration * multiplier;
-        transform_outer.translation += body_outer.data.velocity * multiplier;
-    }
+    bodies
 }
 
-fn leapfrog(
-    mut body_query: Query<(&mut Transform, &mut Body, Entity)>,
-    parameters: &Res<SimulationParameters>,
-) {
-    euler(body_query, parameters);
+/// Returns the acceleration of each of the sorted bodies if they were at `positions`.
+/// The force laws work in single precision, which is plenty for the offsets between bodies.
+fn accelerations(
+    bodies: &[(Entity, f32, PhysicsState)],
+    positions: &[DVec3],
+    force_law: &dyn ForceLaw,
+) -> Vec<DVec3> {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| {
+            let mut acceleration = DVec3::ZERO;
+
+            for (other, ((_, mass, _), other_position)) in bodies.iter().zip(positions).enumerate()
+            {
+                if other != index {
+                    acceleration += force_law
+                        .acceleration((*other_position - *position).as_vec3(), *mass)
+                        .as_dvec3();
+                }
+            }
+
+            acceleration
+        })
+        .collect()
 }
 
-fn rk4(
-    mut body_query: Query<(&mut Transform, &mut Body, Entity)>,
-    parameters: &Res<SimulationParameters>,
+/// Writes the state at the end of a step back to the bodies.
+fn write_back(
+    body_query: &mut BodyQuery,
+    bodies: &[(Entity, f32, PhysicsState)],
+    positions: &[DVec3],
+    velocities: &[DVec3],
+    accelerations: &[DVec3],
 ) {
-    euler(body_query, parameters);
+    for (index, (entity, _, _)) in bodies.iter().enumerate() {
+        if let Ok((mut transform, mut body, mut state, _)) = body_query.get_mut(*entity) {
+            *state = PhysicsState {
+                position: positions[index],
+                velocity: velocities[index],
+            };
+
+            transform.translation = state.position.as_vec3();
+            body.data.velocity = state.velocity.as_vec3();
+            body.data.acceleration = accelerations[index].as_vec3();
+        }
+    }
+}
+
+fn euler(mut body_query: BodyQuery, parameters: &Res<SimulationParameters>) {
+    let bodies = sorted_bodies(&body_query);
+
+    // Make sure that we account for the total updates per frame.
+    let (_, multiplier) = substeps(parameters);
+    let dt = multiplier as f64;
+    let force_law = parameters.force_law();
+
+    let positions: Vec<_> = bodies.iter().map(|(_, _, s)| s.position).collect();
+    let acceleration = accelerations(&bodies, &positions, force_law.as_ref());
+
+    let velocities: Vec<_> = bodies
+        .iter()
+        .zip(&acceleration)
+        .map(|((_, _, state), acceleration)| state.velocity + *acceleration * dt)
+        .collect();
+    let positions: Vec<_> = positions
+        .iter()
+        .zip(&velocities)
+        .map(|(position, velocity)| *position + *velocity * dt)
+        .collect();
+
+    write_back(
+        &mut body_query,
+        &bodies,
+        &positions,
+        &velocities,
+        &acceleration,
+    );
+}
+
+/// Kicks the velocities half a step, drifts the positions a full step, then kicks the other half.
+///
+/// Needs two evaluations of the forces per step, but keeps the energy bounded instead of letting it drift.
+fn leapfrog(mut body_query: BodyQuery, parameters: &Res<SimulationParameters>) {
+    let bodies = sorted_bodies(&body_query);
+    let dt = substeps(parameters).1 as f64;
+    let force_law = parameters.force_law();
+
+    let positions: Vec<_> = bodies.iter().map(|(_, _, s)| s.position).collect();
+    let start = accelerations(&bodies, &positions, force_law.as_ref());
+
+    let half: Vec<_> = bodies
+        .iter()
+        .zip(&start)
+        .map(|((_, _, state), acceleration)| state.velocity + *acceleration * (dt / 2.0))
+        .collect();
+    let positions: Vec<_> = positions
+        .iter()
+        .zip(&half)
+        .map(|(position, velocity)| *position + *velocity * dt)
+        .collect();
+
+    let end = accelerations(&bodies, &positions, force_law.as_ref());
+    let velocities: Vec<_> = half
+        .iter()
+        .zip(&end)
+        .map(|(velocity, acceleration)| *velocity + *acceleration * (dt / 2.0))
+        .collect();
+
+    write_back(&mut body_query, &bodies, &positions, &velocities, &end);
+}
+
+/// The classic fourth order Runge-Kutta method, four evaluations of the forces per step.
+fn rk4(mut body_query: BodyQuery, parameters: &Res<SimulationParameters>) {
+    let bodies = sorted_bodies(&body_query);
+    let dt = substeps(parameters).1 as f64;
+    let force_law = parameters.force_law();
+
+    let positions: Vec<_> = bodies.iter().map(|(_, _, s)| s.position).collect();
+    let velocities: Vec<_> = bodies.iter().map(|(_, _, s)| s.velocity).collect();
+
+    // Each stage is the velocity and acceleration at the state the previous one points to
+    let stage = |previous: &(Vec<DVec3>, Vec<DVec3>), fraction: f64| {
+        let (velocity, acceleration) = previous;
+        let moved: Vec<_> = positions
+            .iter()
+            .zip(velocity)
+            .map(|(position, velocity)| *position + *velocity * (dt * fraction))
+            .collect();
+        let kicked: Vec<_> = velocities
+            .iter()
+            .zip(acceleration)
+            .map(|(velocity, acceleration)| *velocity + *acceleration * (dt * fraction))
+            .collect();
+
+        let acceleration = accelerations(&bodies, &moved, force_law.as_ref());
+        (kicked, acceleration)
+    };
+
+    let k1 = (
+        velocities.clone(),
+        accelerations(&bodies, &positions, force_law.as_ref()),
+    );
+    let k2 = stage(&k1, 0.5);
+    let k3 = stage(&k2, 0.5);
+    let k4 = stage(&k3, 1.0);
+
+    let new_positions: Vec<_> = (0..bodies.len())
+        .map(|i| positions[i] + (k1.0[i] + 2.0 * k2.0[i] + 2.0 * k3.0[i] + k4.0[i]) * (dt / 6.0))
+        .collect();
+    let new_velocities: Vec<_> = (0..bodies.len())
+        .map(|i| velocities[i] + (k1.1[i] + 2.0 * k2.1[i] + 2.0 * k3.1[i] + k4.1[i]) * (dt / 6.0))
+        .collect();
+
+    write_back(
+        &mut body_query,
+        &bodies,
+        &new_positions,
+        &new_velocities,
+        &k1.1,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::simulation::body::BodyData;
+
+    /// How far a massless body on a circular orbit of 1 AU around the Sun ends up from where it should be
+    /// after a year, with a step of a day.
+    fn error_after_a_year(integrator: Integrator) -> f32 {
+        let parameters = SimulationParameters {
+            integrator,
+            time_step: UPDATE_FREQUENCY as f32,
+            updates_per_step: 1.0,
+            ..default()
+        };
+        let mean_motion = parameters.gravitational_constant.sqrt();
+
+        let mut world = World::new();
+        let body = |mass, velocity| Body {
+            data: BodyData {
+                mass,
+                velocity,
+                ..default()
+            },
+            metadata: default(),
+            satellites: None,
+        };
+
+        world.spawn((body(1.0, Vec3::ZERO), Transform::default()));
+        let planet = world
+            .spawn((
+                body(0.0, Vec3::Y * mean_motion),
+                Transform::from_translation(Vec3::X),
+            ))
+            .id();
+        world.insert_resource(parameters);
+
+        for _ in 0..365 {
+            world.run_system_once(gravity_system).unwrap();
+        }
+
+        let angle = mean_motion * 365.0;
+        let expected = Vec3::new(angle.cos(), angle.sin(), 0.0);
+
+        world
+            .get::<Transform>(planet)
+            .unwrap()
+            .translation
+            .distance(expected)
+    }
+
+    #[test]
+    fn higher_order_integrators_are_more_accurate() {
+        let euler = error_after_a_year(Integrator::Euler);
+        let leapfrog = error_after_a_year(Integrator::Leapfrog);
+        let rk4 = error_after_a_year(Integrator::RK4);
+
+        assert!(leapfrog < euler, "{} {}", leapfrog, euler);
+        assert!(rk4 < leapfrog / 10.0, "{} {}", rk4, leapfrog);
+    }
 }
@@ -0,0 +1,617 @@
+//! Records the state of the bodies to a compact binary file, and plays recordings back.
+//!
+//! A recording is a header followed by frames, all little-endian:
+//!
+//! | Field            | Type             |
+//! |------------------|------------------|
+//! | Magic            | `b"SSRECORD"`    |
+//! | Version          | u32              |
+//! | Body count       | u32              |
+//! | Cadence, days    | f64              |
+//! | Per body: name   | u32 length, UTF-8 |
+//!
+//! Every frame is the Julian Date (TDB) as an f64, then the position and velocity of every body
+//! in world space and units (AU / `unit_scale`, per day), as six f32s. Bodies that are gone are
+//! written as NaN.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::time::{JulianDate, SimulationTime};
+use crate::simulation::body::Body;
+
+const MAGIC: &[u8; 8] = b"SSRECORD";
+
+/// The newest version of the format we can read.
+pub const RECORDING_VERSION: u32 = 1;
+
+/// Bytes per body in a frame
+const STATE_SIZE: usize = 6 * 4;
+
+/// Position and velocity of one body in one frame.
+pub type State = (Vec3, Vec3);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// TDB
+    pub julian_date: f64,
+    pub states: Vec<State>,
+}
+
+/// A recording read into memory.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub names: Vec<String>,
+
+    /// Days between frames, as requested when recording
+    pub cadence: f64,
+
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+        Self::parse(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Reads a recording. A partial frame at the end, as left by a crash, is dropped.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a recording".to_string());
+        }
+
+        let version = reader.u32()?;
+
+        if version == 0 || version > RECORDING_VERSION {
+            return Err(format!(
+                "Version {} is not supported, we read up to version {}",
+                version, RECORDING_VERSION
+            ));
+        }
+
+        let count = reader.u32()? as usize;
+        let cadence = reader.f64()?;
+
+        let names = (0..count)
+            .map(|_| {
+                let length = reader.u32()? as usize;
+                String::from_utf8(reader.take(length)?.to_vec())
+                    .map_err(|_| "Invalid body name".to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let frame_size = 8 + count * STATE_SIZE;
+        let mut frames = Vec::new();
+
+        while reader.remaining() >= frame_size {
+            let julian_date = reader.f64()?;
+            let states = (0..count)
+                .map(|_| {
+                    let mut values = [0.0; 6];
+                    for value in &mut values {
+                        *value = reader.f32()?;
+                    }
+
+                    Ok((
+                        Vec3::new(values[0], values[1], values[2]),
+                        Vec3::new(values[3], values[4], values[5]),
+                    ))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+
+            frames.push(Frame {
+                julian_date,
+                states,
+            });
+        }
+
+        if reader.remaining() > 0 {
+            warn!(
+                "Dropped {} bytes of an incomplete frame at the end of the recording",
+                reader.remaining()
+            );
+        }
+
+        Ok(Self {
+            names,
+            cadence,
+            frames,
+        })
+    }
+
+    /// The time and states at `cursor`, a fractional frame index.
+    /// Positions between frames are interpolated with cubic Hermite splines, which use the velocities
+    /// to follow the orbits smoothly even when frames are far apart.
+    pub fn sample(&self, cursor: f64) -> Option<Frame> {
+        let last = self.frames.len().checked_sub(1)?;
+        let cursor = cursor.clamp(0.0, last as f64);
+
+        let i = (cursor.floor() as usize).min(last.saturating_sub(1));
+        let (a, b) = (&self.frames[i], &self.frames[(i + 1).min(last)]);
+
+        let s = (cursor - i as f64).clamp(0.0, 1.0) as f32;
+        let dt = (b.julian_date - a.julian_date) as f32;
+
+        let (s2, s3) = (s * s, s * s * s);
+        let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+        let h10 = s3 - 2.0 * s2 + s;
+        let h01 = -2.0 * s3 + 3.0 * s2;
+        let h11 = s3 - s2;
+
+        Some(Frame {
+            julian_date: a.julian_date + (b.julian_date - a.julian_date) * s as f64,
+            states: a
+                .states
+                .iter()
+                .zip(&b.states)
+                .map(|((p0, v0), (p1, v1))| {
+                    (
+                        h00 * *p0 + h10 * dt * *v0 + h01 * *p1 + h11 * dt * *v1,
+                        v0.lerp(*v1, s),
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + length)
+            .ok_or("Unexpected end of the recording".to_string())?;
+
+        self.offset += length;
+
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+/// Writes a recording as it is made.
+pub struct RecordingWriter {
+    writer: BufWriter<File>,
+    bodies: usize,
+}
+
+impl RecordingWriter {
+    pub fn create(path: &Path, names: &[String], cadence: f64) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        }
+
+        let file = File::create(path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+
+        let mut writer = Self {
+            writer: BufWriter::new(file),
+            bodies: names.len(),
+        };
+
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&RECORDING_VERSION.to_le_bytes());
+        header.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        header.extend_from_slice(&cadence.to_le_bytes());
+
+        for name in names {
+            header.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            header.extend_from_slice(name.as_bytes());
+        }
+
+        writer.write(&header)?;
+
+        Ok(writer)
+    }
+
+    pub fn write_frame(&mut self, julian_date: f64, states: &[State]) -> Result<(), String> {
+        if states.len() != self.bodies {
+            return Err(format!(
+                "Expected {} bodies in the frame, got {}",
+                self.bodies,
+                states.len()
+            ));
+        }
+
+        let mut frame = Vec::with_capacity(8 + states.len() * STATE_SIZE);
+        frame.extend_from_slice(&julian_date.to_le_bytes());
+
+        for (position, velocity) in states {
+            for value in position.to_array().iter().chain(&velocity.to_array()) {
+                frame.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        self.write(&frame)
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Could not write the recording: {}", e))
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| format!("Could not write the recording: {}", e))
+    }
+}
+
+/// Streams the state of the bodies to a file while recording.
+#[derive(Resource)]
+pub struct Recorder {
+    /// Days between frames
+    pub cadence: f64,
+
+    pub frames: usize,
+
+    writer: Option<RecordingWriter>,
+    path: Option<PathBuf>,
+
+    /// The bodies in the order they are written
+    entities: Vec<Entity>,
+
+    last: Option<JulianDate>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            cadence: 1.0,
+            frames: 0,
+            writer: None,
+            path: None,
+            entities: Vec::new(),
+            last: None,
+        }
+    }
+}
+
+impl Recorder {
+    /// Starts recording the bodies in `bodies` to `path`. Bodies spawned later are not recorded.
+    pub fn start(&mut self, path: &Path, bodies: &[(Entity, String)]) -> Result<(), String> {
+        self.stop();
+
+        let names: Vec<String> = bodies.iter().map(|(_, name)| name.clone()).collect();
+
+        self.writer = Some(RecordingWriter::create(path, &names, self.cadence)?);
+        self.path = Some(path.to_path_buf());
+        self.entities = bodies.iter().map(|(entity, _)| *entity).collect();
+        self.frames = 0;
+        self.last = None;
+
+        info!(
+            "Recording {} bodies to {} every {} days",
+            bodies.len(),
+            path.display(),
+            self.cadence
+        );
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        let Some(writer) = self.writer.take() else {
+            return;
+        };
+
+        match writer.finish() {
+            Ok(_) => info!(
+                "Recorded {} frames to {}",
+                self.frames,
+                self.path.as_deref().unwrap_or(Path::new("")).display()
+            ),
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// Plays a recording back instead of simulating.
+#[derive(Resource, Debug)]
+pub struct Playback {
+    pub recording: Option<Recording>,
+
+    /// Fractional frame index
+    pub cursor: f64,
+
+    /// Frames per second, negative to play in reverse
+    pub speed: f64,
+
+    pub paused: bool,
+
+    /// The bodies the recorded ones are shown with, `None` for the ones not around
+    entities: Vec<Option<Entity>>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            recording: None,
+            cursor: 0.0,
+            speed: 30.0,
+            paused: false,
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl Playback {
+    /// Starts playing `recording` with the bodies in `bodies`, matched by name.
+    pub fn start(&mut self, recording: Recording, bodies: &[(Entity, String)]) {
+        let by_name: HashMap<&str, Entity> = bodies
+            .iter()
+            .rev()
+            .map(|(entity, name)| (name.as_str(), *entity))
+            .collect();
+
+        self.entities = recording
+            .names
+            .iter()
+            .map(|name| by_name.get(name.as_str()).copied())
+            .collect();
+
+        for (name, entity) in recording.names.iter().zip(&self.entities) {
+            if entity.is_none() {
+                warn!("{} is in the recording but not in the simulation", name);
+            }
+        }
+
+        info!(
+            "Playing back {} frames of {} bodies",
+            recording.frames.len(),
+            recording.names.len()
+        );
+
+        self.recording = Some(recording);
+        self.cursor = 0.0;
+        self.paused = false;
+    }
+
+    /// Leaves the bodies where the playback is, and goes back to simulating them.
+    pub fn stop(&mut self) {
+        self.recording = None;
+        self.entities.clear();
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.recording.as_ref().map_or(0, |r| r.frames.len())
+    }
+}
+
+/// Run condition for the systems that move the bodies and the clock, which the playback does instead,
+/// and for the ones that detect events or export rows, which would otherwise report the recording again.
+pub fn not_playing_back(playback: Res<Playback>) -> bool {
+    !playback.is_active()
+}
+
+/// Names of the bodies, in the order we record them.
+pub fn body_names(query: &Query<(Entity, &Body)>) -> Vec<(Entity, String)> {
+    query
+        .iter()
+        .map(|(entity, body)| {
+            (
+                entity,
+                body.metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown>".to_string()),
+            )
+        })
+        .collect()
+}
+
+pub fn record_system(
+    mut recorder: ResMut<Recorder>,
+    time: Res<SimulationTime>,
+    query: Query<(&Transform, &Body)>,
+) {
+    if !recorder.is_recording() {
+        return;
+    }
+
+    // Frames are spaced by at least the cadence, in whichever direction the clock runs
+    if let Some(last) = recorder.last {
+        if time.current.days_since(&last).abs() < recorder.cadence {
+            return;
+        }
+    }
+
+    let states: Vec<State> = recorder
+        .entities
+        .iter()
+        .map(|entity| match query.get(*entity) {
+            Ok((transform, body)) => (transform.translation, body.data.velocity),
+            Err(_) => (Vec3::NAN, Vec3::NAN),
+        })
+        .collect();
+
+    let result = recorder
+        .writer
+        .as_mut()
+        .unwrap()
+        .write_frame(time.current.value(), &states);
+
+    match result {
+        Ok(_) => {
+            recorder.frames += 1;
+            recorder.last = Some(time.current);
+        }
+        Err(e) => {
+            error!("Stopped recording: {}", e);
+            recorder.stop();
+        }
+    }
+}
+
+/// Moves the playback along in real time, and puts the bodies and the clock where the recording has them.
+pub fn playback_system(
+    mut playback: ResMut<Playback>,
+    mut query: Query<(&mut Transform, &mut Body)>,
+    mut time: ResMut<SimulationTime>,
+    real_time: Res<Time>,
+) {
+    let count = playback.frame_count();
+
+    if count == 0 {
+        return;
+    }
+
+    if !playback.paused {
+        let cursor = playback.cursor + playback.speed * real_time.delta_secs_f64();
+        let last = (count - 1) as f64;
+
+        // Stop at either end, ready to play the other way
+        if cursor <= 0.0 || cursor >= last {
+            playback.paused = true;
+        }
+
+        playback.cursor = cursor.clamp(0.0, last);
+    }
+
+    let Some(frame) = playback.recording.as_ref().unwrap().sample(playback.cursor) else {
+        return;
+    };
+
+    for (entity, (position, velocity)) in playback.entities.iter().zip(frame.states) {
+        let Some(Ok((mut transform, mut body))) = entity.map(|e| query.get_mut(e)) else {
+            continue;
+        };
+
+        if position.is_nan() {
+            continue;
+        }
+
+        transform.translation = position;
+        body.data.velocity = velocity;
+    }
+
+    time.current = JulianDate::new(frame.julian_date);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A body on a circular orbit of 1 AU around the origin, at `day`, with an orbital period of 2π days.
+    fn circular(day: f64) -> State {
+        let (sin, cos) = (day as f32).sin_cos();
+
+        (Vec3::new(cos, 0.0, sin), Vec3::new(-sin, 0.0, cos))
+    }
+
+    #[test]
+    fn writes_and_reads_recordings() {
+        let path = std::env::temp_dir().join(format!("recording-{}.bin", std::process::id()));
+        let names = vec!["Sun".to_string(), "Björn".to_string()];
+
+        let mut writer = RecordingWriter::create(&path, &names, 0.5).unwrap();
+
+        for i in 0..4 {
+            let day = i as f64 * 0.5;
+            writer
+                .write_frame(2451545.0 + day, &[(Vec3::ZERO, Vec3::ZERO), circular(day)])
+                .unwrap();
+        }
+
+        assert!(writer.write_frame(2451547.0, &[circular(2.0)]).is_err());
+        writer.finish().unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Header, then 4 frames of 2 bodies
+        assert_eq!(
+            bytes.len(),
+            8 + 4 + 4 + 8 + (4 + 3) + (4 + 6) + 4 * (8 + 2 * 24)
+        );
+
+        let recording = Recording::parse(&bytes).unwrap();
+        assert_eq!(recording.names, names);
+        assert_eq!(recording.cadence, 0.5);
+        assert_eq!(recording.frames.len(), 4);
+        assert_eq!(recording.frames[3].julian_date, 2451546.5);
+        assert_eq!(recording.frames[3].states[1], circular(1.5));
+
+        // A crash in the middle of a frame loses just that frame
+        bytes.truncate(bytes.len() - 10);
+        assert_eq!(Recording::parse(&bytes).unwrap().frames.len(), 3);
+
+        bytes[8] = 2;
+        assert!(Recording::parse(&bytes).is_err());
+        assert!(Recording::parse(b"SSRECOR").is_err());
+    }
+
+    #[test]
+    fn interpolates_along_orbits() {
+        let recording = Recording {
+            names: vec!["Body".to_string()],
+            cadence: 0.5,
+            frames: (0..5)
+                .map(|i| Frame {
+                    julian_date: i as f64 * 0.5,
+                    states: vec![circular(i as f64 * 0.5)],
+                })
+                .collect(),
+        };
+
+        // Exactly on the frames
+        assert_eq!(recording.sample(2.0).unwrap().states[0], circular(1.0));
+        assert_eq!(recording.sample(4.0).unwrap().states[0], circular(2.0));
+
+        // Between frames we stay close to the orbit, where a straight line would cut the corner by 3%
+        let frame = recording.sample(2.5).unwrap();
+        assert_eq!(frame.julian_date, 1.25);
+        assert!(frame.states[0].0.distance(circular(1.25).0) < 1e-3);
+
+        // Past the ends
+        assert_eq!(recording.sample(-1.0).unwrap().states[0], circular(0.0));
+        assert_eq!(recording.sample(10.0).unwrap().states[0], circular(2.0));
+
+        assert!(Recording::default().sample(0.0).is_none());
+    }
+}
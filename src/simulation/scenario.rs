@@ -0,0 +1,543 @@
+//! Scenarios describe everything about a run: which bodies there are and what they look like,
+//! the physics, when it starts and where the camera is. They are RON files, see `scenarios/`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use bevy::{
+    ecs::system::RunSystemOnce,
+    prelude::*,
+    scene::ron::{extensions::Extensions, Options},
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use super::{
+    approach::CloseApproaches,
+    body::{Body, BodyType, Sun},
+    calendar::Timeline,
+    eclipse::Eclipses,
+    frame::{barycentric_correction_system, ReferenceFrame},
+    jump::DateJump,
+    lagrange::LagrangePoints,
+    player::Player,
+    recording::{Playback, Recorder},
+    settings::{
+        FollowBody, ForceLawKind, Integrator, SelectedBody, SimulationMode, SimulationParameters,
+    },
+    setup::{initialize_bodies_system, spawn_player_system},
+    time::{JulianDate, SimulationTime},
+    trajectory::{CalculateTrajectory, LiveTrajectoryPreview, Trajectories},
+    units::UnitSystem,
+};
+use crate::spk::{load_rails_system, KernelSeed};
+
+/// The newest version of the format we can read.
+pub const SCENARIO_VERSION: u32 = 1;
+
+/// Where the scenarios the UI lists are, relative to the working directory.
+pub const SCENARIO_DIR: &str = "scenarios";
+
+/// The scenario we start with, unless another one is given on the command line.
+pub const DEFAULT_SCENARIO_PATH: &str = "scenarios/solar_system.ron";
+
+/// Built in, so we can still start if the file is missing.
+const DEFAULT_SCENARIO: &str = include_str!("../../scenarios/solar_system.ron");
+
+#[derive(Resource, Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Version of the format the scenario is written in
+    pub version: u32,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// Data file with the bodies, in the format of our compiled data
+    #[serde(default)]
+    pub data: Option<PathBuf>,
+
+    /// Bodies in addition to the ones in `data`, in the same format and units
+    #[serde(default)]
+    pub bodies: Vec<Body>,
+
+    /// Texture and type of the bodies, by name. Bodies not listed keep the type in their data.
+    #[serde(default)]
+    pub appearance: HashMap<String, Appearance>,
+
+    #[serde(default)]
+    pub parameters: ScenarioParameters,
+
+    /// UTC date like 2030-06-01T12:00 or Julian Date (TDB) like JD2462653.5 to start at.
+    /// The bodies in `data` are placed there with `kernel` if given, or integrated there otherwise.
+    /// Without `data`, the `bodies` are taken to be at this epoch.
+    #[serde(default)]
+    pub epoch: Option<String>,
+
+    /// SPK kernel to place the bodies with
+    #[serde(default)]
+    pub kernel: Option<PathBuf>,
+
+    /// Shift the bodies into the barycentric frame once they are spawned.
+    /// If not given, the current setting is kept, which is to shift them unless turned off.
+    #[serde(default)]
+    pub barycentric_correction: Option<bool>,
+
+    #[serde(default)]
+    pub camera: CameraPose,
+
+    /// Where we loaded the scenario from
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Appearance {
+    /// Asset path
+    #[serde(default)]
+    pub texture: Option<String>,
+
+    pub body_type: BodyType,
+}
+
+/// Overrides of the default `SimulationParameters`, see there for what they mean.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScenarioParameters {
+    /// Derived from `mass_scale` and `unit_scale` if not given
+    pub gravitational_constant: Option<f32>,
+    pub mass_scale: Option<f32>,
+    pub unit_scale: Option<f32>,
+    pub data_units: Option<UnitSystem>,
+    pub time_step: Option<f32>,
+    pub updates_per_step: Option<f32>,
+    pub softening_factor: Option<f32>,
+    pub integrator: Option<Integrator>,
+    pub mode: Option<SimulationMode>,
+    pub force_law: Option<ForceLawKind>,
+    pub yukawa_strength: Option<f32>,
+    pub yukawa_range: Option<f32>,
+    pub mond_acceleration: Option<f32>,
+    pub power_law_exponent: Option<f32>,
+}
+
+impl ScenarioParameters {
+    /// Takes every parameter `overrides` has.
+    pub fn merge(&mut self, overrides: &ScenarioParameters) {
+        self.gravitational_constant = overrides
+            .gravitational_constant
+            .or(self.gravitational_constant);
+        self.mass_scale = overrides.mass_scale.or(self.mass_scale);
+        self.unit_scale = overrides.unit_scale.or(self.unit_scale);
+        self.data_units = overrides.data_units.or(self.data_units);
+        self.time_step = overrides.time_step.or(self.time_step);
+        self.updates_per_step = overrides.updates_per_step.or(self.updates_per_step);
+        self.softening_factor = overrides.softening_factor.or(self.softening_factor);
+        self.integrator = overrides.integrator.or(self.integrator);
+        self.mode = overrides.mode.or(self.mode);
+        self.force_law = overrides.force_law.or(self.force_law);
+        self.yukawa_strength = overrides.yukawa_strength.or(self.yukawa_strength);
+        self.yukawa_range = overrides.yukawa_range.or(self.yukawa_range);
+        self.mond_acceleration = overrides.mond_acceleration.or(self.mond_acceleration);
+        self.power_law_exponent = overrides.power_law_exponent.or(self.power_law_exponent);
+    }
+
+    pub fn simulation_parameters(&self) -> SimulationParameters {
+        let defaults = SimulationParameters::default();
+
+        let mut parameters = SimulationParameters {
+            gravitational_constant: defaults.gravitational_constant,
+            mass_scale: self.mass_scale.unwrap_or(defaults.mass_scale),
+            unit_scale: self.unit_scale.unwrap_or(defaults.unit_scale),
+            data_units: self.data_units.unwrap_or(defaults.data_units),
+            time_step: self.time_step.unwrap_or(defaults.time_step),
+            updates_per_step: self.updates_per_step.unwrap_or(defaults.updates_per_step),
+            softening_factor: self.softening_factor.unwrap_or(defaults.softening_factor),
+            integrator: self.integrator.unwrap_or(defaults.integrator),
+            mode: self.mode.unwrap_or(defaults.mode),
+            force_law: self.force_law.unwrap_or(defaults.force_law),
+            yukawa_strength: self.yukawa_strength.unwrap_or(defaults.yukawa_strength),
+            yukawa_range: self.yukawa_range.unwrap_or(defaults.yukawa_range),
+            mond_acceleration: self.mond_acceleration.unwrap_or(defaults.mond_acceleration),
+            power_law_exponent: self
+                .power_law_exponent
+                .unwrap_or(defaults.power_law_exponent),
+        };
+
+        parameters.gravitational_constant = self
+            .gravitational_constant
+            .unwrap_or(parameters.derived_gravitational_constant());
+
+        parameters
+    }
+}
+
+/// Where the player starts and where they look. Lengths are in AU.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraPose {
+    /// Of the player, in world space
+    pub position: [f32; 3],
+
+    /// How far behind the player the camera is
+    pub distance: f32,
+
+    /// Point in world space the camera looks at, the player if not given
+    pub look_at: Option<[f32; 3]>,
+
+    /// Name of the body to follow from the start
+    pub follow: Option<String>,
+}
+
+impl Default for CameraPose {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0, 0.0],
+            distance: 0.00465047,
+            look_at: None,
+            follow: None,
+        }
+    }
+}
+
+impl CameraPose {
+    /// Of the player, and of the camera relative to it.
+    pub fn transforms(&self, unit_scale: f32) -> (Transform, Transform) {
+        let position = Vec3::from(self.position) / unit_scale;
+        let target = self
+            .look_at
+            .map(|p| Vec3::from(p) / unit_scale - position)
+            .unwrap_or(Vec3::ZERO);
+
+        (
+            Transform::from_translation(position),
+            Transform::from_xyz(0.0, 0.0, self.distance / unit_scale).looking_at(target, Vec3::Y),
+        )
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::parse(DEFAULT_SCENARIO).expect("the built in scenario is invalid")
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+        let mut scenario = Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        scenario.path = Some(path.to_path_buf());
+
+        Ok(scenario)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let scenario: Self = Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(text)
+            .map_err(|e| e.to_string())?;
+
+        if scenario.version == 0 || scenario.version > SCENARIO_VERSION {
+            return Err(format!(
+                "Version {} is not supported, we read up to version {}",
+                scenario.version, SCENARIO_VERSION
+            ));
+        }
+
+        if let Some(epoch) = &scenario.epoch {
+            if JulianDate::parse(epoch).is_none() {
+                return Err(format!(
+                    "Invalid epoch \"{}\": expected a UTC date like 2030-06-01T12:00 or a Julian Date like JD2462653.5",
+                    epoch
+                ));
+            }
+        }
+
+        Ok(scenario)
+    }
+
+    /// The scenarios in `dir`, by file name.
+    pub fn list(dir: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().is_some_and(|e| e == "ron"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        paths.sort();
+        paths
+    }
+
+    pub fn epoch(&self) -> Option<JulianDate> {
+        self.epoch.as_deref().and_then(JulianDate::parse)
+    }
+
+    /// Copies of the bodies defined in the scenario itself, satellites included,
+    /// so loading them does not change the scenario.
+    pub fn bodies(&self) -> Vec<Body> {
+        self.bodies
+            .iter()
+            .map(|body| Body {
+                satellites: body.satellites.as_ref().map(|satellites| {
+                    satellites
+                        .iter()
+                        .map(|s| Arc::new(RwLock::new(s.read().unwrap().clone())))
+                        .collect()
+                }),
+                ..body.clone()
+            })
+            .collect()
+    }
+
+    /// Sets up the parameters, the kernel, the date to jump to and the frame for this scenario.
+    pub fn apply(
+        &self,
+        parameters: &mut SimulationParameters,
+        seed: &mut KernelSeed,
+        jump: &mut DateJump,
+        frame: &mut ReferenceFrame,
+    ) {
+        *parameters = self.parameters.simulation_parameters();
+        if let Some(correction) = self.barycentric_correction {
+            frame.barycentric_correction = correction;
+        }
+
+        seed.kernel = self.kernel.clone();
+        seed.epoch = None;
+
+        if let Some(epoch) = self.epoch() {
+            match (&self.kernel, &self.data) {
+                (Some(_), _) => seed.epoch = Some(epoch),
+                (None, Some(_)) => jump.jump_to(epoch),
+                // The bodies are already there
+                (None, None) => {}
+            }
+        }
+
+        info!("Using scenario \"{}\"", self.name);
+    }
+}
+
+/// A scenario picked in the UI, to switch to on the next frame.
+#[derive(Resource, Default)]
+pub struct PendingScenario(pub Option<Scenario>);
+
+/// Replaces the bodies and the player with the ones of the pending scenario, and starts it over.
+pub fn switch_scenario_system(world: &mut World) {
+    let Some(scenario) = world.resource_mut::<PendingScenario>().0.take() else {
+        return;
+    };
+
+    despawn_bodies(world);
+
+    let mut parameters = SimulationParameters::default();
+    let mut seed = KernelSeed::default();
+    let mut jump = DateJump::default();
+    let mut frame = world
+        .remove_resource::<ReferenceFrame>()
+        .unwrap_or_default();
+
+    scenario.apply(&mut parameters, &mut seed, &mut jump, &mut frame);
+
+    world.insert_resource(parameters);
+    world.insert_resource(frame);
+    world.insert_resource(seed);
+    world.insert_resource(jump);
+    world.insert_resource(scenario);
+
+    for result in [
+        world.run_system_once(initialize_bodies_system),
+        world.run_system_once(spawn_player_system),
+        world.run_system_once(barycentric_correction_system),
+        world.run_system_once(load_rails_system),
+    ] {
+        if let Err(e) = result {
+            error!("Could not set up the scenario: {}", e);
+        }
+    }
+}
+
+/// Despawns the bodies and the player, and resets everything that refers to them.
+pub fn despawn_bodies(world: &mut World) {
+    let entities: Vec<Entity> = world
+        .query_filtered::<Entity, Or<(With<Body>, With<Player>)>>()
+        .iter(world)
+        .collect();
+
+    for entity in entities {
+        world.entity_mut(entity).despawn_recursive();
+    }
+
+    world.remove_resource::<Sun>();
+
+    // Everything that refers to the bodies we just despawned
+    world.insert_resource(FollowBody::default());
+    world.insert_resource(SelectedBody::default());
+    world.insert_resource(SimulationTime::default());
+    world.insert_resource(DateJump::default());
+    world.insert_resource(CloseApproaches::default());
+    world.insert_resource(Timeline::default());
+    world.insert_resource(Eclipses::default());
+    world.insert_resource(LagrangePoints::default());
+    world.insert_resource(Trajectories::default());
+    world.insert_resource(CalculateTrajectory::default());
+    world.insert_resource(LiveTrajectoryPreview::default());
+    world.resource_mut::<ReferenceFrame>().center_position = None;
+
+    // The recorded bodies are gone
+    if let Some(mut recorder) = world.get_resource_mut::<Recorder>() {
+        recorder.stop();
+    }
+
+    if let Some(mut playback) = world.get_resource_mut::<Playback>() {
+        playback.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::data::{prepare_dataset, Dataset};
+
+    #[test]
+    fn parses_default_scenario() {
+        let scenario = Scenario::default();
+
+        assert_eq!(scenario.version, SCENARIO_VERSION);
+        assert_eq!(
+            scenario.data.as_deref(),
+            Some(Path::new(super::super::data::DATA_PATH))
+        );
+        assert!(matches!(
+            scenario.appearance["Sun"].body_type,
+            BodyType::Star
+        ));
+        assert_eq!(
+            scenario.appearance["Sun"].texture.as_deref(),
+            Some("sun.jpg")
+        );
+        assert_eq!(scenario.camera.follow, None);
+
+        // The file on disk is the one we build in
+        assert_eq!(
+            Scenario::load(Path::new(DEFAULT_SCENARIO_PATH))
+                .unwrap()
+                .name,
+            scenario.name
+        );
+
+        let parameters = scenario.parameters.simulation_parameters();
+        assert_eq!(parameters.integrator, Integrator::Leapfrog);
+        assert_eq!(
+            parameters.gravitational_constant,
+            SimulationParameters::default().gravitational_constant
+        );
+    }
+
+    #[test]
+    fn parses_every_scenario() {
+        for path in Scenario::list(Path::new(SCENARIO_DIR)) {
+            Scenario::load(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn parses_inline_bodies() {
+        let scenario = Scenario::parse(
+            r#"(
+                version: 1,
+                name: "Binary",
+                epoch: "JD2451545.0",
+                parameters: (integrator: RK4, time_step: 0.1),
+                bodies: [
+                    (
+                        data: (mass: 1.0, radius: 0.005, position: {"x": 0.0, "y": 0.0, "z": 0.5}, velocity: {"x": 0.0, "y": 0.0, "z": 0.0}),
+                        metadata: (name: "A", color: (1.0, 0.8, 0.2, 1.0)),
+                        satellites: [
+                            (
+                                data: (mass: 0.001, radius: 0.0005, orbital_elements: (eccentricity: 0.1, semi_major_axis: 1.0, inclination: 0.0, longitude_of_ascending_node: 0.0, argument_of_perifocus: 0.0, mean_anomaly: 0.0)),
+                                metadata: (name: "b"),
+                            ),
+                        ],
+                    ),
+                ],
+                camera: (position: (0.0, 1.0, 0.0), follow: "b"),
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.bodies.len(), 1);
+        assert_eq!(scenario.epoch().unwrap().value(), 2451545.0);
+        assert_eq!(scenario.camera.follow.as_deref(), Some("b"));
+
+        // Our data has y and z swapped
+        let body = &scenario.bodies()[0];
+        assert_eq!(body.data.position, Vec3::new(0.0, 0.5, 0.0));
+        assert!(!body.satellites.as_ref().unwrap()[0]
+            .read()
+            .unwrap()
+            .data
+            .has_state_vectors());
+
+        let parameters = scenario.parameters.simulation_parameters();
+        assert_eq!(parameters.integrator, Integrator::RK4);
+        assert_eq!(parameters.time_step, 0.1);
+
+        // b starts at periapsis, relative to A
+        let dataset = prepare_dataset(
+            Dataset {
+                epoch: scenario.epoch().unwrap().value(),
+                bodies: scenario.bodies(),
+            },
+            &parameters,
+        );
+        let a = &dataset.bodies[0];
+        let b = a.satellites.as_ref().unwrap()[0].read().unwrap().data;
+        assert!((b.position.distance(a.data.position) - 0.9).abs() < 1e-6);
+
+        // Without data, the bodies are already at the epoch
+        let (mut seed, mut jump) = (KernelSeed::default(), DateJump::default());
+        let mut frame = ReferenceFrame::default();
+        scenario.apply(
+            &mut SimulationParameters::default(),
+            &mut seed,
+            &mut jump,
+            &mut frame,
+        );
+        assert!(!jump.is_active());
+        assert_eq!(seed.kernel, None);
+        assert!(frame.barycentric_correction);
+
+        let scenario =
+            Scenario::parse("(version: 1, name: \"Drifting\", barycentric_correction: false)")
+                .unwrap();
+        scenario.apply(
+            &mut SimulationParameters::default(),
+            &mut seed,
+            &mut jump,
+            &mut frame,
+        );
+        assert!(!frame.barycentric_correction);
+    }
+
+    #[test]
+    fn rejects_invalid_scenarios() {
+        let newer = Scenario::parse("(version: 2, name: \"From the future\")").unwrap_err();
+        assert!(newer.contains("Version 2"), "{}", newer);
+
+        assert!(Scenario::parse("(version: 1, name: \"Typo\", paramaters: ())").is_err());
+        assert!(Scenario::parse("(version: 1, name: \"Then\", epoch: \"soon\")").is_err());
+        assert!(Scenario::parse("(name: \"Unversioned\")").is_err());
+    }
+}
@@ -1,21 +1,25 @@
-use std::{env, sync::Mutex};
-
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{force::MOND_ACCELERATION, units::UnitSystem};
 
 /// Physics update frequency (Hz)
 pub const UPDATE_FREQUENCY: i32 = 60;
 
-#[derive(Resource, Reflect)]
+#[derive(Resource, Reflect, Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationParameters {
-    /// AU^3 / Solar Mass * day^2
+    /// Length unit^3 / mass unit * day^2, derived from `mass_scale` and `unit_scale` unless overridden
     pub gravitational_constant: f32,
 
-    /// Solar Mass
+    /// Unit of mass the simulation runs in, Solar Mass
     pub mass_scale: f32,
 
-    /// AU
+    /// Unit of length the simulation runs in, AU
     pub unit_scale: f32,
 
+    /// Units our data is authored in
+    pub data_units: UnitSystem,
+
     /// Days per second
     pub time_step: f32,
 
@@ -27,23 +31,51 @@ pub struct SimulationParameters {
 
     /// Which integrator to use
     pub integrator: Integrator,
+
+    /// Full N-body or the patched-conic approximation
+    pub mode: SimulationMode,
+
+    /// How bodies attract each other
+    pub force_law: ForceLawKind,
+
+    /// Yukawa α, relative to Newtonian gravity
+    pub yukawa_strength: f32,
+
+    /// Yukawa λ, AU
+    pub yukawa_range: f32,
+
+    /// MOND a0, AU / day^2
+    pub mond_acceleration: f32,
+
+    /// Gravity falls off as 1 / r^power_law_exponent
+    pub power_law_exponent: f32,
 }
 
 impl Default for SimulationParameters {
     fn default() -> Self {
-        Self {
-            gravitational_constant: 2.96e-4,
+        let mut parameters = Self {
+            gravitational_constant: 0.0,
             mass_scale: 1.0,
             unit_scale: 1.0,
+            data_units: UnitSystem::default(),
             time_step: 1.0,
             updates_per_step: 10.0,
             softening_factor: 1e-12,
             integrator: Integrator::default(),
-        }
+            mode: SimulationMode::default(),
+            force_law: ForceLawKind::default(),
+            yukawa_strength: 1.0,
+            yukawa_range: 1.0,
+            mond_acceleration: MOND_ACCELERATION,
+            power_law_exponent: 3.0,
+        };
+
+        parameters.gravitational_constant = parameters.derived_gravitational_constant();
+        parameters
     }
 }
 
-#[derive(Default, Reflect, Debug)]
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Integrator {
     Euler,
     #[default]
@@ -51,101 +83,66 @@ pub enum Integrator {
     RK4,
 }
 
-#[derive(Resource, Default)]
-pub struct FollowBody {
-    pub entity: Option<Entity>,
-    pub is_active: bool,
-}
-
-#[derive(Resource, Default)]
-pub struct SelectedBody {
-    pub entity: Option<Entity>,
+impl Integrator {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "euler" => Some(Self::Euler),
+            "leapfrog" => Some(Self::Leapfrog),
+            "rk4" => Some(Self::RK4),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Resource, Default)]
-pub struct ElapsedTime(pub f32);
-
-pub fn elapsed_time_update_system(
-    mut elapsed_time: ResMut<ElapsedTime>,
-    parameters: Res<SimulationParameters>,
-) {
-    elapsed_time.0 += parameters.time_step / UPDATE_FREQUENCY as f32;
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SimulationMode {
+    /// Every body attracts every other body.
+    #[default]
+    NBody,
+    /// Every body follows a two-body conic relative to the primary whose sphere of influence it is in.
+    PatchedConics,
 }
 
-use crate::ui::element::UI_DEBUG;
-
-pub fn params_override_system(mut params: ResMut<SimulationParameters>) {
-    let args = env::args().collect::<Vec<_>>();
-
-    info!("Args: {:?}", args);
-
-    if args.len() < 2 {
-        return;
+impl SimulationMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "nbody" => Some(Self::NBody),
+            "patched_conics" => Some(Self::PatchedConics),
+            _ => None,
+        }
     }
+}
 
-    if args[1] == "debug" {
-        info!("Debug mode enabled");
-        unsafe { UI_DEBUG = true };
-    }
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ForceLawKind {
+    #[default]
+    Newtonian,
+    Plummer,
+    Yukawa,
+    Mond,
+    PowerLaw,
+}
 
-    for arg in args[1..args.len()].chunks_exact(2) {
-        if arg.len() == 1 {
-            error!("Argument {} has no value provided", arg[0]);
-            return;
+impl ForceLawKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "newtonian" => Some(Self::Newtonian),
+            "plummer" => Some(Self::Plummer),
+            "yukawa" => Some(Self::Yukawa),
+            "mond" => Some(Self::Mond),
+            "power_law" => Some(Self::PowerLaw),
+            _ => None,
         }
+    }
+}
 
-        let field = &arg[0];
-        let value = &arg[1];
-
-        if field == "integrator" {
-            let parameter_override: &mut Integrator = params.get_field_mut(field).unwrap();
-            let override_successful = match value.as_str() {
-                "euler" => {
-                    let _ = parameter_override.set(Box::new(Integrator::Euler));
-                    true
-                }
-                "leapfrog" => {
-                    let _ = parameter_override.set(Box::new(Integrator::Leapfrog));
-                    true
-                }
-                "rk4" => {
-                    let _ = parameter_override.set(Box::new(Integrator::RK4));
-                    true
-                }
-                _ => {
-                    error!(
-                        "Could not parse value \"{}\" for {}: invalid integrator. Defaulting to {:?}",
-                        value, field, Integrator::default()
-                    );
-                    false
-                }
-            };
-
-            if override_successful {
-                info!("Using override value \"{}\" for {}", value, field)
-            }
-
-            return;
-        }
+#[derive(Resource, Default)]
+pub struct FollowBody {
+    pub entity: Option<Entity>,
+    pub is_active: bool,
+}
 
-        let parameter_override: &mut f32 = params.get_field_mut(field).unwrap();
-        let parsed_value = value.parse::<f32>();
-
-        match parsed_value {
-            Ok(f) => match parameter_override.set(Box::new(f)) {
-                Ok(_) => {
-                    info!("Using override value \"{}\" for {}", f, field)
-                }
-                Err(error) => {
-                    error!(
-                        "Could not set value \"{}\" for {}: {:?}",
-                        value, field, error
-                    )
-                }
-            },
-            Err(_) => {
-                error!("Could not parse value \"{}\" for {}", value, field)
-            }
-        }
-    }
+#[derive(Resource, Default)]
+pub struct SelectedBody {
+    pub entity: Option<Entity>,
 }
@@ -1,134 +1,230 @@
-use bevy::{color, prelude::*, render::camera};
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
     material::saturn_rings::SaturnRingMaterial,
+    mpcorb::MinorPlanetImport,
     simulation::{
-        body::*, data::initialize_bodies, player::Player, settings::SimulationParameters,
+        body::*,
+        data::initialize_bodies,
+        player::Player,
+        scenario::Scenario,
+        settings::{FollowBody, SimulationParameters},
+        time::SimulationTime,
     },
+    spk::KernelSeed,
 };
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "every resource the bodies are built from is a system parameter"
+)]
 pub fn initialize_bodies_system(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut _ring_material: ResMut<Assets<SaturnRingMaterial>>,
-    _parameters: Res<SimulationParameters>,
+    parameters: Res<SimulationParameters>,
     asset_server: Res<AssetServer>,
+    mut time: ResMut<SimulationTime>,
+    mut follow: ResMut<FollowBody>,
+    scenario: Res<Scenario>,
+    seed: Res<KernelSeed>,
+    minor_planets: Res<MinorPlanetImport>,
 ) {
-    let bodies = initialize_bodies(&asset_server);
-
-    if let Some(bodies) = bodies {
-        for body in bodies {
-            if body.metadata.name.as_ref().is_some_and(|n| n == "Sun") {
-                let sun = commands
-                    .spawn((
-                        body.clone(),
-                        Mesh3d(meshes.add(Sphere {
-                            radius: body.data.radius,
-                        })),
-                        MeshMaterial3d(materials.add(StandardMaterial {
-                            base_color: Color::WHITE,
-                            base_color_texture: body.metadata.texture.clone(),
-                            emissive_texture: body.metadata.texture.clone(),
-                            emissive: LinearRgba::new(2.0, 1.0, 1.0, 1.0),
-                            ..default()
-                        })),
-                        Transform::from_translation(body.data.position),
-                        Star {},
-                    ))
-                    .with_children(|p| {
-                        p.spawn(PointLight {
-                            shadows_enabled: true,
-                            color: Color::WHITE,
-                            range: f32::MAX,
-                            radius: body.data.radius * 1.05,
-                            intensity: 1_000_000.0,
-                            shadow_depth_bias: 0.0,
-                            shadow_map_near_z: 0.0,
-                            shadow_normal_bias: 0.0,
-                            ..default()
-                        });
-                    })
-                    .id();
-
-                commands.insert_resource(Sun(sun));
-            } else {
-                let mut entity = commands.spawn((
-                    body.clone(),
-                    Mesh3d(meshes.add(Sphere {
-                        radius: body.data.radius,
-                    })),
-                    MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: match &body.metadata.texture {
-                            Some(_) => Color::WHITE,
-                            None => body.metadata.color,
-                        },
-                        base_color_texture: body.metadata.texture.clone(),
-                        ..default()
-                    })),
-                    Transform::from_translation(body.data.position),
-                ));
-
-                // Saturn's rings
-                entity.with_children(|parent| {
-                    if body.metadata.name == Some("Saturn".to_string()) {
-                        parent.spawn((
-                            // TODO too faint, should probably use shader as initially planned
-                            Mesh3d(meshes.add(Mesh::from(Plane3d {
-                                half_size: Vec2::new(
-                                    body.data.radius * 5.0,
-                                    body.data.radius * 5.0,
-                                ),
-                                ..Default::default()
-                            }))),
-                            MeshMaterial3d(materials.add(StandardMaterial {
-                                base_color_texture: Some(asset_server.load("saturn_rings.png")),
-                                emissive_texture: Some(asset_server.load("saturn_rings.png")),
-                                emissive: Color::WHITE.into(),
-                                ..default()
-                            })),
-                        ));
-                    }
-                });
+    let dataset = initialize_bodies(
+        Some(asset_server.as_ref()),
+        &parameters,
+        &scenario,
+        &seed,
+        &minor_planets,
+    );
+
+    // Bodies that name their parent explicitly, we can only link them once everything is spawned
+    let mut named_parents: Vec<(Entity, String)> = Vec::new();
+    let mut entities: HashMap<String, Entity> = HashMap::new();
 
-                insert_type_marker(&mut entity, &body);
+    match dataset {
+        Ok(dataset) => {
+            *time = SimulationTime::new(dataset.epoch);
 
-                if let Some(satellites) = body.satellites {
+            for body in dataset.bodies {
+                let body_entity = spawn_body(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    &asset_server,
+                    &body,
+                    body.data.position,
+                );
+
+                register_body(&mut entities, &mut named_parents, body_entity, &body);
+
+                if let Some(satellites) = &body.satellites {
                     for satellite in satellites {
                         let satellite = satellite.read().unwrap();
 
-                        let mut satellite_entity = commands.spawn((
-                            satellite.clone(),
-                            Mesh3d(meshes.add(Sphere {
-                                radius: satellite.data.radius,
-                            })),
-                            MeshMaterial3d(materials.add(StandardMaterial {
-                                base_color: satellite.metadata.color,
-                                base_color_texture: satellite.metadata.texture.clone(),
-                                ..default()
-                            })),
-                            Transform::from_translation(satellite.data.position),
-                        ));
-
-                        insert_type_marker(&mut satellite_entity, &satellite);
+                        let satellite_entity = spawn_body(
+                            &mut commands,
+                            &mut meshes,
+                            &mut materials,
+                            &asset_server,
+                            &satellite,
+                            satellite.data.position,
+                        );
+
+                        if satellite.metadata.parent.is_none() {
+                            commands
+                                .entity(satellite_entity)
+                                .insert(ParentBody(body_entity));
+                        }
+
+                        register_body(
+                            &mut entities,
+                            &mut named_parents,
+                            satellite_entity,
+                            &satellite,
+                        );
                     }
                 }
             }
         }
+        Err(e) => error!("Could not set up the scenario \"{}\": {}", scenario.name, e),
+    }
+
+    for (entity, parent) in named_parents {
+        match entities.get(&parent) {
+            Some(parent) => {
+                commands.entity(entity).insert(ParentBody(*parent));
+            }
+            None => error!("Could not find parent body \"{}\" of {}", parent, entity),
+        }
+    }
+
+    if let Some(name) = &scenario.camera.follow {
+        match entities.get(name) {
+            Some(entity) => {
+                follow.entity = Some(*entity);
+                follow.is_active = true;
+            }
+            None => error!("Could not find body \"{}\" to follow", name),
+        }
+    }
+}
+
+fn register_body(
+    entities: &mut HashMap<String, Entity>,
+    named_parents: &mut Vec<(Entity, String)>,
+    entity: Entity,
+    body: &Body,
+) {
+    if let Some(name) = &body.metadata.name {
+        entities.insert(name.clone(), entity);
+    }
+
+    if let Some(parent) = &body.metadata.parent {
+        named_parents.push((entity, parent.clone()));
     }
 }
 
-pub fn spawn_player_system(mut commands: Commands, parameters: Res<SimulationParameters>) {
+/// Spawns a body at `translation`, with its mesh and material. The Sun also gets its light, and Saturn its rings.
+pub fn spawn_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    asset_server: &AssetServer,
+    body: &Body,
+    translation: Vec3,
+) -> Entity {
+    if body.metadata.name.as_ref().is_some_and(|n| n == "Sun") {
+        let sun = commands
+            .spawn((
+                body.clone(),
+                Mesh3d(meshes.add(Sphere {
+                    radius: body.data.radius,
+                })),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::WHITE,
+                    base_color_texture: body.metadata.texture.clone(),
+                    emissive_texture: body.metadata.texture.clone(),
+                    emissive: LinearRgba::new(2.0, 1.0, 1.0, 1.0),
+                    ..default()
+                })),
+                Transform::from_translation(translation),
+                Star {},
+            ))
+            .with_children(|p| {
+                p.spawn(PointLight {
+                    shadows_enabled: true,
+                    color: Color::WHITE,
+                    range: f32::MAX,
+                    radius: body.data.radius * 1.05,
+                    intensity: 1_000_000.0,
+                    shadow_depth_bias: 0.0,
+                    shadow_map_near_z: 0.0,
+                    shadow_normal_bias: 0.0,
+                });
+            })
+            .id();
+
+        commands.insert_resource(Sun(sun));
+
+        return sun;
+    }
+
+    let mut entity = commands.spawn((
+        body.clone(),
+        Mesh3d(meshes.add(Sphere {
+            radius: body.data.radius,
+        })),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: match &body.metadata.texture {
+                Some(_) => Color::WHITE,
+                None => body.metadata.color,
+            },
+            base_color_texture: body.metadata.texture.clone(),
+            ..default()
+        })),
+        Transform::from_translation(translation),
+    ));
+
+    // Saturn's rings
+    entity.with_children(|parent| {
+        if body.metadata.name == Some("Saturn".to_string()) {
+            parent.spawn((
+                // TODO too faint, should probably use shader as initially planned
+                Mesh3d(meshes.add(Mesh::from(Plane3d {
+                    half_size: Vec2::new(body.data.radius * 5.0, body.data.radius * 5.0),
+                    ..Default::default()
+                }))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color_texture: Some(asset_server.load("saturn_rings.png")),
+                    emissive_texture: Some(asset_server.load("saturn_rings.png")),
+                    emissive: Color::WHITE.into(),
+                    ..default()
+                })),
+            ));
+        }
+    });
+
+    insert_type_marker(&mut entity, body);
+
+    entity.id()
+}
+
+pub fn spawn_player_system(
+    mut commands: Commands,
+    parameters: Res<SimulationParameters>,
+    scenario: Res<Scenario>,
+) {
+    let (player, camera) = scenario.camera.transforms(parameters.unit_scale);
+
+    spawn_player(&mut commands, player, camera);
+}
+
+/// Spawns the player at `player`, with the camera at `camera` relative to it.
+pub fn spawn_player(commands: &mut Commands, player: Transform, camera: Transform) {
     commands
-        .spawn((
-            Player,
-            Transform::from_xyz(
-                0.00450250878464055477,
-                0.00076707642709100705 * 10.0,
-                0.00026605791776697764,
-            ),
-            Visibility::default(),
-        ))
+        .spawn((Player, player, Visibility::default()))
         .with_children(|parent| {
             parent.spawn((
                 bevy::core_pipeline::bloom::Bloom::NATURAL,
@@ -142,8 +238,7 @@ pub fn spawn_player_system(mut commands: Commands, parameters: Res<SimulationPar
                     near: 1e-5,
                     ..default()
                 }),
-                Transform::from_xyz(0.0, 0.0, 0.00465047 / parameters.unit_scale)
-                    .looking_at(Vec3::ZERO, Vec3::Y),
+                camera,
                 bevy_flycam::FlyCam,
             ));
         });
@@ -163,6 +258,9 @@ fn insert_type_marker(entity: &mut EntityCommands, body: &Body) {
         BodyType::Moon => {
             entity.insert(Moon {});
         }
+        BodyType::Spacecraft => {
+            entity.insert(Spacecraft {});
+        }
         BodyType::Other => {
             entity.insert(Other {});
         }
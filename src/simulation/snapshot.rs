@@ -0,0 +1,606 @@
+//! Snapshots of the complete dynamic state, to save a run and pick it up later.
+//!
+//! They are RON files, which read floats back exactly, so a restored snapshot evolves bit-for-bit
+//! like the run it was taken from.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    ecs::{system::RunSystemOnce, world::EntityRef},
+    math::DVec3,
+    prelude::*,
+    scene::ron::{self, ser::PrettyConfig},
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    body::{
+        Body, BodyData, BodyMetadata, BodyOrbitalElements, BodyType, DwarfPlanet, Moon, Other,
+        ParentBody, Planet, Spacecraft, Star,
+    },
+    jump::DateJump,
+    physics::PhysicsState,
+    player::Player,
+    scenario::{despawn_bodies, Scenario},
+    settings::{FollowBody, SelectedBody, SimulationParameters},
+    setup::{spawn_body, spawn_player},
+    time::SimulationTime,
+};
+
+/// The newest version of the format we can read.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Where F5 saves to and F9 loads from.
+pub const QUICK_SAVE_PATH: &str = "snapshots/quicksave.ron";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Version of the format the snapshot is written in
+    pub version: u32,
+
+    /// The scenario the snapshot was taken in
+    pub scenario: Option<PathBuf>,
+
+    pub time: SimulationTime,
+    pub parameters: SimulationParameters,
+
+    /// Including the ones spawned at runtime
+    pub bodies: Vec<BodySnapshot>,
+
+    pub camera: CameraSnapshot,
+}
+
+/// A body as it is now. Bodies refer to each other by their index in the snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodySnapshot {
+    pub name: Option<String>,
+    pub id: Option<u32>,
+    pub parent: Option<String>,
+    pub relative_to_parent: bool,
+
+    /// sRGB
+    pub color: [f32; 4],
+
+    /// Asset path
+    pub texture: Option<String>,
+
+    pub body_type: BodyType,
+
+    /// The body we orbit, see `ParentBody`
+    pub parent_body: Option<usize>,
+
+    /// Where the body is now, in world space
+    pub translation: [f32; 3],
+
+    /// Where the body started, see `BodyData::position`
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub acceleration: [f32; 3],
+    pub mass: f32,
+    pub radius: f32,
+    pub temperature: f32,
+    pub density: f32,
+    pub rotation: f32,
+    pub obliquity: f32,
+    pub orbital_elements: Option<BodyOrbitalElements>,
+
+    /// `translation` and `velocity` in the double precision the physics runs in, not in older snapshots
+    #[serde(default)]
+    pub state: Option<([f64; 3], [f64; 3])>,
+}
+
+impl BodySnapshot {
+    /// The body, and where it is.
+    pub fn body(&self, asset_server: Option<&AssetServer>) -> (Body, Vec3) {
+        let body = Body {
+            data: BodyData {
+                position: Vec3::from(self.position),
+                velocity: Vec3::from(self.velocity),
+                acceleration: Vec3::from(self.acceleration),
+                mass: self.mass,
+                radius: self.radius,
+                temperature: self.temperature,
+                density: self.density,
+                rotation: self.rotation,
+                obliquity: self.obliquity,
+                orbital_elements: self.orbital_elements,
+            },
+            metadata: BodyMetadata {
+                name: self.name.clone(),
+                id: self.id,
+                parent: self.parent.clone(),
+                relative_to_parent: self.relative_to_parent,
+                color: Color::srgba(self.color[0], self.color[1], self.color[2], self.color[3]),
+                texture: self
+                    .texture
+                    .as_ref()
+                    .and_then(|texture| Some(asset_server?.load(texture.clone()))),
+                body_type: self.body_type.clone(),
+            },
+            satellites: None,
+        };
+
+        (body, Vec3::from(self.translation))
+    }
+
+    pub fn state(&self) -> Option<PhysicsState> {
+        self.state.map(|(position, velocity)| PhysicsState {
+            position: DVec3::from(position),
+            velocity: DVec3::from(velocity),
+        })
+    }
+}
+
+/// Where the player is and where the camera looks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CameraSnapshot {
+    pub player_translation: [f32; 3],
+    pub player_rotation: [f32; 4],
+
+    /// Relative to the player
+    pub camera_translation: [f32; 3],
+    pub camera_rotation: [f32; 4],
+
+    /// Index of the body we follow
+    pub follow: Option<usize>,
+    pub follow_active: bool,
+
+    /// Index of the body selected in the UI
+    pub selected: Option<usize>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of `world`. Not possible in the middle of a date jump,
+    /// as the jump temporarily changes the time step.
+    pub fn capture(world: &mut World) -> Result<Self, String> {
+        if world
+            .get_resource::<DateJump>()
+            .is_some_and(|jump| jump.is_active())
+        {
+            return Err("Cannot take a snapshot while jumping to a date".to_string());
+        }
+
+        let entities: Vec<Entity> = world
+            .query_filtered::<Entity, With<Body>>()
+            .iter(world)
+            .collect();
+
+        let index: HashMap<Entity, usize> = entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (*entity, i))
+            .collect();
+
+        let bodies = entities
+            .iter()
+            .map(|entity| {
+                let entity = world.entity(*entity);
+                let body = entity.get::<Body>().unwrap();
+                let translation = entity
+                    .get::<Transform>()
+                    .map(|t| t.translation)
+                    .unwrap_or(body.data.position);
+
+                BodySnapshot {
+                    name: body.metadata.name.clone(),
+                    id: body.metadata.id,
+                    parent: body.metadata.parent.clone(),
+                    relative_to_parent: body.metadata.relative_to_parent,
+                    color: body.metadata.color.to_srgba().to_f32_array(),
+                    texture: body
+                        .metadata
+                        .texture
+                        .as_ref()
+                        .and_then(|t| t.path())
+                        .map(|p| p.to_string()),
+                    body_type: marker_type(&entity),
+                    parent_body: entity
+                        .get::<ParentBody>()
+                        .and_then(|p| index.get(&p.0).copied()),
+                    translation: translation.to_array(),
+                    position: body.data.position.to_array(),
+                    velocity: body.data.velocity.to_array(),
+                    acceleration: body.data.acceleration.to_array(),
+                    mass: body.data.mass,
+                    radius: body.data.radius,
+                    temperature: body.data.temperature,
+                    density: body.data.density,
+                    rotation: body.data.rotation,
+                    obliquity: body.data.obliquity,
+                    orbital_elements: body.data.orbital_elements,
+                    state: entity
+                        .get::<PhysicsState>()
+                        .map(|s| s.synced(translation, body.data.velocity))
+                        .map(|s| (s.position.to_array(), s.velocity.to_array())),
+                }
+            })
+            .collect();
+
+        let mut camera = CameraSnapshot::default();
+
+        if let Ok(player) = world
+            .query_filtered::<&Transform, With<Player>>()
+            .get_single(world)
+        {
+            camera.player_translation = player.translation.to_array();
+            camera.player_rotation = player.rotation.to_array();
+        }
+
+        if let Ok(transform) = world
+            .query_filtered::<&Transform, With<bevy_flycam::FlyCam>>()
+            .get_single(world)
+        {
+            camera.camera_translation = transform.translation.to_array();
+            camera.camera_rotation = transform.rotation.to_array();
+        }
+
+        if let Some(follow) = world.get_resource::<FollowBody>() {
+            camera.follow = follow.entity.and_then(|e| index.get(&e).copied());
+            camera.follow_active = follow.is_active;
+        }
+
+        camera.selected = world
+            .get_resource::<SelectedBody>()
+            .and_then(|selected| selected.entity)
+            .and_then(|e| index.get(&e).copied());
+
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            scenario: world
+                .get_resource::<Scenario>()
+                .and_then(|s| s.path.clone()),
+            time: *world.resource::<SimulationTime>(),
+            parameters: world.resource::<SimulationParameters>().clone(),
+            bodies,
+            camera,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|e| format!("Could not serialize the snapshot: {}", e))?;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+        }
+
+        fs::write(path, text).map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let snapshot: Self = ron::from_str(text).map_err(|e| e.to_string())?;
+
+        if snapshot.version == 0 || snapshot.version > SNAPSHOT_VERSION {
+            return Err(format!(
+                "Version {} is not supported, we read up to version {}",
+                snapshot.version, SNAPSHOT_VERSION
+            ));
+        }
+
+        if let Some(body) = snapshot
+            .bodies
+            .iter()
+            .find(|b| b.parent_body.is_some_and(|p| p >= snapshot.bodies.len()))
+        {
+            return Err(format!(
+                "{} orbits a body that is not in the snapshot",
+                body.name.as_deref().unwrap_or("<unknown>")
+            ));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Replaces the bodies, the player and the clock with the ones in the snapshot.
+    pub fn restore(self, world: &mut World) {
+        despawn_bodies(world);
+
+        if let Some(scenario) = self.scenario.as_deref().map(Scenario::load) {
+            match scenario {
+                Ok(scenario) => world.insert_resource(scenario),
+                Err(e) => warn!("Could not load the scenario of the snapshot: {}", e),
+            }
+        }
+
+        world.insert_resource(self.time);
+        world.insert_resource(self.parameters.clone());
+
+        if let Err(e) = world.run_system_once_with(self, spawn_snapshot_system) {
+            error!("Could not restore the snapshot: {}", e);
+        }
+    }
+}
+
+/// The kind of body, from its marker component. Bodies spawned at runtime only have the marker.
+fn marker_type(entity: &EntityRef) -> BodyType {
+    if entity.contains::<Star>() {
+        BodyType::Star
+    } else if entity.contains::<Planet>() {
+        BodyType::Planet
+    } else if entity.contains::<DwarfPlanet>() {
+        BodyType::DwarfPlanet
+    } else if entity.contains::<Moon>() {
+        BodyType::Moon
+    } else if entity.contains::<Spacecraft>() {
+        BodyType::Spacecraft
+    } else if entity.contains::<Other>() {
+        BodyType::Other
+    } else {
+        BodyType::Unknown
+    }
+}
+
+fn spawn_snapshot_system(
+    In(snapshot): In<Snapshot>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut follow: ResMut<FollowBody>,
+    mut selected: ResMut<SelectedBody>,
+) {
+    let entities: Vec<Entity> = snapshot
+        .bodies
+        .iter()
+        .map(|b| {
+            let (body, translation) = b.body(Some(&asset_server));
+
+            spawn_body(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &asset_server,
+                &body,
+                translation,
+            )
+        })
+        .collect();
+
+    for (body, entity) in snapshot.bodies.iter().zip(&entities) {
+        if let Some(state) = body.state() {
+            commands.entity(*entity).insert(state);
+        }
+
+        if let Some(parent) = body.parent_body {
+            commands
+                .entity(*entity)
+                .insert(ParentBody(entities[parent]));
+        }
+    }
+
+    let camera = &snapshot.camera;
+
+    spawn_player(
+        &mut commands,
+        Transform::from_translation(Vec3::from(camera.player_translation))
+            .with_rotation(Quat::from_array(camera.player_rotation)),
+        Transform::from_translation(Vec3::from(camera.camera_translation))
+            .with_rotation(Quat::from_array(camera.camera_rotation)),
+    );
+
+    follow.entity = camera.follow.and_then(|i| entities.get(i).copied());
+    follow.is_active = camera.follow_active;
+    selected.entity = camera.selected.and_then(|i| entities.get(i).copied());
+
+    info!(
+        "Restored {} bodies at {} (TDB)",
+        entities.len(),
+        snapshot.time.current
+    );
+}
+
+/// Snapshots to take or restore at the end of the frame.
+#[derive(Resource, Debug, Default)]
+pub struct SnapshotRequests {
+    save: Option<PathBuf>,
+    load: Option<PathBuf>,
+
+    /// What happened to the last request, for the UI
+    pub status: Option<Result<String, String>>,
+}
+
+impl SnapshotRequests {
+    pub fn save(&mut self, path: PathBuf) {
+        self.save = Some(path);
+    }
+
+    pub fn load(&mut self, path: PathBuf) {
+        self.load = Some(path);
+    }
+}
+
+pub fn quick_save_system(keys: Res<ButtonInput<KeyCode>>, mut requests: ResMut<SnapshotRequests>) {
+    if keys.just_pressed(KeyCode::F5) {
+        requests.save(PathBuf::from(QUICK_SAVE_PATH));
+    }
+
+    if keys.just_pressed(KeyCode::F9) {
+        requests.load(PathBuf::from(QUICK_SAVE_PATH));
+    }
+}
+
+pub fn snapshot_system(world: &mut World) {
+    let (save, load) = {
+        let mut requests = world.resource_mut::<SnapshotRequests>();
+        (requests.save.take(), requests.load.take())
+    };
+
+    if let Some(path) = save {
+        let status = Snapshot::capture(world)
+            .and_then(|snapshot| snapshot.save(&path))
+            .map(|_| format!("Saved {}", path.display()));
+
+        match &status {
+            Ok(message) => info!("{}", message),
+            Err(e) => error!("Could not save the snapshot: {}", e),
+        }
+
+        world.resource_mut::<SnapshotRequests>().status = Some(status);
+    }
+
+    if let Some(path) = load {
+        let status = Snapshot::load(&path).map(|snapshot| {
+            snapshot.restore(world);
+            format!("Loaded {}", path.display())
+        });
+
+        if let Err(e) = &status {
+            error!("Could not load the snapshot: {}", e);
+        }
+
+        world.resource_mut::<SnapshotRequests>().status = Some(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{
+        data::{load_dataset, DATA_PATH},
+        physics::gravity_system,
+        settings::Integrator,
+    };
+
+    /// A world with just what the physics needs, with the bodies spawned in the given order.
+    fn world_with(
+        bodies: &[(Body, Vec3, Option<PhysicsState>)],
+        parameters: SimulationParameters,
+    ) -> World {
+        let mut world = World::new();
+
+        for (body, translation, state) in bodies {
+            let mut entity = world.spawn((body.clone(), Transform::from_translation(*translation)));
+
+            if let Some(state) = state {
+                entity.insert(*state);
+            }
+
+            // Different archetypes are stored, and so iterated, in a different order
+            if body
+                .metadata
+                .name
+                .as_ref()
+                .is_some_and(|n| n.len() % 2 == 0)
+            {
+                entity.insert(Planet {});
+            }
+        }
+
+        world.insert_resource(parameters);
+        world.insert_resource(SimulationTime::default());
+        world
+    }
+
+    fn step(world: &mut World, ticks: usize) {
+        for _ in 0..ticks {
+            world.run_system_once(gravity_system).unwrap();
+        }
+    }
+
+    /// Positions and velocities by name, as bits.
+    fn state(world: &mut World) -> Vec<(String, [u64; 6])> {
+        let mut state: Vec<_> = world
+            .query::<(&Body, &PhysicsState)>()
+            .iter(world)
+            .map(|(body, state)| {
+                let (p, v) = (state.position, state.velocity);
+
+                (
+                    body.metadata.name.clone().unwrap(),
+                    [p.x, p.y, p.z, v.x, v.y, v.z].map(f64::to_bits),
+                )
+            })
+            .collect();
+
+        state.sort_by(|a, b| a.0.cmp(&b.0));
+        state
+    }
+
+    #[test]
+    fn restores_bit_for_bit() {
+        for integrator in [Integrator::Euler, Integrator::Leapfrog, Integrator::RK4] {
+            let parameters = SimulationParameters {
+                integrator,
+                time_step: 3.0,
+                ..default()
+            };
+
+            let dataset = load_dataset(Path::new(DATA_PATH), &parameters).unwrap();
+            let mut bodies: Vec<(Body, Vec3, Option<PhysicsState>)> = Vec::new();
+
+            for body in &dataset.bodies {
+                bodies.push((body.clone(), body.data.position, None));
+
+                for satellite in body.satellites.iter().flatten() {
+                    let satellite = satellite.read().unwrap().clone();
+                    let position = satellite.data.position;
+                    bodies.push((satellite, position, None));
+                }
+            }
+
+            let mut original = world_with(&bodies, parameters);
+            step(&mut original, 50);
+
+            let text = ron::ser::to_string(&Snapshot::capture(&mut original).unwrap()).unwrap();
+            let snapshot = Snapshot::parse(&text).unwrap();
+
+            // Spawn them back the other way around
+            let restored_bodies: Vec<_> = snapshot
+                .bodies
+                .iter()
+                .rev()
+                .map(|b| {
+                    let (body, translation) = b.body(None);
+                    (body, translation, b.state())
+                })
+                .collect();
+
+            let mut restored = world_with(&restored_bodies, snapshot.parameters.clone());
+
+            assert_eq!(
+                state(&mut original),
+                state(&mut restored),
+                "{:?}",
+                integrator
+            );
+
+            step(&mut original, 100);
+            step(&mut restored, 100);
+
+            assert_eq!(
+                state(&mut original),
+                state(&mut restored),
+                "{:?}",
+                integrator
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        let mut world = world_with(&[], SimulationParameters::default());
+        let mut snapshot = Snapshot::capture(&mut world).unwrap();
+
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        let newer = Snapshot::parse(&ron::ser::to_string(&snapshot).unwrap()).unwrap_err();
+        assert!(newer.contains("not supported"), "{}", newer);
+
+        world.insert_resource(DateJump::default());
+        world
+            .resource_mut::<DateJump>()
+            .jump_to(SimulationTime::default().current);
+        assert!(Snapshot::capture(&mut world).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    data::DATA_EPOCH,
+    physics::substeps,
+    settings::{SimulationMode, SimulationParameters, UPDATE_FREQUENCY},
+    units::DAY,
+};
+
+/// TT - TAI, seconds
+const TT_MINUS_TAI: f64 = 32.184;
+
+/// Julian Date (UTC) from which each TAI - UTC offset (seconds) applies, the leap seconds since 1972.
+/// Before that UTC was not an integer number of seconds off TAI, we just use the first offset.
+const LEAP_SECONDS: [(f64, f64); 28] = [
+    (2441317.5, 10.0), // 1972-01-01
+    (2441499.5, 11.0), // 1972-07-01
+    (2441683.5, 12.0), // 1973-01-01
+    (2442048.5, 13.0), // 1974-01-01
+    (2442413.5, 14.0), // 1975-01-01
+    (2442778.5, 15.0), // 1976-01-01
+    (2443144.5, 16.0), // 1977-01-01
+    (2443509.5, 17.0), // 1978-01-01
+    (2443874.5, 18.0), // 1979-01-01
+    (2444239.5, 19.0), // 1980-01-01
+    (2444786.5, 20.0), // 1981-07-01
+    (2445151.5, 21.0), // 1982-07-01
+    (2445516.5, 22.0), // 1983-07-01
+    (2446247.5, 23.0), // 1985-07-01
+    (2447161.5, 24.0), // 1988-01-01
+    (2447892.5, 25.0), // 1990-01-01
+    (2448257.5, 26.0), // 1991-01-01
+    (2448804.5, 27.0), // 1992-07-01
+    (2449169.5, 28.0), // 1993-07-01
+    (2449534.5, 29.0), // 1994-07-01
+    (2450083.5, 30.0), // 1996-01-01
+    (2450630.5, 31.0), // 1997-07-01
+    (2451179.5, 32.0), // 1999-01-01
+    (2453736.5, 33.0), // 2006-01-01
+    (2454832.5, 34.0), // 2009-01-01
+    (2456109.5, 35.0), // 2012-07-01
+    (2457204.5, 36.0), // 2015-07-01
+    (2457754.5, 37.0), // 2017-01-01
+];
+
+/// A Julian Date split into a whole and a fractional part,
+/// so that we keep sub-millisecond precision even after adding many small steps.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct JulianDate {
+    whole: f64,
+
+    /// Always in [0, 1)
+    fraction: f64,
+}
+
+impl JulianDate {
+    pub fn new(julian_date: f64) -> Self {
+        Self::from_parts(julian_date.floor(), julian_date - julian_date.floor())
+    }
+
+    pub fn from_parts(whole: f64, fraction: f64) -> Self {
+        let whole_floor = whole.floor();
+        let fraction = fraction + (whole - whole_floor);
+        let carry = fraction.floor();
+
+        Self {
+            whole: whole_floor + carry,
+            fraction: fraction - carry,
+        }
+    }
+
+    /// Gregorian calendar date and time of day.
+    pub fn from_calendar(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: f64,
+    ) -> Self {
+        let (mut year, mut month) = (year as f64, month as f64);
+
+        if month <= 2.0 {
+            year -= 1.0;
+            month += 12.0;
+        }
+
+        let century = (year / 100.0).floor();
+        let gregorian = 2.0 - century + (century / 4.0).floor();
+
+        let whole = (365.25 * (year + 4716.0)).floor()
+            + (30.6001 * (month + 1.0)).floor()
+            + day as f64
+            + gregorian
+            - 1525.0;
+
+        let seconds = hour as f64 * 3600.0 + minute as f64 * 60.0 + second;
+
+        Self::from_parts(whole, 0.5 + seconds / DAY)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.whole + self.fraction
+    }
+
+    /// Days from `other` to us.
+    pub fn days_since(&self, other: &JulianDate) -> f64 {
+        (self.whole - other.whole) + (self.fraction - other.fraction)
+    }
+
+    pub fn add_days(&mut self, days: f64) {
+        *self = Self::from_parts(self.whole + days.trunc(), self.fraction + days.fract());
+    }
+
+    pub fn plus_seconds(&self, seconds: f64) -> Self {
+        let mut date = *self;
+        date.add_days(seconds / DAY);
+        date
+    }
+
+    /// Gregorian calendar (year, month, day, hour, minute, second).
+    pub fn to_calendar(&self) -> (i32, u32, u32, u32, u32, f64) {
+        let shifted = self.fraction + 0.5;
+        let z = self.whole + shifted.floor();
+        let f = shifted - shifted.floor();
+
+        let a = if z < 2299161.0 {
+            z
+        } else {
+            let alpha = ((z - 1867216.25) / 36524.25).floor();
+            z + 1.0 + alpha - (alpha / 4.0).floor()
+        };
+
+        let b = a + 1524.0;
+        let c = ((b - 122.1) / 365.25).floor();
+        let d = (365.25 * c).floor();
+        let e = ((b - d) / 30.6001).floor();
+
+        let day = b - d - (30.6001 * e).floor();
+        let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+        let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+
+        let seconds = f * DAY;
+        let hour = (seconds / 3600.0).floor();
+        let minute = ((seconds - hour * 3600.0) / 60.0).floor();
+        let second = seconds - hour * 3600.0 - minute * 60.0;
+
+        (
+            year as i32,
+            month as u32,
+            day as u32,
+            hour as u32,
+            minute as u32,
+            second,
+        )
+    }
+
+    /// Converts a TDB Julian Date into UTC.
+    pub fn tdb_to_utc(&self) -> Self {
+        let tt = self.plus_seconds(-tdb_minus_tt(self.value()));
+        let tai = tt.plus_seconds(-TT_MINUS_TAI);
+
+        tai.plus_seconds(-tai_minus_utc(tai.value()))
+    }
+
+    /// Converts a UTC Julian Date into TDB.
+    pub fn utc_to_tdb(&self) -> Self {
+        let tai = self.plus_seconds(tai_minus_utc(self.value()));
+        let tt = tai.plus_seconds(TT_MINUS_TAI);
+
+        tt.plus_seconds(tdb_minus_tt(tt.value()))
+    }
+
+    /// Parses a UTC date like "2030-06-01", "2030-06-01 12:00" or "2030-06-01T12:00:30",
+    /// or a Julian Date (TDB) like "JD2462653.5", into a TDB Julian Date.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+
+        if let Some(julian_date) = text.strip_prefix("JD") {
+            return julian_date
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|d| d.is_finite())
+                .map(Self::new);
+        }
+
+        let (date, time) = match text.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (text, None),
+        };
+
+        // A leading "-" is a year before 1 BC, do not mistake it for a separator
+        let (sign, date) = match date.strip_prefix('-') {
+            Some(date) => (-1, date),
+            None => (1, date),
+        };
+
+        let mut date = date.split('-');
+        let year = sign * date.next()?.parse::<i32>().ok()?;
+        let month = date.next()?.parse::<u32>().ok()?;
+        let day = date.next()?.parse::<u32>().ok()?;
+
+        if date.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+
+        let (hour, minute, second) = match time {
+            Some(time) => {
+                let mut time = time.split(':');
+                let hour = time.next()?.parse::<u32>().ok()?;
+                let minute = time
+                    .next()
+                    .map(|m| m.parse::<u32>())
+                    .unwrap_or(Ok(0))
+                    .ok()?;
+                let second = time
+                    .next()
+                    .map(|s| s.parse::<f64>())
+                    .unwrap_or(Ok(0.0))
+                    .ok()?;
+
+                if time.next().is_some()
+                    || hour > 23
+                    || minute > 59
+                    || !(0.0..61.0).contains(&second)
+                {
+                    return None;
+                }
+
+                (hour, minute, second)
+            }
+            None => (0, 0, 0.0),
+        };
+
+        Some(Self::from_calendar(year, month, day, hour, minute, second).utc_to_tdb())
+    }
+}
+
+impl fmt::Display for JulianDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day, hour, minute, second) = self.to_calendar();
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:06.3}",
+            year, month, day, hour, minute, second
+        )
+    }
+}
+
+/// TAI - UTC in seconds at the given Julian Date (UTC).
+fn tai_minus_utc(julian_date: f64) -> f64 {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(start, _)| julian_date >= *start)
+        .map(|(_, offset)| *offset)
+        .unwrap_or(LEAP_SECONDS[0].1)
+}
+
+/// TDB - TT in seconds, the periodic terms caused by the Earth's eccentric orbit, good to about 30 μs.
+fn tdb_minus_tt(julian_date: f64) -> f64 {
+    let mean_anomaly = (357.53 + 0.985_600_28 * (julian_date - 2451545.0)).to_radians();
+
+    0.001_657 * mean_anomaly.sin() + 0.000_014 * (2.0 * mean_anomaly).sin()
+}
+
+/// The simulated instant. Every time-dependent system should read the date from here.
+#[derive(Resource, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SimulationTime {
+    /// When the initial conditions in our data are valid, TDB
+    pub epoch: JulianDate,
+
+    /// TDB
+    pub current: JulianDate,
+}
+
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self::new(DATA_EPOCH)
+    }
+}
+
+impl SimulationTime {
+    pub fn new(epoch: f64) -> Self {
+        Self {
+            epoch: JulianDate::new(epoch),
+            current: JulianDate::new(epoch),
+        }
+    }
+
+    /// Julian Date (TDB), for when the precision of an f64 is enough
+    pub fn julian_date(&self) -> f64 {
+        self.current.value()
+    }
+
+    /// Days since the epoch
+    pub fn elapsed(&self) -> f64 {
+        self.current.days_since(&self.epoch)
+    }
+}
+
+/// Returns how many days a physics update moves the bodies by.
+pub fn tick_duration(parameters: &SimulationParameters) -> f64 {
+    match parameters.mode {
+        SimulationMode::NBody => {
+            let (count, dt) = substeps(parameters);
+            count as f64 * dt as f64
+        }
+        SimulationMode::PatchedConics => (parameters.time_step / UPDATE_FREQUENCY as f32) as f64,
+    }
+}
+
+/// Advances the clock by as much as the bodies were moved this physics update.
+pub fn simulation_time_system(
+    mut time: ResMut<SimulationTime>,
+    parameters: Res<SimulationParameters>,
+) {
+    time.current.add_days(tick_duration(&parameters));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TDB - UTC in seconds.
+    fn offset(utc: JulianDate) -> f64 {
+        utc.utc_to_tdb().days_since(&utc) * DAY
+    }
+
+    #[test]
+    fn converts_utc_to_tdb() {
+        // 37 leap seconds since 2017, plus TT - TAI, plus a couple of milliseconds at most
+        let utc = JulianDate::from_calendar(2025, 1, 1, 0, 0, 0.0);
+        assert!((offset(utc) - 69.184).abs() < 0.002, "{}", offset(utc));
+
+        // The last leap second
+        let before = JulianDate::from_calendar(2016, 12, 31, 12, 0, 0.0);
+        let after = JulianDate::from_calendar(2017, 1, 1, 12, 0, 0.0);
+        assert!((offset(after) - offset(before) - 1.0).abs() < 1e-4);
+
+        let back = utc.utc_to_tdb().tdb_to_utc();
+        assert!(back.days_since(&utc).abs() * DAY < 1e-6);
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(
+            JulianDate::parse("JD2460700.5"),
+            Some(JulianDate::new(2460700.5))
+        );
+        assert_eq!(
+            JulianDate::parse(" JD 2460700.25 "),
+            Some(JulianDate::new(2460700.25))
+        );
+
+        let noon = JulianDate::parse("2030-06-01T12:00").unwrap();
+        assert!((noon.tdb_to_utc().value() - 2462654.0).abs() * DAY < 1e-3);
+        assert_eq!(JulianDate::parse("2030-06-01 12:00:00"), Some(noon));
+        assert_eq!(JulianDate::parse("2030-06-01T12"), Some(noon));
+
+        let midnight = JulianDate::parse("2030-06-01").unwrap();
+        assert!((noon.days_since(&midnight) - 0.5).abs() * DAY < 1e-3);
+
+        // The start of the Julian period, in the proleptic Gregorian calendar
+        let start = JulianDate::parse("-4713-11-24T12:00").unwrap();
+        assert!(start.tdb_to_utc().value().abs() * DAY < 1e-3);
+
+        for text in [
+            "",
+            "tomorrow",
+            "2030-06",
+            "2030-13-01",
+            "2030-06-32",
+            "2030-06-01-02",
+            "2030-06-01T24:00",
+            "2030-06-01T12:60",
+            "2030-06-01T12:00:00:00",
+            "JD",
+            "JDx",
+            "JDinf",
+            "JDnan",
+        ] {
+            assert_eq!(JulianDate::parse(text), None, "{:?}", text);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::{prelude::*, transform};
+use bevy::prelude::*;
 
 use super::{
     body::Body,
@@ -73,35 +73,24 @@ pub fn precalculate_trajectory_system(
 
     let bodies_inner: Vec<_> = body_query
         .iter()
-        .map(|(t, p, e)| (t.clone(), p.clone(), e.clone()))
+        .map(|(t, p, e)| (*t, p.clone(), e))
         .collect();
 
     let mut bodies_outer = bodies_inner.clone();
+    let force_law = parameters.force_law();
 
-    for step in 0..trajectory.steps {
+    for _step in 0..trajectory.steps {
         for (index, (transform_outer, body_outer, entity_outer)) in
             bodies_outer.iter_mut().enumerate()
         {
-            body_outer.data.acceleration = Vec3::ZERO;
+            let start = transform_outer.translation;
 
-            let start = transform_outer.translation.clone();
-
-            for (transform_inner, body_inner, entity_inner) in &bodies_inner {
-                if entity_inner == entity_outer {
-                    continue;
-                }
-
-                let distance_squared = transform_inner
-                    .translation
-                    .distance_squared(transform_outer.translation)
-                    .max(parameters.softening_factor);
-
-                let force_direction =
-                    (transform_inner.translation - transform_outer.translation).normalize();
-
-                body_outer.data.acceleration += force_direction
-                    * (parameters.gravitational_constant * body_inner.data.mass / distance_squared);
-            }
+            body_outer.data.acceleration = compute_acceleration(
+                transform_outer.translation,
+                &bodies_inner,
+                force_law.as_ref(),
+                *entity_outer,
+            );
 
             body_outer.data.velocity += body_outer.data.acceleration * parameters.time_step;
             transform_outer.translation += body_outer.data.velocity * parameters.time_step;
@@ -156,18 +145,19 @@ fn live_projection_euler(
 ) {
     let mut bodies_outer: Vec<_> = body_query
         .iter()
-        .map(|(t, p, e)| (t.clone(), p.clone(), e.clone()))
+        .map(|(t, p, e)| (*t, p.clone(), e))
         .collect();
 
     let multiplier = parameters.time_step / UPDATE_FREQUENCY as f32 / parameters.updates_per_step;
     let mut accelerations = vec![Vec3::ZERO; bodies_outer.len()];
+    let force_law = parameters.force_law();
 
     for step in 0..trajectories.steps {
         for (i, (transform_outer, _body_outer, entity_outer)) in bodies_outer.iter().enumerate() {
             accelerations[i] = compute_acceleration(
                 transform_outer.translation,
                 &bodies_outer,
-                parameters,
+                force_law.as_ref(),
                 *entity_outer,
             );
         }
@@ -0,0 +1,305 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    body::{BodyData, BodyOrbitalElements},
+    settings::SimulationParameters,
+};
+
+/// Metres in an Astronomical Unit
+pub const ASTRONOMICAL_UNIT: f64 = 149_597_870_700.0;
+
+/// Seconds in a day
+pub const DAY: f64 = 86_400.0;
+
+/// Kilograms in a Solar Mass
+pub const SOLAR_MASS: f64 = 1.988_41e30;
+
+/// m^3 / kg * s^2
+pub const GRAVITATIONAL_CONSTANT: f64 = 6.674_30e-11;
+
+/// Metres in a kilometre
+pub const KILOMETER: f64 = 1000.0;
+
+/// Metres per second
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// Metres in a light-minute
+pub const LIGHT_MINUTE: f64 = SPEED_OF_LIGHT * 60.0;
+
+/// The units our data may be authored in.
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum UnitSystem {
+    /// Astronomical Units, Solar Mass and days
+    #[default]
+    Astronomical,
+    /// Metres, kilograms and seconds
+    SI,
+}
+
+impl UnitSystem {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "astronomical" => Some(Self::Astronomical),
+            "si" => Some(Self::SI),
+            _ => None,
+        }
+    }
+
+    /// Returns the unit of length (m), mass (kg) and time (s).
+    pub fn base_units(&self) -> (f64, f64, f64) {
+        match self {
+            UnitSystem::Astronomical => (ASTRONOMICAL_UNIT, SOLAR_MASS, DAY),
+            UnitSystem::SI => (1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Returns the gravitational constant in the given units of length (m), mass (kg) and time (s).
+pub fn gravitational_constant(length: f64, mass: f64, time: f64) -> f64 {
+    GRAVITATIONAL_CONSTANT * mass * time * time / (length * length * length)
+}
+
+impl SimulationParameters {
+    /// The unit of length (m), mass (kg) and time (s) the simulation runs in.
+    /// Lengths are in `unit_scale` Astronomical Units, masses in `mass_scale` Solar Mass, time is in days.
+    pub fn base_units(&self) -> (f64, f64, f64) {
+        (
+            ASTRONOMICAL_UNIT * self.unit_scale as f64,
+            SOLAR_MASS * self.mass_scale as f64,
+            DAY,
+        )
+    }
+
+    /// The gravitational constant in the units the simulation runs in.
+    pub fn derived_gravitational_constant(&self) -> f32 {
+        let (length, mass, time) = self.base_units();
+
+        gravitational_constant(length, mass, time) as f32
+    }
+}
+
+/// Converts values from one unit system to the units the simulation runs in.
+#[derive(Debug, Clone, Copy)]
+pub struct UnitConversion {
+    length: f64,
+    mass: f64,
+    time: f64,
+}
+
+impl UnitConversion {
+    pub fn new(from: UnitSystem, parameters: &SimulationParameters) -> Self {
+        let (from_length, from_mass, from_time) = from.base_units();
+        let (to_length, to_mass, to_time) = parameters.base_units();
+
+        Self {
+            length: from_length / to_length,
+            mass: from_mass / to_mass,
+            time: from_time / to_time,
+        }
+    }
+
+    pub fn length(&self, value: f32) -> f32 {
+        (value as f64 * self.length) as f32
+    }
+
+    pub fn position(&self, value: Vec3) -> Vec3 {
+        (value.as_dvec3() * self.length).as_vec3()
+    }
+
+    pub fn velocity(&self, value: Vec3) -> Vec3 {
+        value * (self.length / self.time) as f32
+    }
+
+    pub fn duration(&self, value: f32) -> f32 {
+        (value as f64 * self.time) as f32
+    }
+
+    pub fn mass(&self, value: f32) -> f32 {
+        (value as f64 * self.mass) as f32
+    }
+}
+
+impl BodyData {
+    /// Returns our data converted to the units the simulation runs in.
+    pub fn converted(&self, conversion: &UnitConversion) -> Self {
+        Self {
+            position: conversion.position(self.position),
+            velocity: conversion.velocity(self.velocity),
+            mass: conversion.mass(self.mass),
+            radius: conversion.length(self.radius),
+            orbital_elements: self.orbital_elements.map(|e| BodyOrbitalElements {
+                periapsis_distance: e.periapsis_distance.map(|q| conversion.length(q)),
+                semi_major_axis: e.semi_major_axis.map(|a| conversion.length(a)),
+                sidereal_orbit_period: e.sidereal_orbit_period.map(|p| conversion.duration(p)),
+                ..e
+            }),
+            ..*self
+        }
+    }
+}
+
+#[derive(Default, Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum DistanceUnit {
+    Kilometers,
+    #[default]
+    AstronomicalUnits,
+    LightMinutes,
+}
+
+impl DistanceUnit {
+    /// Metres in one unit
+    pub fn meters(&self) -> f64 {
+        match self {
+            DistanceUnit::Kilometers => KILOMETER,
+            DistanceUnit::AstronomicalUnits => ASTRONOMICAL_UNIT,
+            DistanceUnit::LightMinutes => LIGHT_MINUTE,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            DistanceUnit::Kilometers => "km",
+            DistanceUnit::AstronomicalUnits => "AU",
+            DistanceUnit::LightMinutes => "lmin",
+        }
+    }
+}
+
+/// The units values are displayed in on the UI.
+#[derive(Resource, Default, Debug)]
+pub struct DisplayUnits {
+    pub distance: DistanceUnit,
+}
+
+impl DisplayUnits {
+    /// Formats a distance given in the units the simulation runs in.
+    pub fn distance(&self, value: f32, parameters: &SimulationParameters) -> String {
+        let (length, _, _) = parameters.base_units();
+        let value = value as f64 * length / self.distance.meters();
+
+        match self.distance {
+            DistanceUnit::Kilometers => format!("{:.0} {}", value, self.distance.symbol()),
+            _ => format!("{:.6} {}", value, self.distance.symbol()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Earth on the 1st of January 2025, in SI and in the units of our data.
+    fn earth() -> (BodyData, BodyData) {
+        let position = Vec3::new(-0.178_683_44, 0.966_982_8, -5.109_424e-5);
+        let velocity = Vec3::new(-0.017_202_4, -0.003_163_6, 1.0e-7);
+        let mass = 3.003_489_4e-6;
+
+        let si = BodyData {
+            position: (position.as_dvec3() * ASTRONOMICAL_UNIT).as_vec3(),
+            velocity: (velocity.as_dvec3() * ASTRONOMICAL_UNIT / DAY).as_vec3(),
+            mass: (mass as f64 * SOLAR_MASS) as f32,
+            radius: 6.371e6,
+            ..default()
+        };
+
+        let astronomical = BodyData {
+            position,
+            velocity,
+            mass,
+            radius: (6.371e6 / ASTRONOMICAL_UNIT) as f32,
+            ..default()
+        };
+
+        (si, astronomical)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() <= b.length() * 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn unit_systems_round_trip() {
+        for (name, system) in [
+            ("astronomical", UnitSystem::Astronomical),
+            ("si", UnitSystem::SI),
+        ] {
+            assert_eq!(UnitSystem::parse(name), Some(system));
+        }
+
+        assert_eq!(UnitSystem::parse("imperial"), None);
+
+        let (si, astronomical) = earth();
+        let parameters = SimulationParameters::default();
+
+        // Our data is authored in the units the simulation runs in by default
+        let identity =
+            astronomical.converted(&UnitConversion::new(UnitSystem::Astronomical, &parameters));
+        assert_eq!(identity.position, astronomical.position);
+        assert_eq!(identity.velocity, astronomical.velocity);
+
+        let converted = si.converted(&UnitConversion::new(UnitSystem::SI, &parameters));
+        assert_close(converted.position, astronomical.position);
+        assert_close(converted.velocity, astronomical.velocity);
+        assert!((converted.mass / astronomical.mass - 1.0).abs() < 1e-6);
+        assert!((converted.radius / astronomical.radius - 1.0).abs() < 1e-6);
+
+        // Scaled units divide every length, and so velocity, by the scale
+        let scaled = SimulationParameters {
+            unit_scale: 2.0,
+            mass_scale: 4.0,
+            ..default()
+        };
+        let converted = si.converted(&UnitConversion::new(UnitSystem::SI, &scaled));
+        assert_close(converted.position * 2.0, astronomical.position);
+        assert_close(converted.velocity * 2.0, astronomical.velocity);
+        assert!((converted.mass * 4.0 / astronomical.mass - 1.0).abs() < 1e-6);
+
+        assert!((gravitational_constant(1.0, 1.0, 1.0) - GRAVITATIONAL_CONSTANT).abs() < 1e-20);
+        assert!((parameters.derived_gravitational_constant() - 2.959_122e-4).abs() < 1e-10);
+    }
+
+    #[test]
+    fn distance_units_round_trip() {
+        let parameters = SimulationParameters::default();
+        let units = |distance| DisplayUnits { distance };
+
+        for unit in [
+            DistanceUnit::Kilometers,
+            DistanceUnit::AstronomicalUnits,
+            DistanceUnit::LightMinutes,
+        ] {
+            // A distance in the unit, shown in it, reads back as the same number
+            let value = 12.0;
+            let simulation = (value * unit.meters() / ASTRONOMICAL_UNIT) as f32;
+            let text = units(unit).distance(simulation, &parameters);
+            let (number, symbol) = text.split_once(' ').unwrap();
+
+            assert_eq!(symbol, unit.symbol());
+            assert!(
+                (number.parse::<f64>().unwrap() - value).abs() < 1e-3,
+                "{}",
+                text
+            );
+        }
+
+        assert_eq!(
+            units(DistanceUnit::Kilometers).distance(1.0, &parameters),
+            "149597871 km"
+        );
+        assert_eq!(
+            units(DistanceUnit::LightMinutes).distance(1.0, &parameters),
+            "8.316746 lmin"
+        );
+
+        // Shown in AU, whatever the simulation runs in
+        let scaled = SimulationParameters {
+            unit_scale: 2.0,
+            ..default()
+        };
+        assert_eq!(
+            units(DistanceUnit::AstronomicalUnits).distance(0.5, &scaled),
+            "1.000000 AU"
+        );
+    }
+}
@@ -8,7 +8,7 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use super::body::Body;
+use super::body::{Body, Satellites};
 
 /// Deserialize our JSON data into proper Vec3 types.
 /// Note: we are swapping "z" and "y" because they are flipped in our JSON data.
@@ -53,6 +53,11 @@ where
     deserializer.deserialize_map(Vec3Visitor)
 }
 
+/// Placeholder for state vectors missing from our JSON data.
+pub fn undefined_vec3() -> Vec3 {
+    Vec3::NAN
+}
+
 pub fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
 where
     D: Deserializer<'de>,
@@ -64,9 +69,7 @@ where
         .unwrap_or_else(|| super::body::BodyMetadata::default().color))
 }
 
-pub fn deserialize_satellites<'de, D>(
-    deserializer: D,
-) -> Result<Option<Vec<Arc<RwLock<Body>>>>, D::Error>
+pub fn deserialize_satellites<'de, D>(deserializer: D) -> Result<Option<Satellites>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -76,7 +79,7 @@ where
     struct SatellitesVisitor;
 
     impl<'de> Visitor<'de> for SatellitesVisitor {
-        type Value = Option<Vec<Arc<RwLock<Body>>>>;
+        type Value = Option<Satellites>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
             formatter.write_str("an optional array of satellites")
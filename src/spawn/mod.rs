@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
-use crate::simulation::body::{Body, BodyData, BodyMetadata, Planet};
+use crate::simulation::{
+    body::{Body, BodyData, BodyMetadata, Planet, Spacecraft},
+    settings::{SimulationMode, SimulationParameters},
+};
 pub struct SpawnBodyPlugin;
 
 impl Plugin for SpawnBodyPlugin {
@@ -30,7 +33,7 @@ pub struct DeleteSpawnBodyPreviewEvent {}
 #[derive(Event, Debug, Clone)]
 pub struct SpawnBodyEvent {}
 
-#[derive(Resource, Debug, Clone)]
+#[derive(Resource, Debug, Clone, Default)]
 pub struct SpawnBodyPreview(pub Option<SpawnBodyPreviewData>);
 
 impl SpawnBodyPreview {
@@ -47,11 +50,9 @@ pub struct SpawnBodyPreviewData {
 }
 
 pub fn create_preview_system(
-    mut commands: Commands,
-    mut gizmos: Gizmos,
     mut preview: ResMut<SpawnBodyPreview>,
     mut events: EventReader<CreateSpawnBodyPreviewEvent>,
-    mut camera: Query<&mut Transform, With<Camera>>,
+    camera: Query<&Transform, With<Camera>>,
 ) {
     if events.is_empty() {
         return;
@@ -68,12 +69,11 @@ pub fn create_preview_system(
 
 pub fn spawn_system(
     mut commands: Commands,
-    mut camera: Query<&mut Transform, With<Camera>>,
+    camera: Query<&Transform, With<Camera>>,
     mut events: EventReader<SpawnBodyEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-
-    c: Query<&Projection>,
+    parameters: Res<SimulationParameters>,
 ) {
     if events.is_empty() {
         return;
@@ -104,19 +104,22 @@ pub fn spawn_system(
             ..default()
         })),
         Transform::from_translation(camera.single().translation + camera.single().forward() * 0.01),
-        Planet {},
     ));
+
+    // The patched-conic approximation only makes sense for spacecraft
+    match parameters.mode {
+        SimulationMode::NBody => entity.insert(Planet {}),
+        SimulationMode::PatchedConics => entity.insert(Spacecraft {}),
+    };
 }
 
 /// If the player has opted to specify custom coordinates as the target for the spawned body, visualize the body's direction vector.
 pub fn render_preview_system(
-    mut commands: Commands,
     mut gizmos: Gizmos,
-    mut preview: Res<SpawnBodyPreview>,
-    mut camera: Query<&mut Transform, With<Camera>>,
-    c: Query<&Projection>,
+    preview: Res<SpawnBodyPreview>,
+    camera: Query<&Transform, With<Camera>>,
 ) {
-    if let Some(p) = &preview.0 {
+    if preview.0.is_some() {
         // let start = camera.single().rotation.to_euler(EulerRot::YZX);
         // let end = camera.single().translation - p.to * 0.1;
 
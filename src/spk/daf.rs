@@ -0,0 +1,208 @@
+//! Reads NAIF's Double precision Array Files, the container SPK kernels are stored in.
+//!
+//! A DAF is a sequence of 1024 byte records. The first is the file record, which points to a chain of
+//! summary records. Each summary describes one array of doubles, addressed in words of 8 bytes from
+//! the start of the file, counting from 1.
+
+use std::{collections::HashSet, fs, path::Path};
+
+const RECORD_LENGTH: usize = 1024;
+const WORD_LENGTH: usize = 8;
+
+/// Describes one array in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DafSummary {
+    pub name: String,
+    pub doubles: Vec<f64>,
+
+    /// The last two are the initial and final address of the array
+    pub integers: Vec<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Daf {
+    bytes: Vec<u8>,
+    little_endian: bool,
+
+    /// E.g. "DAF/SPK"
+    pub id_word: String,
+    pub internal_name: String,
+
+    /// Number of doubles in a summary
+    pub nd: usize,
+
+    /// Number of integers in a summary
+    pub ni: usize,
+
+    /// Record number of the first summary record
+    forward: usize,
+}
+
+impl Daf {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+        Self::parse(bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, String> {
+        if bytes.len() < RECORD_LENGTH {
+            return Err("Too short for a DAF".to_string());
+        }
+
+        let text = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&bytes[range])
+                .trim_end_matches(['\0', ' '])
+                .to_string()
+        };
+
+        let id_word = text(0..8);
+
+        if !id_word.starts_with("DAF/") && id_word != "NAIF/DAF" {
+            return Err(format!("Not a DAF, the file starts with \"{}\"", id_word));
+        }
+
+        let little_endian = match text(88..96).as_str() {
+            "LTL-IEEE" => true,
+            "BIG-IEEE" => false,
+            // Old files do not say, guess from whether ND makes sense
+            _ => (1..=124).contains(&i32::from_le_bytes(bytes[8..12].try_into().unwrap())),
+        };
+
+        let integer = |offset: usize| {
+            let word: [u8; 4] = bytes[offset..offset + 4].try_into().unwrap();
+            match little_endian {
+                true => i32::from_le_bytes(word),
+                false => i32::from_be_bytes(word),
+            }
+        };
+
+        let (nd, ni, forward) = (integer(8), integer(12), integer(76));
+
+        if nd < 0 || ni < 2 || forward < 1 {
+            return Err(format!(
+                "Invalid file record: ND {}, NI {}, FWARD {}",
+                nd, ni, forward
+            ));
+        }
+
+        Ok(Self {
+            id_word,
+            internal_name: text(16..76),
+            nd: nd as usize,
+            ni: ni as usize,
+            forward: forward as usize,
+            little_endian,
+            bytes,
+        })
+    }
+
+    fn double_at(&self, offset: usize) -> Result<f64, String> {
+        let word: [u8; 8] = self
+            .bytes
+            .get(offset..offset + WORD_LENGTH)
+            .ok_or(format!("Offset {} is past the end of the file", offset))?
+            .try_into()
+            .unwrap();
+
+        Ok(match self.little_endian {
+            true => f64::from_le_bytes(word),
+            false => f64::from_be_bytes(word),
+        })
+    }
+
+    fn integer_at(&self, offset: usize) -> Result<i32, String> {
+        let word: [u8; 4] = self
+            .bytes
+            .get(offset..offset + 4)
+            .ok_or(format!("Offset {} is past the end of the file", offset))?
+            .try_into()
+            .unwrap();
+
+        Ok(match self.little_endian {
+            true => i32::from_le_bytes(word),
+            false => i32::from_be_bytes(word),
+        })
+    }
+
+    /// Size of a summary, in words.
+    fn summary_size(&self) -> usize {
+        self.nd + self.ni.div_ceil(2)
+    }
+
+    /// Every summary in the file, in the order they were written.
+    pub fn summaries(&self) -> Result<Vec<DafSummary>, String> {
+        let mut summaries = Vec::new();
+        let mut record = self.forward;
+        let mut visited = HashSet::new();
+
+        // Each summary record is followed by a record with the names of its arrays
+        while record != 0 {
+            if !visited.insert(record) {
+                return Err(format!("The summary records loop back to {}", record));
+            }
+
+            let start = (record - 1).saturating_mul(RECORD_LENGTH);
+
+            if start >= self.bytes.len() {
+                return Err(format!(
+                    "Summary record {} is past the end of the file",
+                    record
+                ));
+            }
+
+            let names = start + RECORD_LENGTH;
+
+            let next = self.double_at(start)? as usize;
+            let count = self.double_at(start + 2 * WORD_LENGTH)? as usize;
+
+            if count.saturating_mul(self.summary_size()) > RECORD_LENGTH / WORD_LENGTH - 3 {
+                return Err(format!(
+                    "Summary record {} claims {} summaries, more than fit",
+                    record, count
+                ));
+            }
+
+            for i in 0..count {
+                let offset = start + (3 + i * self.summary_size()) * WORD_LENGTH;
+
+                let doubles = (0..self.nd)
+                    .map(|j| self.double_at(offset + j * WORD_LENGTH))
+                    .collect::<Result<Vec<f64>, String>>()?;
+
+                let integers = (0..self.ni)
+                    .map(|j| self.integer_at(offset + self.nd * WORD_LENGTH + j * 4))
+                    .collect::<Result<Vec<i32>, String>>()?;
+
+                let name_length = self.summary_size() * WORD_LENGTH;
+                let name = self
+                    .bytes
+                    .get(names + i * name_length..names + (i + 1) * name_length)
+                    .map(|name| String::from_utf8_lossy(name).trim_end().to_string())
+                    .unwrap_or_default();
+
+                summaries.push(DafSummary {
+                    name,
+                    doubles,
+                    integers,
+                });
+            }
+
+            record = next;
+        }
+
+        Ok(summaries)
+    }
+
+    /// The doubles from `start` to `end`, both inclusive.
+    pub fn read_doubles(&self, start: usize, end: usize) -> Result<Vec<f64>, String> {
+        if start == 0 || end < start {
+            return Err(format!("Invalid address range {}..={}", start, end));
+        }
+
+        (start..=end)
+            .map(|address| self.double_at((address - 1) * WORD_LENGTH))
+            .collect()
+    }
+}
@@ -0,0 +1,808 @@
+//! Reads JPL ephemerides from SPICE SPK kernels, like DE440s, so the bodies can be placed at any epoch
+//! the kernel covers, or kept on it as time goes on, without a network connection.
+//!
+//! Only Chebyshev segments are supported: type 2, which stores position only, and type 3,
+//! which stores velocity as well. Either may be in the J2000 or the ECLIPJ2000 frame.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{math::DVec3, prelude::*};
+
+use crate::simulation::{
+    body::Body,
+    data::Dataset,
+    orbit::{ecliptic_to_world, propagate_kepler, world_to_ecliptic},
+    physics::PhysicsState,
+    settings::SimulationParameters,
+    time::{tick_duration, JulianDate, SimulationTime},
+    units::{gravitational_constant, UnitSystem, DAY, KILOMETER, SOLAR_MASS},
+};
+
+pub mod daf;
+
+use daf::Daf;
+
+/// Julian Date (TDB) of J2000, SPK epochs are seconds of TDB past it
+const J2000: f64 = 2451545.0;
+
+/// Obliquity of the ecliptic at J2000, as used for ECLIPJ2000 and by Horizons
+const OBLIQUITY_J2000: f64 = 84381.448 / 3600.0;
+
+/// NAIF frame codes
+const FRAME_J2000: i32 = 1;
+const FRAME_ECLIPJ2000: i32 = 17;
+
+/// NAIF ID of the solar system barycenter
+pub const SOLAR_SYSTEM_BARYCENTER: i32 = 0;
+
+/// Position and velocity in the ecliptic frame of J2000.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpkState {
+    /// km
+    pub position: DVec3,
+
+    /// km/s
+    pub velocity: DVec3,
+}
+
+impl std::ops::Add for SpkState {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            position: self.position + other.position,
+            velocity: self.velocity + other.velocity,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpkSegment {
+    pub name: String,
+    pub target: i32,
+    pub center: i32,
+    pub frame: i32,
+    pub data_type: i32,
+
+    /// Coverage, seconds past J2000 (TDB)
+    pub start: f64,
+    pub end: f64,
+
+    /// Start of the first record and length of every record, seconds
+    init: f64,
+    interval: f64,
+
+    /// Doubles per record
+    record_size: usize,
+    records: usize,
+
+    /// Address of the first double of the segment
+    address: usize,
+}
+
+impl SpkSegment {
+    pub fn covers(&self, epoch: f64) -> bool {
+        (self.start..=self.end).contains(&epoch)
+    }
+
+    /// Components stored per record, 3 for positions only, 6 with velocities.
+    fn components(&self) -> usize {
+        match self.data_type {
+            2 => 3,
+            _ => 6,
+        }
+    }
+}
+
+/// An SPK kernel, with the arrays read as needed.
+#[derive(Debug, Clone)]
+pub struct SpkKernel {
+    daf: Daf,
+    pub segments: Vec<SpkSegment>,
+}
+
+impl SpkKernel {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Self::from_daf(Daf::open(path)?).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(bytes: Vec<u8>) -> Result<Self, String> {
+        Self::from_daf(Daf::parse(bytes)?)
+    }
+
+    fn from_daf(daf: Daf) -> Result<Self, String> {
+        if daf.id_word != "DAF/SPK" && daf.id_word != "NAIF/DAF" {
+            return Err(format!("Not an SPK kernel, but a {}", daf.id_word));
+        }
+
+        if daf.nd != 2 || daf.ni != 6 {
+            return Err(format!(
+                "Expected summaries of 2 doubles and 6 integers, not {} and {}",
+                daf.nd, daf.ni
+            ));
+        }
+
+        let mut segments = Vec::new();
+
+        for summary in daf.summaries()? {
+            let [target, center, frame, data_type, start, end] = summary.integers[..] else {
+                unreachable!();
+            };
+
+            if data_type != 2 && data_type != 3 {
+                warn!(
+                    "Skipping segment \"{}\" of {}, type {} is not supported",
+                    summary.name, target, data_type
+                );
+                continue;
+            }
+
+            // The records, then a directory of 4 doubles at the end of the array
+            let addresses = usize::try_from(start)
+                .ok()
+                .zip(usize::try_from(end).ok())
+                .filter(|(start, end)| {
+                    *start > 0 && end.checked_sub(3).is_some_and(|d| d > *start)
+                });
+
+            let Some((address, end)) = addresses else {
+                return Err(format!(
+                    "Segment \"{}\" has invalid addresses {}..={}",
+                    summary.name, start, end
+                ));
+            };
+
+            let directory = daf.read_doubles(end - 3, end)?;
+
+            let segment = SpkSegment {
+                name: summary.name,
+                target,
+                center,
+                frame,
+                data_type,
+                start: summary.doubles[0],
+                end: summary.doubles[1],
+                init: directory[0],
+                interval: directory[1],
+                record_size: directory[2] as usize,
+                records: directory[3] as usize,
+                address,
+            };
+
+            let coefficients = (segment.record_size.saturating_sub(2)) / segment.components();
+
+            if !segment.interval.is_finite()
+                || segment.interval <= 0.0
+                || segment.records == 0
+                || coefficients == 0
+                || segment.records.saturating_mul(segment.record_size) > end - 3 - address
+            {
+                return Err(format!(
+                    "Segment \"{}\" has an invalid directory",
+                    segment.name
+                ));
+            }
+
+            segments.push(segment);
+        }
+
+        Ok(Self { daf, segments })
+    }
+
+    /// The segment for `target` at `epoch`, later segments take precedence over earlier ones.
+    pub fn segment(&self, target: i32, epoch: f64) -> Option<&SpkSegment> {
+        self.segments
+            .iter()
+            .rev()
+            .find(|s| s.target == target && s.covers(epoch))
+    }
+
+    /// State of `target` relative to the center of its segment, at `epoch` seconds past J2000 (TDB).
+    pub fn relative_state(&self, target: i32, epoch: f64) -> Result<(i32, SpkState), String> {
+        let segment = self.segment(target, epoch).ok_or(format!(
+            "No segment for {} at {} s past J2000",
+            target, epoch
+        ))?;
+
+        let index = (((epoch - segment.init) / segment.interval).floor().max(0.0) as usize)
+            .min(segment.records - 1);
+
+        let address = segment.address + index * segment.record_size;
+        let record = self
+            .daf
+            .read_doubles(address, address + segment.record_size - 1)?;
+
+        let (middle, radius) = (record[0], record[1]);
+        let s = (epoch - middle) / radius;
+
+        let coefficients = (segment.record_size - 2) / segment.components();
+        let component = |i: usize| {
+            let start = 2 + i * coefficients;
+            chebyshev(&record[start..start + coefficients], s)
+        };
+
+        let (x, y, z) = (component(0), component(1), component(2));
+
+        let state = SpkState {
+            position: DVec3::new(x.0, y.0, z.0),
+            velocity: match segment.data_type {
+                // Scale the derivative with respect to s to seconds
+                2 => DVec3::new(x.1, y.1, z.1) / radius,
+                _ => DVec3::new(component(3).0, component(4).0, component(5).0),
+            },
+        };
+
+        let state = match segment.frame {
+            FRAME_ECLIPJ2000 => state,
+            FRAME_J2000 => SpkState {
+                position: equatorial_to_ecliptic(state.position),
+                velocity: equatorial_to_ecliptic(state.velocity),
+            },
+            frame => {
+                return Err(format!(
+                    "Segment \"{}\" is in frame {}, only J2000 and ECLIPJ2000 are supported",
+                    segment.name, frame
+                ))
+            }
+        };
+
+        Ok((segment.center, state))
+    }
+
+    /// State of `target` relative to the solar system barycenter, following the centers of the segments.
+    pub fn barycentric_state(&self, target: i32, julian_date: f64) -> Result<SpkState, String> {
+        let epoch = (julian_date - J2000) * DAY;
+
+        let mut state = SpkState::default();
+        let mut body = target;
+
+        // The chain is short, e.g. Moon -> Earth-Moon barycenter -> solar system barycenter
+        for _ in 0..16 {
+            if body == SOLAR_SYSTEM_BARYCENTER {
+                return Ok(state);
+            }
+
+            let (center, relative) = self.relative_state(body, epoch)?;
+
+            state = state + relative;
+            body = center;
+        }
+
+        Err(format!(
+            "The centers of {} do not lead to the barycenter",
+            target
+        ))
+    }
+}
+
+/// Value and derivative of a Chebyshev series at `s`, in [-1, 1].
+fn chebyshev(coefficients: &[f64], s: f64) -> (f64, f64) {
+    // T(n-2), T(n-1) and their derivatives
+    let (mut t0, mut t1) = (1.0, s);
+    let (mut d0, mut d1) = (0.0, 1.0);
+
+    let mut value = coefficients[0];
+    let mut derivative = 0.0;
+
+    if let Some(c) = coefficients.get(1) {
+        value += c * t1;
+        derivative += c * d1;
+    }
+
+    for c in coefficients.iter().skip(2) {
+        let t = 2.0 * s * t1 - t0;
+        let d = 2.0 * t1 + 2.0 * s * d1 - d0;
+
+        value += c * t;
+        derivative += c * d;
+
+        (t0, t1) = (t1, t);
+        (d0, d1) = (d1, d);
+    }
+
+    (value, derivative)
+}
+
+fn equatorial_to_ecliptic(v: DVec3) -> DVec3 {
+    let (sin, cos) = OBLIQUITY_J2000.to_radians().sin_cos();
+
+    DVec3::new(v.x, cos * v.y + sin * v.z, -sin * v.y + cos * v.z)
+}
+
+/// A kernel to place the bodies with on startup, instead of the state vectors in our data.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct KernelSeed {
+    pub kernel: Option<PathBuf>,
+
+    /// Defaults to the epoch of our data
+    pub epoch: Option<JulianDate>,
+
+    /// Keep the bodies the kernel covers on it afterwards, instead of integrating them
+    pub on_rails: bool,
+}
+
+/// The kernel that bodies on rails follow, if any.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Rails {
+    pub kernel: Option<SpkKernel>,
+}
+
+/// Opens the kernel of the `KernelSeed` to keep the bodies on, if it asks for that.
+pub fn load_rails_system(seed: Res<KernelSeed>, mut rails: ResMut<Rails>) {
+    rails.kernel = None;
+
+    let (true, Some(path)) = (seed.on_rails, &seed.kernel) else {
+        return;
+    };
+
+    match SpkKernel::open(path) {
+        Ok(kernel) => {
+            info!(
+                "The bodies in {} follow it instead of being integrated",
+                path.display()
+            );
+            rails.kernel = Some(kernel);
+        }
+        Err(e) => error!("Could not load the kernel, integrating every body: {}", e),
+    }
+}
+
+/// Puts the bodies the kernel covers where it has them at the end of this physics update,
+/// over whatever they were integrated to. They still pull on the other bodies, which are integrated as usual,
+/// as are the ones without a NAIF ID or past the coverage of the kernel.
+pub fn rails_system(
+    rails: Res<Rails>,
+    time: Res<SimulationTime>,
+    parameters: Res<SimulationParameters>,
+    mut bodies: Query<(&mut Transform, &mut Body, &mut PhysicsState)>,
+) {
+    let Some(kernel) = &rails.kernel else {
+        return;
+    };
+
+    // The clock only moves on after us
+    let mut date = time.current;
+    date.add_days(tick_duration(&parameters));
+
+    let (length, _, duration) = parameters.base_units();
+
+    // Like `ecliptic_to_world`, in the double precision the physics runs in
+    let world = |v: DVec3| DVec3::new(v.x, v.z, v.y);
+
+    for (mut transform, mut body, mut state) in &mut bodies {
+        let Some(spk) = body
+            .metadata
+            .id
+            .and_then(|id| kernel.barycentric_state(id as i32, date.value()).ok())
+        else {
+            continue;
+        };
+
+        *state = PhysicsState {
+            position: world(spk.position * KILOMETER / length),
+            velocity: world(spk.velocity * KILOMETER * duration / length),
+        };
+
+        transform.translation = state.position.as_vec3();
+        body.data.velocity = state.velocity.as_vec3();
+    }
+}
+
+/// Places the bodies of `dataset`, as authored in `units`, at their state in `kernel` at `julian_date`.
+/// Bodies the kernel does not cover are moved along a Keplerian orbit around their parent instead.
+/// Returns the names of those.
+pub fn seed_dataset(
+    dataset: &mut Dataset,
+    kernel: &SpkKernel,
+    julian_date: f64,
+    units: UnitSystem,
+) -> Vec<String> {
+    let (length, mass, time) = units.base_units();
+    let g = gravitational_constant(length, mass, time);
+    let elapsed = (julian_date - dataset.epoch) * DAY / time;
+
+    let state = |id: Option<u32>| {
+        let state = kernel.barycentric_state(id? as i32, julian_date).ok()?;
+
+        Some((
+            ecliptic_to_world(state.position * KILOMETER / length),
+            ecliptic_to_world(state.velocity * KILOMETER * time / length),
+        ))
+    };
+
+    // Bodies without a parent orbit the Sun
+    let sun = dataset
+        .bodies
+        .iter()
+        .find(|b| b.metadata.id == Some(10))
+        .map(|b| (b.data.position, b.data.velocity, b.data.mass))
+        .unwrap_or((Vec3::ZERO, Vec3::ZERO, (SOLAR_MASS / mass) as f32));
+
+    let mut propagated = Vec::new();
+
+    let mut seed = |body: &mut Body, parent: Option<&Body>| {
+        if let Some((position, velocity)) = state(body.metadata.id) {
+            body.data.position = position;
+            body.data.velocity = velocity;
+            body.metadata.relative_to_parent = false;
+            return;
+        }
+
+        propagated.push(
+            body.metadata
+                .name
+                .clone()
+                .unwrap_or("<unknown>".to_string()),
+        );
+
+        // Only orbital elements, they are moved to the new epoch when they are resolved
+        if !body.data.has_state_vectors() {
+            if let Some(elements) = body.data.orbital_elements.as_mut() {
+                elements.epoch.get_or_insert(dataset.epoch);
+            }
+            return;
+        }
+
+        // Our data is heliocentric, unless given relative to the parent
+        let (parent_position, parent_velocity, parent_mass) = match parent {
+            Some(p) => (p.data.position, p.data.velocity, p.data.mass),
+            None => sun,
+        };
+
+        let (position, velocity) = match body.metadata.relative_to_parent {
+            true => (body.data.position, body.data.velocity),
+            false => (
+                body.data.position - parent_position,
+                body.data.velocity - parent_velocity,
+            ),
+        };
+
+        let (position, velocity) = propagate_kepler(
+            world_to_ecliptic(position),
+            world_to_ecliptic(velocity),
+            g * (parent_mass + body.data.mass) as f64,
+            elapsed,
+        );
+
+        body.data.position = ecliptic_to_world(position);
+        body.data.velocity = ecliptic_to_world(velocity);
+        body.metadata.relative_to_parent = true;
+    };
+
+    for body in &mut dataset.bodies {
+        let satellites: Vec<_> = body.satellites.iter().flatten().map(Arc::clone).collect();
+
+        // The satellites are relative to where their planet was, before we move it
+        for satellite in &satellites {
+            seed(&mut satellite.write().unwrap(), Some(body));
+        }
+
+        seed(body, None);
+    }
+
+    dataset.epoch = julian_date;
+
+    propagated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "data/spk/fixture.bsp";
+
+    /// Laid out like DE440s, fitted to our data around its epoch, see `make_ephemeris.py`
+    const EPHEMERIS: &str = "data/spk/ephemeris.bsp";
+
+    /// The epoch of our data, where the fixture starts, in seconds past J2000
+    const ET0: f64 = 788961600.0;
+
+    fn fixture() -> SpkKernel {
+        SpkKernel::open(Path::new(FIXTURE)).unwrap()
+    }
+
+    fn julian_date(epoch: f64) -> f64 {
+        J2000 + epoch / DAY
+    }
+
+    fn assert_close(a: DVec3, b: DVec3) {
+        assert!((a - b).length() <= b.length() * 1e-12, "{} != {}", a, b);
+    }
+
+    /// Evaluates the coefficients of a record of the fixture the long way.
+    fn series(coefficients: &[f64], s: f64) -> (f64, f64) {
+        let t = [1.0, s, 2.0 * s * s - 1.0];
+        let d = [0.0, 1.0, 4.0 * s];
+
+        coefficients
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(v, dv), (i, c)| (v + c * t[i], dv + c * d[i]))
+    }
+
+    #[test]
+    fn reads_summaries() {
+        let kernel = fixture();
+
+        let segments: Vec<_> = kernel
+            .segments
+            .iter()
+            .map(|s| (s.name.as_str(), s.target, s.center, s.frame, s.data_type))
+            .collect();
+
+        assert_eq!(
+            segments,
+            [
+                ("EMB", 3, 0, 1, 2),
+                ("EARTH", 399, 3, 17, 3),
+                ("SUN", 10, 0, 1, 2),
+                ("EARTH OVERRIDE", 399, 3, 17, 2),
+            ]
+        );
+
+        assert_eq!(kernel.segments[0].start, ET0);
+        assert_eq!(kernel.segments[0].end, ET0 + 4.0 * DAY);
+        assert_eq!(kernel.segments[0].records, 2);
+    }
+
+    #[test]
+    fn evaluates_type_2() {
+        let kernel = fixture();
+
+        // Half way through the first day, in the second record
+        for (epoch, middle, record) in [
+            (ET0 + 0.5 * DAY, ET0 + DAY, 0),
+            (ET0 + 3.25 * DAY, ET0 + 3.0 * DAY, 1),
+        ] {
+            let coefficients = [
+                [
+                    [-2.6e7, 1.3e6, 2.0e3],
+                    [1.3e8, 2.5e6, -1.0e3],
+                    [5.6e7, 1.1e6, 500.0],
+                ],
+                [
+                    [-2.5e7, 1.3e6, 2.0e3],
+                    [1.31e8, 2.4e6, -1.0e3],
+                    [5.65e7, 1.0e6, 500.0],
+                ],
+            ][record];
+
+            let s = (epoch - middle) / DAY;
+            let (x, y, z) = (
+                series(&coefficients[0], s),
+                series(&coefficients[1], s),
+                series(&coefficients[2], s),
+            );
+
+            let (center, state) = kernel.relative_state(3, epoch).unwrap();
+
+            assert_eq!(center, 0);
+            assert_close(
+                state.position,
+                equatorial_to_ecliptic(DVec3::new(x.0, y.0, z.0)),
+            );
+            assert_close(
+                state.velocity,
+                equatorial_to_ecliptic(DVec3::new(x.1, y.1, z.1) / DAY),
+            );
+        }
+    }
+
+    #[test]
+    fn evaluates_type_3() {
+        let kernel = fixture();
+
+        let s = -0.5;
+        let (center, state) = kernel.relative_state(399, ET0 + DAY).unwrap();
+
+        assert_eq!(center, 3);
+        assert_close(
+            state.position,
+            DVec3::new(4000.0 + 100.0 * s, -2000.0 + 50.0 * s, 10.0 + s),
+        );
+        assert_close(state.velocity, DVec3::new(0.01 + 0.001 * s, 0.02, 0.0));
+    }
+
+    #[test]
+    fn later_segments_take_precedence() {
+        let (_, state) = fixture().relative_state(399, ET0 + 3.0 * DAY).unwrap();
+
+        assert_eq!(state.position, DVec3::new(1.0, 2.0, 3.0));
+        assert_eq!(state.velocity, DVec3::ZERO);
+    }
+
+    #[test]
+    fn chains_to_the_barycenter() {
+        let kernel = fixture();
+        let epoch = ET0 + 1.5 * DAY;
+
+        let earth = kernel.barycentric_state(399, julian_date(epoch)).unwrap();
+        let (_, barycenter) = kernel.relative_state(3, epoch).unwrap();
+        let (_, relative) = kernel.relative_state(399, epoch).unwrap();
+
+        assert_close(earth.position, barycenter.position + relative.position);
+        assert_close(earth.velocity, barycenter.velocity + relative.velocity);
+    }
+
+    #[test]
+    fn rejects_uncovered_epochs() {
+        let kernel = fixture();
+
+        assert!(kernel
+            .barycentric_state(399, julian_date(ET0 - DAY))
+            .is_err());
+        assert!(kernel
+            .barycentric_state(399, julian_date(ET0 + 5.0 * DAY))
+            .is_err());
+        assert!(kernel.barycentric_state(301, julian_date(ET0)).is_err());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(SpkKernel::parse(vec![0; 2048]).is_err());
+        assert!(SpkKernel::open(Path::new("data/compiled_data.json")).is_err());
+    }
+
+    #[test]
+    fn seeds_dataset() {
+        use crate::simulation::{
+            data::{load_data, prepare_dataset, DATA_PATH},
+            settings::SimulationParameters,
+            units::ASTRONOMICAL_UNIT,
+        };
+
+        let kernel = fixture();
+        let epoch = julian_date(ET0 + DAY);
+
+        let mut dataset = load_data(Path::new(DATA_PATH)).unwrap();
+        let propagated = seed_dataset(&mut dataset, &kernel, epoch, UnitSystem::Astronomical);
+
+        assert_eq!(dataset.epoch, epoch);
+        assert!(propagated.iter().any(|n| n == "Moon"));
+        assert!(!propagated.iter().any(|n| n == "Earth" || n == "Sun"));
+
+        let earth = kernel.barycentric_state(399, epoch).unwrap();
+        let earth = ecliptic_to_world(earth.position * KILOMETER / ASTRONOMICAL_UNIT);
+
+        let parameters = SimulationParameters::default();
+        let dataset = prepare_dataset(dataset, &parameters);
+        let body = |name: &str| {
+            let body = dataset
+                .bodies
+                .iter()
+                .flat_map(|b| {
+                    std::iter::once(b.clone()).chain(
+                        b.satellites
+                            .iter()
+                            .flatten()
+                            .map(|s| s.read().unwrap().clone()),
+                    )
+                })
+                .find(|b| b.metadata.name.as_deref() == Some(name))
+                .unwrap();
+
+            body.data.position / parameters.unit_scale
+        };
+
+        assert!((body("Earth") - earth).length() < 1e-6);
+
+        // The Moon keeps to its orbit around wherever the kernel put the Earth
+        let distance = (body("Moon") - body("Earth")).length();
+        assert!((distance - 0.00257).abs() < 0.0002, "{}", distance);
+    }
+
+    /// The Earth and the Moon of a kernel laid out like DE440s, chained through the Earth-Moon barycenter,
+    /// have to match the Horizons states it was fitted to.
+    #[test]
+    fn matches_horizons() {
+        let kernel = SpkKernel::open(Path::new(EPHEMERIS)).unwrap();
+        let au = crate::simulation::units::ASTRONOMICAL_UNIT / KILOMETER;
+
+        for (id, response) in [(399, "Earth – 399"), (301, "Moon – 301")] {
+            let path = format!("data/responses/{}.txt", response);
+            let text = std::fs::read_to_string(path).unwrap();
+            let reference = crate::horizons::parse_response(&text).unwrap().vectors[0].states[0];
+
+            let body = kernel.barycentric_state(id, reference.julian_date).unwrap();
+            let sun = kernel.barycentric_state(10, reference.julian_date).unwrap();
+
+            let position = (body.position - sun.position) / au;
+            let velocity = (body.velocity - sun.velocity) * DAY / au;
+
+            assert!((position - reference.position).length() < 1e-9, "{}", id);
+            assert!((velocity - reference.velocity).length() < 1e-10, "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let bytes = std::fs::read(FIXTURE).unwrap();
+        let broken = |patches: &[(usize, &[u8])]| {
+            let mut bytes = bytes.clone();
+
+            for (offset, patch) in patches {
+                bytes[*offset..offset + patch.len()].copy_from_slice(patch);
+            }
+
+            SpkKernel::parse(bytes).unwrap_err()
+        };
+
+        // The summary record points to the last record, which points back to it
+        let error = broken(&[
+            (1024, &4.0f64.to_le_bytes()),
+            (3072, &[2.0f64.to_le_bytes(), [0; 8], [0; 8]].concat()),
+        ]);
+        assert!(error.contains("loop"), "{}", error);
+
+        let error = broken(&[(1040, &1e6f64.to_le_bytes())]);
+        assert!(error.contains("more than fit"), "{}", error);
+
+        // The first segment ends before there is room for its directory
+        let error = broken(&[(1084, &2i32.to_le_bytes())]);
+        assert!(error.contains("invalid addresses"), "{}", error);
+    }
+
+    #[test]
+    fn keeps_bodies_on_rails() {
+        use crate::simulation::{body::BodyData, units::ASTRONOMICAL_UNIT};
+        use bevy::ecs::system::RunSystemOnce;
+
+        let kernel = SpkKernel::open(Path::new(EPHEMERIS)).unwrap();
+        let epoch = julian_date(ET0);
+        let parameters = SimulationParameters::default();
+
+        let mut world = World::new();
+        world.insert_resource(Rails {
+            kernel: Some(kernel.clone()),
+        });
+        world.insert_resource(SimulationTime::new(epoch));
+        world.insert_resource(parameters.clone());
+
+        let body = |id| Body {
+            data: BodyData {
+                position: Vec3::ONE,
+                ..default()
+            },
+            metadata: crate::simulation::body::BodyMetadata { id, ..default() },
+            satellites: None,
+        };
+        let spawn = |world: &mut World, id| {
+            world
+                .spawn((body(id), Transform::from_translation(Vec3::ONE)))
+                .id()
+        };
+
+        let earth = spawn(&mut world, Some(399));
+        let mars = spawn(&mut world, Some(499));
+        let unknown = spawn(&mut world, None);
+
+        world.run_system_once(rails_system).unwrap();
+
+        let expected = kernel
+            .barycentric_state(399, epoch + tick_duration(&parameters))
+            .unwrap();
+        let state = world.get::<PhysicsState>(earth).unwrap();
+
+        // In the frame and units the simulation runs in
+        let scaled =
+            |v: DVec3, time: f64| DVec3::new(v.x, v.z, v.y) * KILOMETER * time / ASTRONOMICAL_UNIT;
+
+        assert!((state.position - scaled(expected.position, 1.0)).length() < 1e-12);
+        assert!((state.velocity - scaled(expected.velocity, DAY)).length() < 1e-12);
+        assert_eq!(
+            world.get::<Transform>(earth).unwrap().translation,
+            state.position.as_vec3()
+        );
+
+        // Integrated as usual
+        for entity in [mars, unknown] {
+            assert_eq!(
+                world.get::<Transform>(entity).unwrap().translation,
+                Vec3::ONE
+            );
+        }
+    }
+}
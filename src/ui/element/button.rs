@@ -5,7 +5,7 @@ use imgui::{ColorStackToken, StyleStackToken};
 
 use crate::ui::{apply_button_color, clear_button_color, UiColor};
 
-use super::{Border, Builder, Computed, ParentProperties, Size, UiElement, UiElementType, UiNode};
+use super::{Border, Builder, Computed, Size, UiElement, UiElementType, UiNode};
 
 #[derive(Debug, Clone)]
 pub struct Button {
@@ -102,14 +102,10 @@ impl UiNode for Button {
         let mut style_stack: Vec<StyleStackToken> = Vec::new();
         let mut color_stack: Vec<ColorStackToken> = Vec::new();
 
-        if self.border.is_some() {
-            style_stack.push(
-                context.push_style_var(imgui::StyleVar::FrameBorderSize(self.border.unwrap().size)),
-            );
+        if let Some(border) = self.border {
+            style_stack.push(context.push_style_var(imgui::StyleVar::FrameBorderSize(border.size)));
 
-            color_stack.push(
-                context.push_style_color(imgui::StyleColor::Border, self.border.unwrap().color),
-            );
+            color_stack.push(context.push_style_color(imgui::StyleColor::Border, border.color));
         }
 
         if context.button_with_size(self.label.clone(), [width, height]) {
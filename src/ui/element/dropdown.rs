@@ -21,7 +21,7 @@ thread_local! {
 }
 
 thread_local! {
-    static DROPDOWN_ID_INCR: AtomicUsize = AtomicUsize::new(0);
+    static DROPDOWN_ID_INCR: AtomicUsize = const { AtomicUsize::new(0) };
 }
 
 #[derive(PartialEq, Debug, Clone)]
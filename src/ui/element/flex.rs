@@ -64,6 +64,7 @@ pub struct Flex {
     computed_width: Option<f32>,
     computed_height: Option<f32>,
     computed_axis_available_space: Option<f32>,
+    #[allow(dead_code, reason = "overflow is not computed yet")]
     computed_overflow: ComputedOverflow,
     debug_id: Option<&'static str>,
 }
@@ -152,7 +153,7 @@ impl Computed for Flex {
             return;
         }
 
-        let border = self.border.and_then(|b| Some(b.size)).unwrap_or(0.0);
+        let border = self.border.map(|b| b.size).unwrap_or(0.0);
 
         let total_width = self.computed_width.unwrap();
         let available_width = total_width - border - (self.padding * 2.0);
@@ -388,7 +389,7 @@ impl Flex {
         assert!(self.computed_width.is_some(), "Computed width is unset.");
         assert!(self.computed_height.is_some(), "Computed height is unset.");
 
-        let border = self.border.and_then(|b| Some(b.size)).unwrap_or(0.0);
+        let border = self.border.map(|b| b.size).unwrap_or(0.0);
         let halfborder = border / 2.0;
 
         let width = self.computed_width.unwrap();
@@ -475,9 +476,7 @@ impl Flex {
                     }
                 }
 
-                let cursor_right_before_drawing_child = context.cursor_screen_pos();
-
-                child.build(context, &draw_list);
+                child.build(context, draw_list);
             }
         }
 
@@ -495,7 +494,7 @@ impl Flex {
         let width = self.computed_width.unwrap();
         let height = self.computed_height.unwrap();
 
-        let border = self.border.and_then(|b| Some(b.size)).unwrap_or(0.0);
+        let border = self.border.map(|b| b.size).unwrap_or(0.0);
         let halfborder = border / 2.0;
 
         let cursor = context.cursor_screen_pos();
@@ -609,7 +608,7 @@ impl Flex {
                     }
                 }
 
-                child.build(context, &draw_list);
+                child.build(context, draw_list);
             }
         }
 
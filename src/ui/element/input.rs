@@ -1,6 +1,5 @@
 use std::{
-    any::Any,
-    cell::{RefCell, RefMut},
+    cell::RefCell,
     collections::HashMap,
     fmt,
     sync::{
@@ -9,24 +8,20 @@ use std::{
     },
 };
 
-use bevy::{color::LinearRgba, scene::ron::value};
-use imgui::{ColorStackToken, StyleStackToken};
-
 use crate::ui::UiColor;
 
-use super::{
-    rect::Rect, Border, Builder, Computed, HasChildren, Size, UiElement, UiElementType, UiNode,
-};
+use super::{rect::Rect, Border, Builder, Computed, Size, UiElement, UiElementType, UiNode};
 
 thread_local! {
     static DROPDOWN_SELECTED_MAP: RefCell<HashMap<usize, i32>> = RefCell::new(HashMap::new());
 }
 
 thread_local! {
-    static DROPDOWN_ID_INCR: AtomicUsize = AtomicUsize::new(0);
+    static DROPDOWN_ID_INCR: AtomicUsize = const { AtomicUsize::new(0) };
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(dead_code, reason = "the builder only puts labels on top so far")]
 enum LabelPlacement {
     Left,
     Right,
@@ -119,7 +114,7 @@ impl InputI32 {
     }
 }
 
-impl<'a> Drop for InputI32 {
+impl Drop for InputI32 {
     fn drop(&mut self) {
         DROPDOWN_ID_INCR.with(|incr| {
             let _ = incr.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| {
@@ -133,7 +128,7 @@ impl<'a> Drop for InputI32 {
     }
 }
 
-impl<'a> Default for InputI32 {
+impl Default for InputI32 {
     fn default() -> Self {
         Self {
             id: InputI32::id_that_will_not_work_in_immediate_mode_oopsies(),
@@ -152,7 +147,7 @@ impl<'a> Default for InputI32 {
     }
 }
 
-impl<'a> Computed for InputI32 {
+impl Computed for InputI32 {
     fn get_computed_width(&self) -> Option<f32> {
         self.computed_width
     }
@@ -172,7 +167,7 @@ impl<'a> Computed for InputI32 {
     fn calculate_min_width(&self, context: &imgui::Ui) -> Option<f32> {
         match self.label_alignment {
             LabelPlacement::Left | LabelPlacement::Right => {
-                Some(context.calc_text_size(&self.label)[0] + Self::INPUT_FIELD_MIN_WIDTH)
+                Some(context.calc_text_size(self.label)[0] + Self::INPUT_FIELD_MIN_WIDTH)
             }
             LabelPlacement::Top => Some(Self::INPUT_FIELD_MIN_WIDTH),
         }
@@ -182,13 +177,13 @@ impl<'a> Computed for InputI32 {
         match self.label_alignment {
             LabelPlacement::Left | LabelPlacement::Right => Some(Self::INPUT_FIELD_MIN_HEIGHT),
             LabelPlacement::Top => {
-                Some(context.calc_text_size(&self.label)[1] + Self::INPUT_FIELD_MIN_HEIGHT)
+                Some(context.calc_text_size(self.label)[1] + Self::INPUT_FIELD_MIN_HEIGHT)
             }
         }
     }
 }
 
-impl<'a> UiNode for InputI32 {
+impl UiNode for InputI32 {
     fn get_width(&self) -> &Size {
         &self.width
     }
@@ -237,10 +232,6 @@ impl<'a> UiNode for InputI32 {
         );
 
         let ax = context.push_item_width(width);
-        let mut style_stack: Vec<StyleStackToken> = Vec::new();
-
-        let x = context.clone_style().frame_border_size;
-
         // style_stack.push(context.push_style_var(imgui::StyleVar::FramePadding([
         //     4.0,
         //     (height - context.current_font_size()) / 2.0 - x * 2.0,
@@ -248,11 +239,11 @@ impl<'a> UiNode for InputI32 {
 
         match self.label_alignment {
             LabelPlacement::Left => {
-                context.text(&self.label);
+                context.text(self.label);
                 context.same_line();
             }
             LabelPlacement::Top => {
-                context.text(&self.label);
+                context.text(self.label);
             }
             _ => {}
         }
@@ -54,12 +54,12 @@ pub trait Computed {
     fn set_computed_height(&mut self, new_height: f32);
 
     /// Calculates the minimum width of `self`.
-    fn calculate_min_width(&self, context: &imgui::Ui) -> Option<f32> {
+    fn calculate_min_width(&self, _context: &imgui::Ui) -> Option<f32> {
         None
     }
 
     /// Calculates the minimum height of `self`.
-    fn calculate_min_height(&self, context: &imgui::Ui) -> Option<f32> {
+    fn calculate_min_height(&self, _context: &imgui::Ui) -> Option<f32> {
         None
     }
 
@@ -70,8 +70,11 @@ pub trait Computed {
     /// It mutates the element's children.
     /// Do consider that the element's own sizes are calculated by its parent.
     /// TODO: actually, why is it not its own trait if it needs to be invoked by the root node?
-    fn compute_children_size(&mut self, context: &imgui::Ui, parent_properties: &ParentProperties) {
-        return;
+    fn compute_children_size(
+        &mut self,
+        _context: &imgui::Ui,
+        _parent_properties: &ParentProperties,
+    ) {
     }
 }
 
@@ -83,6 +86,10 @@ pub trait Builder {
     fn parent(&mut self) -> &mut impl UiNode;
 }
 
+#[allow(
+    dead_code,
+    reason = "children do not size themselves against their parent yet"
+)]
 pub struct ParentProperties<'a> {
     computed_width: Option<f32>,
     computed_height: Option<f32>,
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 
 use crate::ui::UiColor;
 
-use super::{Border, Computed, ParentProperties, Size, UiElement, UiElementType, UiNode};
+use super::{Border, Computed, Size, UiElement, UiElementType, UiNode};
 
 #[derive(Debug, Clone)]
 pub struct Rect {
@@ -124,7 +124,7 @@ impl UiNode for Rect {
         assert!(self.computed_width.is_some(), "Computed width is unset.");
         assert!(self.computed_height.is_some(), "Computed height is unset.");
 
-        let border = self.border.and_then(|b| Some(b.size)).unwrap_or(0.0);
+        let border = self.border.map(|b| b.size).unwrap_or(0.0);
         let halfborder = border / 2.0;
 
         let width = self.computed_width.unwrap();
@@ -24,6 +24,7 @@ impl<'a> RootBuilder<'a> {
     }
 }
 
+#[derive(Default)]
 pub struct RootNode {
     children: Vec<UiElement>,
 }
@@ -46,7 +47,7 @@ impl RootNode {
         let draw_list = context.get_window_draw_list();
 
         for child in &mut self.children {
-            child.build(&context, &draw_list /* Override::default() */);
+            child.build(context, &draw_list /* Override::default() */);
         }
 
         context.new_line();
@@ -1,15 +1,9 @@
-use std::{fmt, sync::Arc};
+use bevy::color::{Alpha, LinearRgba};
 
-use bevy::{
-    color::{Alpha, LinearRgba},
-    text::{self, cosmic_text::Color},
-};
-
-use crate::ui::{apply_button_color, clear_button_color, element::rect::Rect, UiColor};
+use crate::ui::{element::rect::Rect, UiColor};
 
 use super::{
-    flex::{FlexAxisAlign, FlexCrossAxisAlign},
-    Border, Builder, Computed, ParentProperties, Size, UiElement, UiElementType, UiNode,
+    flex::FlexCrossAxisAlign, Border, Builder, Computed, Size, UiElement, UiElementType, UiNode,
 };
 
 // NOTE: this seems like a place where Size::FitContent would be great
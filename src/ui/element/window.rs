@@ -1,15 +1,11 @@
-use bevy::{color::LinearRgba, log::error, log::warn};
-use imgui::Condition;
+use bevy::color::LinearRgba;
 
 use crate::ui::{
     util::{id, with_color_scheme},
     UiColor,
 };
 
-use super::{
-    flex::{Flex, FlexChild},
-    Border, Computed, ParentProperties, Size, UiElement, UiNode,
-};
+use super::{flex::Flex, Border, Computed, ParentProperties, Size, UiElement, UiNode};
 
 pub struct WindowBuilder<'a> {
     window: &'a mut UiWindow,
@@ -56,6 +52,7 @@ impl Default for WindowSize {
 pub struct UiWindow {
     title: String,
     title_bar: bool,
+    #[allow(dead_code, reason = "the window border is not drawn yet")]
     border: Border,
     displayed: bool,
     movable: bool,
@@ -91,11 +88,11 @@ impl UiWindow {
             // WindowDimension::Stretch => bevy_window_height,
         };
 
-        return (width, height);
+        (width, height)
     }
 
     fn compute_children_size(&mut self, context: &imgui::Ui) {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             panic!("Window with title '{}' has no children.", self.title);
         }
 
@@ -137,7 +134,7 @@ impl UiWindow {
 
 impl UiWindow {
     pub fn new(bevy_window: &bevy::window::Window, width: WindowSize, height: WindowSize) -> Self {
-        let (w, h) = Self::compute_initial_size(&bevy_window, width, height);
+        let (w, h) = Self::compute_initial_size(bevy_window, width, height);
 
         Self {
             children: Vec::new(),
@@ -270,8 +267,8 @@ impl UiWindow {
 
             let draw_list = context.get_window_draw_list();
 
-            for (_i, child) in self.children.iter_mut().enumerate() {
-                child.build(&context, &draw_list /* Override::default() */);
+            for child in self.children.iter_mut() {
+                child.build(context, &draw_list /* Override::default() */);
             }
 
             for style in style_stack {
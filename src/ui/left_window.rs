@@ -32,7 +32,7 @@ pub fn left_window_system(
     mut trajectories: ResMut<crate::simulation::trajectory::Trajectories>,
     mut selected_body: ResMut<crate::simulation::settings::SelectedBody>,
     mut parameters: ResMut<crate::simulation::settings::SimulationParameters>,
-    mut elapsed_time: ResMut<crate::simulation::settings::ElapsedTime>,
+    time: Res<crate::simulation::time::SimulationTime>,
     sun: Res<Sun>,
 ) {
     let bevy_window = windows.single();
@@ -64,8 +64,8 @@ pub fn left_window_system(
                 ui.checkbox("Border", unsafe { &mut BORDER });
                 ui.checkbox("H auto", unsafe { &mut HAUTO });
 
-                let items = vec!["Left", "Right", "Between" /* , "Stretch" */];
-                if let Some(cb) = ui.begin_combo(
+                let items = ["Left", "Right", "Between" /* , "Stretch" */];
+                if let Some(_cb) = ui.begin_combo(
                     "##Combo",
                     format!("Axis: {}", items[unsafe { ALIGN_AXIS_SELECT }]),
                 ) {
@@ -94,8 +94,8 @@ pub fn left_window_system(
                         }
                     }
                 }
-                let items2 = vec!["Start", "End", "Center", "Stretch"];
-                if let Some(cb) = ui.begin_combo(
+                let items2 = ["Start", "End", "Center", "Stretch"];
+                if let Some(_cb) = ui.begin_combo(
                     "##Combo2",
                     format!("Cross axis: {}", items2[unsafe { ALIGN_CROSS_AXIS_SELECT }]),
                 ) {
@@ -429,11 +429,8 @@ pub fn left_window_system(
                         .step(100)
                         .build();
 
-                    if ui.button_with_size("Calculate Trajectories", [200.0, 48.0]) {
-                        trajectory.calculated = true;
-                    } else {
-                        trajectory.calculated = false;
-                    }
+                    trajectory.calculated =
+                        ui.button_with_size("Calculate Trajectories", [200.0, 48.0]);
 
                     ui.same_line_with_spacing(0.0, 4.0);
 
@@ -517,7 +514,7 @@ pub fn left_window_system(
                     camera_transform.translation.z
                 ));
 
-                ui.text(format!("Time Passed: {:.2} days", elapsed_time.0));
+                ui.text(format!("Time Passed: {:.2} days", time.elapsed()));
             });
     });
 
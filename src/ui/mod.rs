@@ -5,12 +5,29 @@ use imgui::ImColor32;
 use mint::Vector4;
 use name_tag::{name_tag_setup_system, name_tag_update_system};
 use util::{active, hover, rgba};
-use window::{spawn_window::spawn_window_system, test_window::test_window_system};
+use window::{
+    approach_window::approach_window_system, calendar_window::calendar_window_system,
+    control_window::control_window_system, eclipse_window::eclipse_window_system,
+    export_window::export_window_system, info_window::info_window_system,
+    jump_window::jump_window_system, lagrange_window::lagrange_window_system,
+    recording_window::recording_window_system, scenario_window::scenario_window_system,
+    snapshot_window::snapshot_window_system, spawn_window::spawn_window_system,
+    test_window::test_window_system,
+};
 
 pub mod element;
 pub mod name_tag;
 // mod flex;
+// The first windows, no longer shown, kept until the element-based windows do everything they did
+#[allow(
+    dead_code,
+    static_mut_refs,
+    clippy::too_many_arguments,
+    clippy::type_complexity,
+    reason = "not registered, see above"
+)]
 mod left_window;
+#[allow(dead_code, reason = "not registered, see above")]
 mod right_window;
 // mod spawn_ui;
 mod util;
@@ -21,7 +38,6 @@ pub struct SimulationUiPlugin;
 impl Plugin for SimulationUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, performance_metrics_system)
-            .add_systems(PostStartup, name_tag_setup_system)
             .add_systems(
                 Update,
                 (
@@ -29,7 +45,19 @@ impl Plugin for SimulationUiPlugin {
                     // right_window_system,
                     spawn_window_system,
                     test_window_system,
-                    name_tag_update_system,
+                    control_window_system,
+                    info_window_system,
+                    eclipse_window_system,
+                    calendar_window_system,
+                    approach_window_system,
+                    lagrange_window_system,
+                    jump_window_system,
+                    scenario_window_system,
+                    snapshot_window_system,
+                    recording_window_system,
+                    export_window_system,
+                    name_tag_setup_system,
+                    name_tag_update_system.after(name_tag_setup_system),
                 ),
             );
     }
@@ -80,7 +108,7 @@ pub fn apply_button_color<'a>(
         ],
     ));
 
-    return color_stack;
+    color_stack
 }
 
 // TODO util
@@ -131,15 +159,15 @@ impl From<LinearRgba> for UiColor {
     }
 }
 
-impl Into<LinearRgba> for UiColor {
-    fn into(self) -> LinearRgba {
-        self.to_linear()
+impl From<UiColor> for LinearRgba {
+    fn from(val: UiColor) -> Self {
+        val.to_linear()
     }
 }
 
-impl Into<Vector4<f32>> for UiColor {
-    fn into(self) -> Vector4<f32> {
-        let c = self.to_linear();
+impl From<UiColor> for Vector4<f32> {
+    fn from(val: UiColor) -> Self {
+        let c = val.to_linear();
 
         Vector4 {
             x: c.red,
@@ -164,9 +192,9 @@ impl DerefMut for UiColor {
     }
 }
 
-impl Into<ImColor32> for UiColor {
-    fn into(self) -> ImColor32 {
-        let c = self.to_linear().to_f32_array();
+impl From<UiColor> for ImColor32 {
+    fn from(val: UiColor) -> Self {
+        let c = val.to_linear().to_f32_array();
         ImColor32::from_rgba_f32s(c[0], c[1], c[2], c[3])
     }
 }
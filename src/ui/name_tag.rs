@@ -2,11 +2,12 @@ use bevy::prelude::*;
 
 use crate::simulation::body::Body;
 
+/// The body a name tag belongs to.
 #[derive(Component)]
-pub struct NameTagId(u32);
+pub struct NameTag(Entity);
 
-pub fn name_tag_setup_system(mut commands: Commands, bodies: Query<&Body>) {
-    for body in bodies.iter() {
+pub fn name_tag_setup_system(mut commands: Commands, bodies: Query<(Entity, &Body), Added<Body>>) {
+    for (entity, body) in bodies.iter() {
         commands
             .spawn((
                 Node {
@@ -18,7 +19,7 @@ pub fn name_tag_setup_system(mut commands: Commands, bodies: Query<&Body>) {
                 Visibility::Hidden,
                 BackgroundColor(Color::BLACK.with_alpha(0.5)),
                 BorderColor(body.metadata.color),
-                NameTagId(body.metadata.id.unwrap()),
+                NameTag(entity),
             ))
             .with_children(|node| {
                 node.spawn((
@@ -37,15 +38,25 @@ pub fn name_tag_setup_system(mut commands: Commands, bodies: Query<&Body>) {
 const NAME_OFFSET_AU: f32 = 0.005;
 
 pub fn name_tag_update_system(
+    mut commands: Commands,
     window: Query<&Window>,
-    mut nodes: Query<(&mut Node, &ComputedNode, &mut Visibility, &NameTagId)>,
-    bodies: Query<(&Body, &Transform, &GlobalTransform)>,
+    mut nodes: Query<(Entity, &mut Node, &ComputedNode, &mut Visibility, &NameTag)>,
+    bodies: Query<&GlobalTransform, With<Body>>,
     cameras: Query<(&Camera, &GlobalTransform)>,
 ) {
-    let (camera, camera_transform) = cameras.single();
+    // Not there if the scenario could not be set up
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
     let window = window.single();
 
-    for (body, _transform, global_transform) in bodies.iter() {
+    for (tag, mut node, computed_node, mut visibility, name_tag) in nodes.iter_mut() {
+        // The body is gone
+        let Ok(global_transform) = bodies.get(name_tag.0) else {
+            commands.entity(tag).despawn_recursive();
+            continue;
+        };
+
         let transform = global_transform.translation();
 
         let on_viewport = camera.world_to_viewport(
@@ -54,25 +65,21 @@ pub fn name_tag_update_system(
         );
 
         if let Ok(coordinate) = on_viewport {
-            for (mut node, computed_node, mut visibility, id) in nodes.iter_mut() {
-                let size = computed_node.size();
-
-                if id.0 == body.metadata.id.unwrap() {
-                    if coordinate.x < 0.0
-                        || coordinate.x > window.width()
-                        || coordinate.y < 0.0
-                        || coordinate.y > window.height()
-                    {
-                        *visibility = Visibility::Hidden;
-                        continue;
-                    } else {
-                        *visibility = Visibility::Visible;
-                    }
+            let size = computed_node.size();
 
-                    node.left = Val::Px(coordinate.x - size.x / 2.0);
-                    node.top = Val::Px(coordinate.y - size.y);
-                }
+            if coordinate.x < 0.0
+                || coordinate.x > window.width()
+                || coordinate.y < 0.0
+                || coordinate.y > window.height()
+            {
+                *visibility = Visibility::Hidden;
+                continue;
+            } else {
+                *visibility = Visibility::Visible;
             }
+
+            node.left = Val::Px(coordinate.x - size.x / 2.0);
+            node.top = Val::Px(coordinate.y - size.y);
         }
     }
 }
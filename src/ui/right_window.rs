@@ -59,13 +59,8 @@ pub fn right_window_system(
                                 .unwrap_or("<unknown body>".to_string()),
                             entity,
                             match selected_body.entity {
-                                Some(se) =>
-                                    if se == entity {
-                                        "(selected)"
-                                    } else {
-                                        ""
-                                    },
-                                None => "",
+                                Some(se) if se == entity => "(selected)",
+                                _ => "",
                             }
                         ))
                         .build(ui)
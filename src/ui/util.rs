@@ -7,19 +7,19 @@ use super::DefaultColor;
 static INCR_ID: AtomicU32 = AtomicU32::new(0);
 
 pub fn id() -> String {
-    format!("##{}", INCR_ID.fetch_add(1, Ordering::SeqCst).to_string())
+    format!("##{}", INCR_ID.fetch_add(1, Ordering::SeqCst))
 }
 
 pub fn with_color_scheme(ui: &imgui::Ui, f: impl FnOnce()) {
-    let mut color_stack = Vec::new();
-
-    color_stack.push(ui.push_style_color(StyleColor::WindowBg, DefaultColor::BACKGROUND));
-    color_stack.push(ui.push_style_color(StyleColor::Button, DefaultColor::BUTTON));
-    color_stack.push(ui.push_style_color(StyleColor::ButtonHovered, DefaultColor::BUTTON_HOVER));
-    color_stack.push(ui.push_style_color(StyleColor::ButtonActive, DefaultColor::BUTTON_ACTIVE));
-    color_stack.push(ui.push_style_color(StyleColor::Border, DefaultColor::BORDER));
-    color_stack.push(ui.push_style_color(StyleColor::Text, DefaultColor::TEXT));
-    color_stack.push(ui.push_style_color(StyleColor::FrameBg, DefaultColor::INPUT));
+    let color_stack = vec![
+        ui.push_style_color(StyleColor::WindowBg, DefaultColor::BACKGROUND),
+        ui.push_style_color(StyleColor::Button, DefaultColor::BUTTON),
+        ui.push_style_color(StyleColor::ButtonHovered, DefaultColor::BUTTON_HOVER),
+        ui.push_style_color(StyleColor::ButtonActive, DefaultColor::BUTTON_ACTIVE),
+        ui.push_style_color(StyleColor::Border, DefaultColor::BORDER),
+        ui.push_style_color(StyleColor::Text, DefaultColor::TEXT),
+        ui.push_style_color(StyleColor::FrameBg, DefaultColor::INPUT),
+    ];
 
     f();
 
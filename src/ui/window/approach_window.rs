@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    approach::CloseApproaches, body::Body, settings::SimulationParameters, units::KILOMETER,
+};
+
+pub fn approach_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    query: Query<(&Body, Entity)>,
+    mut approaches: ResMut<CloseApproaches>,
+    parameters: Res<SimulationParameters>,
+    mut selected: Local<usize>,
+) {
+    let ui = context.ui();
+
+    let bodies: Vec<(Entity, String)> = query
+        .iter()
+        .map(|(body, entity)| {
+            (
+                entity,
+                body.metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown body>".to_string()),
+            )
+        })
+        .collect();
+
+    let names: Vec<&String> = bodies.iter().map(|(_, name)| name).collect();
+
+    // AU per day to km/s
+    let (length, _, time) = parameters.base_units();
+    let speed_scale = (length / time / KILOMETER) as f32;
+
+    ui.window("Close Approaches")
+        .size([560.0, 240.0], imgui::Condition::FirstUseEver)
+        .position([960.0, 480.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.combo_simple_string("##body", &mut selected, &names);
+            ui.same_line();
+
+            if ui.button("Track") {
+                if let Some((entity, _)) = bodies.get(*selected) {
+                    approaches.track(*entity);
+                }
+            }
+
+            for entity in approaches.tracked.clone() {
+                let name = bodies
+                    .iter()
+                    .find(|(e, _)| *e == entity)
+                    .map(|(_, name)| name.as_str())
+                    .unwrap_or("<despawned>");
+
+                ui.text(name);
+                ui.same_line();
+
+                if ui.button(format!("Untrack##{}", entity)) {
+                    approaches.untrack(entity);
+                }
+            }
+
+            ui.input_float("Threshold (AU)", &mut approaches.threshold)
+                .build();
+
+            if ui.button("Clear") {
+                approaches.clear();
+            }
+
+            ui.separator();
+
+            ui.columns(6, "approaches", true);
+
+            for header in ["Body", "Approaching", "JD", "AU", "Radii", "km/s"] {
+                ui.text(header);
+                ui.next_column();
+            }
+
+            ui.separator();
+
+            for approach in approaches.approaches.iter().rev() {
+                ui.text(&approach.body);
+                ui.next_column();
+                ui.text(&approach.other);
+                ui.next_column();
+                ui.text(format!("{:.4}", approach.time));
+                ui.next_column();
+                ui.text(format!("{:.6}", approach.distance));
+                ui.next_column();
+                ui.text(format!("{:.1}", approach.distance_in_radii));
+                ui.next_column();
+                ui.text(format!("{:.3}", approach.relative_speed * speed_scale));
+                ui.next_column();
+            }
+
+            ui.columns(1, "approaches", false);
+        });
+}
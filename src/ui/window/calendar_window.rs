@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{body::Body, calendar::Timeline};
+
+pub fn calendar_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    query: Query<(&Body, Entity)>,
+    mut timeline: ResMut<Timeline>,
+) {
+    let ui = context.ui();
+
+    let bodies: Vec<(Entity, String)> = query
+        .iter()
+        .map(|(body, entity)| {
+            (
+                entity,
+                body.metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown body>".to_string()),
+            )
+        })
+        .collect();
+
+    let names: Vec<&String> = bodies.iter().map(|(_, name)| name).collect();
+
+    let mut observer = timeline
+        .observer
+        .and_then(|o| bodies.iter().position(|(e, _)| *e == o))
+        .unwrap_or(0);
+
+    ui.window("Calendar")
+        .size([480.0, 240.0], imgui::Condition::FirstUseEver)
+        .position([480.0, 480.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if ui.combo_simple_string("Observer", &mut observer, &names) {
+                timeline.observer = bodies.get(observer).map(|(e, _)| *e);
+                timeline.clear();
+            }
+
+            if ui.button("Export CSV") {
+                let path = Path::new("timeline.csv");
+
+                match timeline.export_csv(path) {
+                    Ok(_) => info!("Exported the calendar to {}", path.display()),
+                    Err(e) => error!("Could not export the calendar: {}", e),
+                }
+            }
+
+            ui.same_line();
+
+            if ui.button("Clear") {
+                timeline.clear();
+            }
+
+            ui.separator();
+
+            for event in timeline.events.iter().rev() {
+                ui.text(format!(
+                    "JD {:.4}  {}: {} ({:.2}°)",
+                    event.time, event.kind, event.body, event.elongation
+                ));
+            }
+        });
+}
@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    conics::SphereOverlays,
+    frame::{barycentric_correction_system, FrameCenter, ReferenceFrame},
+    settings::{ForceLawKind, SimulationMode, SimulationParameters},
+    time::SimulationTime,
+    units::{DisplayUnits, DistanceUnit},
+};
+
+pub fn control_window_system(
+    mut commands: Commands,
+    mut context: NonSendMut<ImguiContext>,
+    mut parameters: ResMut<SimulationParameters>,
+    mut frame: ResMut<ReferenceFrame>,
+    mut display_units: ResMut<DisplayUnits>,
+    mut overlays: ResMut<SphereOverlays>,
+    time: Res<SimulationTime>,
+) {
+    let ui = context.ui();
+
+    ui.window("Controls")
+        .size([320.0, 240.0], imgui::Condition::FirstUseEver)
+        .position([0.0, 0.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.separator();
+            ui.text("Time");
+            ui.separator();
+
+            ui.text(format!("TDB: {}", time.current));
+            ui.text(format!("UTC: {}", time.current.tdb_to_utc()));
+            ui.text(format!("JD (TDB): {:.6}", time.julian_date()));
+            ui.text(format!("Elapsed: {:.4} days", time.elapsed()));
+
+            ui.separator();
+            ui.text("Simulation Mode");
+            ui.separator();
+
+            ui.radio_button("N-body", &mut parameters.mode, SimulationMode::NBody);
+            ui.radio_button(
+                "Patched conics",
+                &mut parameters.mode,
+                SimulationMode::PatchedConics,
+            );
+
+            ui.separator();
+            ui.text("Force Law");
+            ui.separator();
+
+            ui.radio_button(
+                "Newtonian",
+                &mut parameters.force_law,
+                ForceLawKind::Newtonian,
+            );
+            ui.radio_button("Plummer", &mut parameters.force_law, ForceLawKind::Plummer);
+            ui.radio_button("Yukawa", &mut parameters.force_law, ForceLawKind::Yukawa);
+            ui.radio_button("MOND", &mut parameters.force_law, ForceLawKind::Mond);
+            ui.radio_button(
+                "Power law",
+                &mut parameters.force_law,
+                ForceLawKind::PowerLaw,
+            );
+
+            match parameters.force_law {
+                ForceLawKind::Yukawa => {
+                    ui.input_float("Strength", &mut parameters.yukawa_strength)
+                        .build();
+                    ui.input_float("Range (AU)", &mut parameters.yukawa_range)
+                        .build();
+                    parameters.yukawa_range = parameters.yukawa_range.max(f32::MIN_POSITIVE);
+                }
+                ForceLawKind::Mond => {
+                    ui.input_float("a0 (AU/day^2)", &mut parameters.mond_acceleration)
+                        .display_format("%e")
+                        .build();
+                    parameters.mond_acceleration =
+                        parameters.mond_acceleration.max(f32::MIN_POSITIVE);
+                }
+                ForceLawKind::PowerLaw => {
+                    ui.input_float("Exponent", &mut parameters.power_law_exponent)
+                        .build();
+                    parameters.power_law_exponent =
+                        parameters.power_law_exponent.max(f32::MIN_POSITIVE);
+                }
+                _ => {}
+            }
+
+            ui.separator();
+            ui.text("Keep Centered On");
+            ui.separator();
+
+            ui.radio_button("Sun", &mut frame.center, FrameCenter::Sun);
+            ui.radio_button("Barycenter", &mut frame.center, FrameCenter::Barycenter);
+
+            // Turning it on shifts the bodies right away, turning it off only keeps the next scenario as it is
+            if ui.checkbox("Barycentric correction", &mut frame.barycentric_correction)
+                && frame.barycentric_correction
+            {
+                commands.run_system_cached(barycentric_correction_system);
+            }
+
+            ui.separator();
+            ui.text("Overlays");
+            ui.separator();
+
+            ui.checkbox("Hill spheres", &mut overlays.hill_sphere);
+            ui.checkbox("Spheres of influence", &mut overlays.sphere_of_influence);
+
+            ui.separator();
+            ui.text("Distance Units");
+            ui.separator();
+
+            ui.radio_button(
+                "Kilometers",
+                &mut display_units.distance,
+                DistanceUnit::Kilometers,
+            );
+            ui.radio_button(
+                "Astronomical Units",
+                &mut display_units.distance,
+                DistanceUnit::AstronomicalUnits,
+            );
+            ui.radio_button(
+                "Light-minutes",
+                &mut display_units.distance,
+                DistanceUnit::LightMinutes,
+            );
+        });
+}
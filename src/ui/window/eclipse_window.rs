@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{body::Body, eclipse::Eclipses};
+
+pub fn eclipse_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    query: Query<(&Body, Entity)>,
+    mut eclipses: ResMut<Eclipses>,
+) {
+    let ui = context.ui();
+
+    let bodies: Vec<(Entity, String)> = query
+        .iter()
+        .map(|(body, entity)| {
+            (
+                entity,
+                body.metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown body>".to_string()),
+            )
+        })
+        .collect();
+
+    let names: Vec<&String> = bodies.iter().map(|(_, name)| name).collect();
+
+    let mut observer = eclipses
+        .observer
+        .and_then(|o| bodies.iter().position(|(e, _)| *e == o))
+        .unwrap_or(0);
+
+    ui.window("Eclipses")
+        .size([480.0, 240.0], imgui::Condition::FirstUseEver)
+        .position([0.0, 480.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if ui.combo_simple_string("Observer", &mut observer, &names) {
+                eclipses.observer = bodies.get(observer).map(|(e, _)| *e);
+                eclipses.clear();
+            }
+
+            if ui.button("Export CSV") {
+                let path = Path::new("eclipses.csv");
+
+                match eclipses.export_csv(path) {
+                    Ok(_) => info!("Exported eclipses to {}", path.display()),
+                    Err(e) => error!("Could not export eclipses: {}", e),
+                }
+            }
+
+            ui.same_line();
+
+            if ui.button("Clear") {
+                eclipses.clear();
+            }
+
+            ui.separator();
+
+            for eclipse in eclipses.finished.iter().chain(eclipses.ongoing.values()) {
+                ui.text(format!(
+                    "{} ({}): {} by {}",
+                    eclipse.kind, eclipse.eclipse_type, eclipse.occulted, eclipse.occulter
+                ));
+                ui.text(format!(
+                    "    JD {:.4} - {}, maximum at {:.4}",
+                    eclipse.start,
+                    eclipse
+                        .end
+                        .map(|end| format!("{:.4}", end))
+                        .unwrap_or("ongoing".to_string()),
+                    eclipse.maximum
+                ));
+
+                if let Some(offset) = eclipse.known_eclipse_offset() {
+                    ui.text(format!("    {:+.3} days from the observed maximum", offset));
+                }
+            }
+        });
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    export::{ExportFormat, Exporter},
+    frame::FrameCenter,
+};
+
+pub fn export_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    mut exporter: ResMut<Exporter>,
+    mut path: Local<Option<String>>,
+    mut bodies: Local<String>,
+) {
+    let ui = context.ui();
+
+    let path = path.get_or_insert_with(|| exporter.settings.path.display().to_string());
+
+    ui.window("Export")
+        .size([320.0, 260.0], imgui::Condition::FirstUseEver)
+        .position([700.0, 560.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if exporter.is_exporting() {
+                ui.text(format!("Exporting to {}", exporter.settings.path.display()));
+                ui.text(format!("{} rows", exporter.rows));
+
+                if ui.button("Stop") {
+                    exporter.stop();
+                }
+
+                return;
+            }
+
+            ui.input_text("Path", path).build();
+            ui.input_text("Bodies", &mut bodies)
+                .hint("All, or a comma separated list")
+                .build();
+            ui.input_scalar("Interval (days)", &mut exporter.settings.interval)
+                .build();
+            exporter.settings.interval = exporter.settings.interval.max(0.0);
+
+            ui.text("Format");
+            ui.radio_button("CSV", &mut exporter.settings.format, ExportFormat::Csv);
+            ui.same_line();
+            ui.radio_button(
+                "JSON Lines",
+                &mut exporter.settings.format,
+                ExportFormat::JsonLines,
+            );
+
+            ui.text("Centered on");
+            ui.radio_button("Sun", &mut exporter.settings.center, FrameCenter::Sun);
+            ui.same_line();
+            ui.radio_button(
+                "Barycenter",
+                &mut exporter.settings.center,
+                FrameCenter::Barycenter,
+            );
+
+            if ui.button("Start") {
+                exporter.settings.path = PathBuf::from(path.as_str());
+                exporter.settings.bodies = bodies
+                    .split(',')
+                    .map(|b| b.trim().to_string())
+                    .filter(|b| !b.is_empty())
+                    .collect();
+
+                if let Err(e) = exporter.start() {
+                    exporter.error = Some(e);
+                }
+            }
+
+            if let Some(error) = exporter.error.as_ref() {
+                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+            }
+        });
+}
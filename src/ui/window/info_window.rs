@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+use imgui::CollapsingHeader;
+
+use crate::simulation::{
+    body::Body,
+    elements::OsculatingElements,
+    passage::{PassageHistory, PassageKind},
+    settings::SimulationParameters,
+    units::DisplayUnits,
+};
+
+pub fn info_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    windows: Query<&Window>,
+    query: Query<(&Body, &OsculatingElements, &PassageHistory, Entity)>,
+    parameters: Res<SimulationParameters>,
+    display_units: Res<DisplayUnits>,
+) {
+    let bevy_window = windows.single();
+    let width = bevy_window.resolution.physical_width() as f32;
+
+    let ui = context.ui();
+
+    ui.window("Orbits")
+        .size([width / 5.0, 480.0], imgui::Condition::FirstUseEver)
+        .position([width - width / 5.0, 0.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            for (body, elements, history, entity) in query.iter() {
+                let Some(primary) = elements.primary else {
+                    continue;
+                };
+
+                let name = |body: &Body| {
+                    body.metadata
+                        .name
+                        .clone()
+                        .unwrap_or("<unknown body>".to_string())
+                };
+
+                if !CollapsingHeader::new(format!("{}##{}", name(body), entity)).build(ui) {
+                    continue;
+                }
+
+                if let Ok((primary_body, _, _, _)) = query.get(primary) {
+                    ui.text(format!("Orbiting: {}", name(primary_body)));
+                }
+
+                ui.text(format!(
+                    "Semi-major axis: {}",
+                    display_units.distance(elements.semi_major_axis, &parameters)
+                ));
+                ui.text(format!("Eccentricity: {:.6}", elements.eccentricity));
+                ui.text(format!("Inclination: {:.4}°", elements.inclination));
+                ui.text(format!(
+                    "Longitude of ascending node: {:.4}°",
+                    elements.longitude_of_ascending_node
+                ));
+                ui.text(format!(
+                    "Argument of periapsis: {:.4}°",
+                    elements.argument_of_periapsis
+                ));
+                ui.text(format!("True anomaly: {:.4}°", elements.true_anomaly));
+                ui.text(format!("Mean anomaly: {:.4}°", elements.mean_anomaly));
+
+                if elements.period.is_finite() {
+                    ui.text(format!("Period: {:.4} days", elements.period));
+                } else {
+                    ui.text("Period: unbound");
+                }
+
+                if let Some(period) = history.measured_period(PassageKind::Periapsis) {
+                    ui.text(format!("Measured anomalistic period: {:.4} days", period));
+                }
+
+                if let Some(period) = history.measured_period(PassageKind::AscendingNode) {
+                    ui.text(format!("Measured draconic period: {:.4} days", period));
+                }
+
+                if let Some(period) = body
+                    .data
+                    .orbital_elements
+                    .and_then(|e| e.sidereal_orbit_period)
+                {
+                    ui.text(format!("Horizons sidereal period: {:.4} days", period));
+                }
+
+                if let Some(passage) = history.passages.last() {
+                    ui.text(format!(
+                        "Last passage: {} at JD {:.4}, {}",
+                        passage.kind,
+                        passage.time,
+                        display_units.distance(passage.distance, &parameters)
+                    ));
+                }
+            }
+        });
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    jump::DateJump,
+    time::{JulianDate, SimulationTime},
+};
+
+pub fn jump_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    mut jump: ResMut<DateJump>,
+    time: Res<SimulationTime>,
+    mut date: Local<String>,
+    mut invalid: Local<bool>,
+) {
+    let ui = context.ui();
+
+    ui.window("Jump to Date")
+        .size([320.0, 140.0], imgui::Condition::FirstUseEver)
+        .position([20.0, 560.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text(format!("Now: {} UTC", time.current.tdb_to_utc()));
+
+            ui.input_text("##date", &mut date)
+                .hint("2030-06-01 12:00 or JD2462653.5")
+                .build();
+            ui.same_line();
+
+            if ui.button("Jump") {
+                match JulianDate::parse(&date) {
+                    Some(target) => {
+                        *invalid = false;
+                        jump.jump_to(target);
+                    }
+                    None => *invalid = true,
+                }
+            }
+
+            if *invalid {
+                ui.text_colored(
+                    [1.0, 0.4, 0.4, 1.0],
+                    "Expected a UTC date or a Julian Date (TDB)",
+                );
+            }
+
+            let Some(target) = jump.target() else {
+                return;
+            };
+
+            imgui::ProgressBar::new(jump.progress)
+                .overlay_text(format!("{} UTC", target.tdb_to_utc()))
+                .build(ui);
+
+            if ui.button("Cancel") {
+                jump.cancel();
+            }
+        });
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    body::Body,
+    lagrange::{LagrangePoints, SpawnAtLagrangePointEvent},
+};
+
+pub fn lagrange_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    query: Query<(&Body, Entity)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut lagrange: ResMut<LagrangePoints>,
+    mut spawn: EventWriter<SpawnAtLagrangePointEvent>,
+) {
+    let ui = context.ui();
+
+    let mut bodies: Vec<(Entity, String, f32)> = query
+        .iter()
+        .map(|(body, entity)| {
+            (
+                entity,
+                body.metadata
+                    .name
+                    .clone()
+                    .unwrap_or("<unknown body>".to_string()),
+                body.data.mass,
+            )
+        })
+        .collect();
+
+    bodies.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut names = vec!["<none>".to_string()];
+    names.extend(bodies.iter().map(|(_, name, _)| name.clone()));
+
+    let index_of = |entity: Option<Entity>| {
+        entity
+            .and_then(|entity| bodies.iter().position(|(e, _, _)| *e == entity))
+            .map(|i| i + 1)
+            .unwrap_or(0)
+    };
+
+    let mut primary = index_of(lagrange.primary);
+    let mut secondary = index_of(lagrange.secondary);
+
+    ui.window("Lagrange Points")
+        .size([320.0, 200.0], imgui::Condition::FirstUseEver)
+        .position([0.0, 260.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            if ui.combo_simple_string("Primary", &mut primary, &names) {
+                lagrange.primary = primary.checked_sub(1).map(|i| bodies[i].0);
+            }
+
+            if ui.combo_simple_string("Secondary", &mut secondary, &names) {
+                lagrange.secondary = secondary.checked_sub(1).map(|i| bodies[i].0);
+            }
+
+            ui.separator();
+
+            if lagrange.points.is_none() {
+                ui.text("Select two orbiting bodies");
+                return;
+            }
+
+            ui.text("Spawn test body at");
+
+            for point in 0..5 {
+                if point > 0 {
+                    ui.same_line();
+                }
+
+                if ui.button(format!("L{}", point + 1)) {
+                    spawn.send(SpawnAtLagrangePointEvent { point });
+                }
+            }
+        });
+
+    let (Some(points), Ok((camera, camera_transform))) = (lagrange.points, cameras.get_single())
+    else {
+        return;
+    };
+
+    let draw_list = ui.get_foreground_draw_list();
+
+    for (i, point) in points.iter().enumerate() {
+        if let Ok(coordinate) = camera.world_to_viewport(camera_transform, *point) {
+            draw_list.add_text(
+                [coordinate.x + 4.0, coordinate.y - 16.0],
+                [0.1, 1.0, 0.1, 1.0],
+                format!("L{}", i + 1),
+            );
+        }
+    }
+}
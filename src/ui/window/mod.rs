@@ -1,4 +1,13 @@
+pub mod approach_window;
+pub mod calendar_window;
 pub mod control_window;
+pub mod eclipse_window;
+pub mod export_window;
 pub mod info_window;
+pub mod jump_window;
+pub mod lagrange_window;
+pub mod recording_window;
+pub mod scenario_window;
+pub mod snapshot_window;
 pub mod spawn_window;
 pub mod test_window;
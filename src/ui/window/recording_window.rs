@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::{
+    body::Body,
+    recording::{body_names, Playback, Recorder, Recording},
+    time::SimulationTime,
+};
+
+pub fn recording_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    mut recorder: ResMut<Recorder>,
+    mut playback: ResMut<Playback>,
+    time: Res<SimulationTime>,
+    bodies: Query<(Entity, &Body)>,
+    mut path: Local<String>,
+    mut error: Local<Option<String>>,
+) {
+    let ui = context.ui();
+
+    if path.is_empty() {
+        *path = "recordings/recording.bin".to_string();
+    }
+
+    ui.window("Recording")
+        .size([320.0, 240.0], imgui::Condition::FirstUseEver)
+        .position([700.0, 790.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.input_text("##path", &mut path).build();
+
+            ui.separator();
+
+            match recorder.is_recording() {
+                true => {
+                    if ui.button("Stop recording") {
+                        recorder.stop();
+                    }
+
+                    ui.same_line();
+                    ui.text(format!("{} frames", recorder.frames));
+                }
+                false => {
+                    ui.disabled(playback.is_active(), || {
+                        if ui.button("Record") {
+                            *error = recorder
+                                .start(Path::new(path.as_str()), &body_names(&bodies))
+                                .err();
+                        }
+                    });
+
+                    ui.same_line();
+                    ui.set_next_item_width(120.0);
+                    ui.input_scalar("Cadence (days)", &mut recorder.cadence)
+                        .build();
+                    recorder.cadence = recorder.cadence.max(0.0);
+                }
+            }
+
+            ui.separator();
+
+            if !playback.is_active() {
+                ui.disabled(recorder.is_recording(), || {
+                    if ui.button("Play back") {
+                        match Recording::load(Path::new(path.as_str())) {
+                            Ok(recording) => {
+                                *error = None;
+                                playback.start(recording, &body_names(&bodies));
+                            }
+                            Err(e) => *error = Some(e),
+                        }
+                    }
+                });
+            } else {
+                if ui.button(if playback.paused { "Play" } else { "Pause" }) {
+                    playback.paused = !playback.paused;
+                }
+
+                ui.same_line();
+
+                if ui.button("Reverse") {
+                    playback.speed = -playback.speed;
+                    playback.paused = false;
+                }
+
+                ui.same_line();
+
+                if ui.button("Stop") {
+                    playback.stop();
+                }
+
+                let last = playback.frame_count().saturating_sub(1) as f64;
+                let mut cursor = playback.cursor;
+
+                if ui.slider("##frame", 0.0, last, &mut cursor) {
+                    playback.cursor = cursor;
+                }
+
+                ui.input_scalar("Speed (frames/s)", &mut playback.speed)
+                    .build();
+
+                ui.text(format!(
+                    "Frame {:.0} of {}, {} TDB",
+                    playback.cursor + 1.0,
+                    last + 1.0,
+                    time.current
+                ));
+            }
+
+            if let Some(error) = error.as_ref() {
+                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+            }
+        });
+}
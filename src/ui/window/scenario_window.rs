@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::scenario::{PendingScenario, Scenario, SCENARIO_DIR};
+
+pub fn scenario_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    scenario: Res<Scenario>,
+    mut pending: ResMut<PendingScenario>,
+    mut paths: Local<Option<Vec<PathBuf>>>,
+    mut error: Local<Option<String>>,
+) {
+    let ui = context.ui();
+
+    let paths = paths.get_or_insert_with(|| Scenario::list(Path::new(SCENARIO_DIR)));
+
+    ui.window("Scenarios")
+        .size([320.0, 220.0], imgui::Condition::FirstUseEver)
+        .position([360.0, 560.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text(&scenario.name);
+
+            if !scenario.description.is_empty() {
+                ui.text_wrapped(&scenario.description);
+            }
+
+            ui.separator();
+
+            for path in paths.iter() {
+                let name = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or_default();
+
+                let _id = ui.push_id(name);
+
+                if ui.button("Load") {
+                    match Scenario::load(path) {
+                        Ok(scenario) => {
+                            *error = None;
+                            pending.0 = Some(scenario);
+                        }
+                        Err(e) => *error = Some(e),
+                    }
+                }
+
+                ui.same_line();
+
+                match scenario.path.as_deref() == Some(path.as_path()) {
+                    true => ui.text_colored([0.4, 1.0, 0.4, 1.0], name),
+                    false => ui.text(name),
+                }
+            }
+
+            if paths.is_empty() {
+                ui.text(format!("No scenarios in {}", SCENARIO_DIR));
+            }
+
+            if ui.button("Refresh") {
+                *paths = Scenario::list(Path::new(SCENARIO_DIR));
+            }
+
+            if let Some(error) = error.as_ref() {
+                ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+            }
+        });
+}
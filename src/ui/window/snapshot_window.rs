@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_mod_imgui::ImguiContext;
+
+use crate::simulation::snapshot::{SnapshotRequests, QUICK_SAVE_PATH};
+
+pub fn snapshot_window_system(
+    mut context: NonSendMut<ImguiContext>,
+    mut requests: ResMut<SnapshotRequests>,
+    mut path: Local<String>,
+) {
+    let ui = context.ui();
+
+    if path.is_empty() {
+        *path = "snapshots/snapshot.ron".to_string();
+    }
+
+    ui.window("Snapshots")
+        .size([320.0, 140.0], imgui::Condition::FirstUseEver)
+        .position([360.0, 790.0], imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.input_text("##path", &mut path).build();
+
+            if ui.button("Save") {
+                requests.save(PathBuf::from(path.as_str()));
+            }
+
+            ui.same_line();
+
+            if ui.button("Load") {
+                requests.load(PathBuf::from(path.as_str()));
+            }
+
+            ui.same_line();
+
+            if ui.button("Quick save (F5)") {
+                requests.save(PathBuf::from(QUICK_SAVE_PATH));
+            }
+
+            ui.same_line();
+
+            if ui.button("Quick load (F9)") {
+                requests.load(PathBuf::from(QUICK_SAVE_PATH));
+            }
+
+            match &requests.status {
+                Some(Ok(message)) => ui.text(message),
+                Some(Err(e)) => ui.text_colored([1.0, 0.4, 0.4, 1.0], e),
+                None => {}
+            }
+        });
+}
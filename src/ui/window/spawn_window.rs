@@ -5,7 +5,7 @@ use crate::{
     simulation::settings::SimulationParameters,
     ui::{
         element::{
-            flex::{self, FlexAxisAlign, FlexChild, FlexCrossAxisAlign},
+            flex::{self, FlexChild, FlexCrossAxisAlign},
             input::InputI32Child,
             text::TextChild,
             window::{
@@ -25,8 +25,11 @@ pub static IS_DEBUG_FLEX_CURSOR_CALLS: AtomicBool = AtomicBool::new(false);
 /// Whether or not we should display Flex elements that have no debug id.
 pub static IS_SHOW_UNNAMED_ELEMENTS: AtomicBool = AtomicBool::new(false);
 
+/// A cursor move made by a Flex element: the new position, the element's debug id, the caller and its line.
+pub type MoveCursorCall = ([f32; 2], Option<&'static str>, &'static str, u32);
+
 thread_local! {
-    pub static MOVE_CURSOR_CALLS: RefCell<Vec<([f32; 2], Option<&'static str>, &'static str, u32)>> = RefCell::new(Vec::new());
+    pub static MOVE_CURSOR_CALLS: RefCell<Vec<MoveCursorCall>> = const { RefCell::new(Vec::new()) };
 }
 
 pub fn test_window_system(
@@ -0,0 +1,370 @@
+//! Checks our ephemerides against reference state vectors from JPL Horizons.
+//!
+//! The references are Horizons vector tables, as returned by the API with `EPHEM_TYPE=VECTORS`,
+//! `OUT_UNITS=AU-D`, `REF_PLANE=ECLIPTIC` and `CSV_FORMAT=YES`. We run the simulation without a window
+//! from the epoch of our data to each reference epoch, and compare the state of every target body
+//! relative to its center body.
+
+use std::{fmt, fs, path::Path};
+
+use bevy::{math::DVec3, prelude::*};
+
+use crate::batch::{run_until, spawn_dataset, HeadlessPlugin};
+use crate::horizons::{parse_response, HorizonsBody, VectorTable};
+use crate::simulation::{
+    body::Body,
+    data::Dataset,
+    physics::PhysicsState,
+    settings::{Integrator, SimulationMode, SimulationParameters, UPDATE_FREQUENCY},
+    time::{JulianDate, SimulationTime},
+};
+
+/// Reference vectors at later epochs, generated by `make_references.py` in there or fetched by `data.py --validation`.
+pub const FIXTURE_DIR: &str = "data/validation";
+
+/// Whether a reference body is one of ours, by NAIF ID if both have one.
+fn matches(reference: &HorizonsBody, body: &Body) -> bool {
+    match (reference.id, body.metadata.id) {
+        (Some(id), Some(body_id)) => id == body_id,
+        _ => body.metadata.name.as_deref() == Some(reference.name.as_str()),
+    }
+}
+
+/// Reads the vector tables of every `.txt` file in `dir`.
+pub fn load_references(dir: &Path) -> Result<Vec<VectorTable>, String> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Could not read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+        .collect();
+
+    paths.sort();
+
+    let mut references = Vec::new();
+
+    for path in paths {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+        let response = parse_response(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        references.extend(response.vectors);
+    }
+
+    Ok(references)
+}
+
+/// How far off a body was from the reference at one epoch.
+#[derive(Debug, Clone)]
+pub struct StateError {
+    pub target: String,
+    pub center: String,
+
+    /// Julian Date (TDB)
+    pub julian_date: f64,
+
+    /// Days since the epoch of our data
+    pub elapsed: f64,
+
+    /// Distance from the reference position, AU
+    pub position: f64,
+
+    /// Difference from the reference velocity, AU / day
+    pub velocity: f64,
+
+    /// Reference distance of the target from the center, AU
+    pub distance: f64,
+}
+
+impl StateError {
+    /// Position error relative to the distance from the center.
+    pub fn relative_position(&self) -> f64 {
+        if self.distance == 0.0 {
+            return self.position;
+        }
+
+        self.position / self.distance
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub integrator: Integrator,
+
+    /// Days per integration step
+    pub step: f64,
+
+    pub errors: Vec<StateError>,
+
+    /// Reference targets or centers we have no body for
+    pub missing: Vec<String>,
+}
+
+impl ValidationReport {
+    /// The largest relative position error of each target.
+    pub fn worst_per_body(&self) -> Vec<&StateError> {
+        let mut worst: Vec<&StateError> = Vec::new();
+
+        for error in &self.errors {
+            match worst.iter_mut().find(|w| w.target == error.target) {
+                Some(w) if w.relative_position() < error.relative_position() => *w = error,
+                Some(_) => {}
+                None => worst.push(error),
+            }
+        }
+
+        worst
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?}, {} day steps", self.integrator, self.step)?;
+        writeln!(
+            f,
+            "{:<12} {:<10} {:>18} {:>10} {:>14} {:>14} {:>12}",
+            "Body", "Center", "JD", "Days", "Position (AU)", "Velocity (AU/d)", "Relative"
+        )?;
+
+        for error in self.worst_per_body() {
+            writeln!(
+                f,
+                "{:<12} {:<10} {:>18.6} {:>10.2} {:>14.3e} {:>14.3e} {:>12.3e}",
+                error.target,
+                error.center,
+                error.julian_date,
+                error.elapsed,
+                error.position,
+                error.velocity,
+                error.relative_position()
+            )?;
+        }
+
+        for missing in &self.missing {
+            writeln!(f, "{:<12} missing from our data", missing)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds an app that runs just the physics, without a window or any rendering,
+/// with every body of the dataset spawned and shifted into the barycentric frame.
+fn headless_app(dataset: &Dataset, parameters: SimulationParameters) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, HeadlessPlugin));
+
+    let world = app.world_mut();
+    world.insert_resource(parameters);
+    world.insert_resource(SimulationTime::new(dataset.epoch));
+    spawn_dataset(world, dataset);
+    world.run_schedule(Startup);
+
+    app
+}
+
+/// Runs `dataset` to every reference epoch, in order, and compares the states.
+/// `dataset` has to be in the units of `parameters`.
+pub fn validate(
+    dataset: &Dataset,
+    references: &[VectorTable],
+    parameters: &SimulationParameters,
+    integrator: Integrator,
+    step: f64,
+) -> ValidationReport {
+    // Make every physics update a single integration step of `step` days
+    let parameters = SimulationParameters {
+        integrator,
+        mode: SimulationMode::NBody,
+        time_step: (step * UPDATE_FREQUENCY as f64) as f32,
+        updates_per_step: 1.0,
+        ..*parameters
+    };
+
+    // Lengths in AU
+    let length = parameters.unit_scale as f64;
+
+    let mut app = headless_app(dataset, parameters);
+    let world = app.world_mut();
+
+    let mut report = ValidationReport {
+        integrator,
+        step,
+        errors: Vec::new(),
+        missing: Vec::new(),
+    };
+
+    let mut epochs: Vec<f64> = references
+        .iter()
+        .flat_map(|r| r.states.iter().map(|s| s.julian_date))
+        .collect();
+
+    // Like `world_to_ecliptic`, in the double precision the physics runs in
+    let ecliptic = |v: DVec3| DVec3::new(v.x, v.z, v.y);
+
+    epochs.sort_by(|a, b| a.total_cmp(b));
+    epochs.dedup();
+
+    let mut bodies = world.query::<(&PhysicsState, &Transform, &Body)>();
+
+    for reference in references {
+        for body in [&reference.target, &reference.center] {
+            let name = body.to_string();
+
+            if !bodies.iter(world).any(|(_, _, b)| matches(body, b))
+                && !report.missing.contains(&name)
+            {
+                report.missing.push(name);
+            }
+        }
+    }
+
+    for epoch in epochs {
+        run_until(world, JulianDate::new(epoch));
+
+        let time = *world.resource::<SimulationTime>();
+
+        for reference in references {
+            let mut state = |body: &HorizonsBody| {
+                bodies
+                    .iter(world)
+                    .find(|(_, _, b)| matches(body, b))
+                    .map(|(s, t, b)| s.synced(t.translation, b.data.velocity))
+                    .map(|s| (s.position, s.velocity))
+            };
+
+            let (Some(target), Some(center)) = (state(&reference.target), state(&reference.center))
+            else {
+                continue;
+            };
+
+            for expected in reference.states.iter().filter(|s| s.julian_date == epoch) {
+                let position = ecliptic(target.0 - center.0) * length;
+                let velocity = ecliptic(target.1 - center.1) * length;
+
+                report.errors.push(StateError {
+                    target: reference.target.to_string(),
+                    center: reference.center.to_string(),
+                    julian_date: epoch,
+                    elapsed: time.elapsed(),
+                    position: position.distance(expected.position),
+                    velocity: velocity.distance(expected.velocity),
+                    distance: expected.position.length(),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::simulation::data::{load_dataset, DATA_PATH};
+
+    /// The largest position error each integrator may have, relative to the distance from the center,
+    /// and the moons it falls out of phase with besides `FAST_MOONS`.
+    const TOLERANCES: [(Integrator, f64, &[&str]); 3] = [
+        (
+            Integrator::Euler,
+            2e-2,
+            &["Deimos", "Io", "Europa", "Ganymede"],
+        ),
+        (Integrator::Leapfrog, 2e-2, &["Deimos", "Io", "Europa"]),
+        (Integrator::RK4, 5e-3, &["Deimos"]),
+    ];
+
+    /// Moons that go around in less than a day, so in fewer than 100 steps.
+    /// No integrator keeps them in phase for a year.
+    const FAST_MOONS: [&str; 5] = ["Phobos", "Amalthea", "Thebe", "Adrastea", "Metis"];
+
+    /// How far off a moon that is out of phase may be, relative to its distance.
+    /// Still in its orbit, it is at most about twice that away.
+    const IN_ORBIT: f64 = 3.0;
+
+    /// Days per integration step, the smallest that keeps a year of them to a few seconds
+    const STEP: f64 = 0.01;
+
+    fn dataset(parameters: &SimulationParameters) -> Dataset {
+        load_dataset(Path::new(DATA_PATH), parameters).expect("could not load our data")
+    }
+
+    /// The vectors our data was compiled from must come out of loading it, up to f32 precision.
+    #[test]
+    fn initial_conditions_match_horizons() {
+        let parameters = SimulationParameters::default();
+        let references = load_references(Path::new("data/responses")).unwrap();
+        let report = validate(
+            &dataset(&parameters),
+            &references,
+            &parameters,
+            Integrator::default(),
+            STEP,
+        );
+
+        println!("{}", report);
+
+        assert!(report.missing.is_empty());
+        assert_eq!(report.errors.len(), references.len());
+
+        for error in &report.errors {
+            assert_eq!(error.elapsed, 0.0);
+            assert!(
+                error.relative_position() < 1e-6,
+                "{} is {:.3e} AU off",
+                error.target,
+                error.position
+            );
+            assert!(
+                error.velocity < 1e-8,
+                "{} is {:.3e} AU/day off",
+                error.target,
+                error.velocity
+            );
+        }
+    }
+
+    /// Every integrator has to stay within its tolerance of the references at later epochs,
+    /// and keep the moons it cannot follow for a year in their orbits.
+    #[test]
+    fn integrators_match_horizons() {
+        let parameters = SimulationParameters::default();
+        let dataset = dataset(&parameters);
+        let references = load_references(Path::new(FIXTURE_DIR)).unwrap();
+
+        assert!(
+            !references.is_empty(),
+            "No reference vectors in {}",
+            FIXTURE_DIR
+        );
+
+        for (integrator, tolerance, out_of_phase) in TOLERANCES {
+            let report = validate(&dataset, &references, &parameters, integrator, STEP);
+
+            println!("{}", report);
+
+            assert!(report.missing.is_empty());
+            assert_eq!(report.errors.len(), 2 * references.len());
+
+            for error in report.worst_per_body() {
+                let target = error.target.as_str();
+                let tolerance = if FAST_MOONS.contains(&target) || out_of_phase.contains(&target) {
+                    IN_ORBIT
+                } else {
+                    tolerance
+                };
+
+                assert!(
+                    error.relative_position() < tolerance,
+                    "{:?}: {} is {:.3e} AU off after {:.1} days",
+                    integrator,
+                    error.target,
+                    error.position,
+                    error.elapsed
+                );
+            }
+        }
+    }
+}